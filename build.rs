@@ -0,0 +1,87 @@
+//! Copies every exercise crate, as it was last committed, into
+//! `$OUT_DIR/pristine` for `lifetimekata reset` to embed. The working tree
+//! is no good for this, since it holds the learner's attempts; it's only
+//! used when there's no git history to read, as in a published package.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn main() {
+    let root = PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR").unwrap());
+    let out = PathBuf::from(std::env::var_os("OUT_DIR").unwrap()).join("pristine");
+    let _ = fs::remove_dir_all(&out);
+    fs::create_dir_all(&out).unwrap();
+
+    // Committing moves the branch, which changes the index, so that's what
+    // is watched rather than the exercises themselves.
+    let git = root.join(".git");
+    if git.is_dir() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        println!("cargo:rerun-if-changed=.git/index");
+    } else {
+        println!("cargo:rerun-if-changed=exercises");
+    }
+
+    match committed(&root) {
+        Some(files) => {
+            for (path, contents) in files {
+                write(&out.join(path), &contents);
+            }
+        }
+        None => copy(&root, &root.join("exercises"), &out),
+    }
+}
+
+/// Every file in an exercise crate, as git has it at `HEAD`.
+fn committed(root: &Path) -> Option<Vec<(String, Vec<u8>)>> {
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(root)
+            .args(args)
+            .output()
+            .ok()?;
+        output.status.success().then_some(output.stdout)
+    };
+    let listing = git(&["ls-tree", "-r", "-z", "--name-only", "HEAD", "exercises"])?;
+    let mut files = Vec::new();
+    for path in listing.split(|&byte| byte == 0) {
+        let path = String::from_utf8(path.to_vec()).ok()?;
+        if in_exercise(Path::new(&path)) {
+            let contents = git(&["show", &format!("HEAD:{path}")])?;
+            files.push((path, contents));
+        }
+    }
+    Some(files)
+}
+
+/// Copies the exercise crates under `dir` in the working tree into `out`.
+fn copy(root: &Path, dir: &Path, out: &Path) {
+    for entry in fs::read_dir(dir).unwrap().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if path.file_name().is_some_and(|name| name != "target") {
+                copy(root, &path, out);
+            }
+        } else {
+            let relative = path.strip_prefix(root).unwrap();
+            if in_exercise(relative) {
+                write(&out.join(relative), &fs::read(&path).unwrap());
+            }
+        }
+    }
+}
+
+/// Whether `path` is inside an exercise's crate, as opposed to its
+/// solutions or its chapter.
+fn in_exercise(path: &Path) -> bool {
+    path.components()
+        .nth(2)
+        .is_some_and(|part| part.as_os_str() == "exercise")
+}
+
+fn write(path: &Path, contents: &[u8]) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
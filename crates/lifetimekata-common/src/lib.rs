@@ -0,0 +1,91 @@
+//! What the exercises' tests have in common, so that they don't each keep
+//! their own copy: the sample texts they split up, a [`Tokenizer`] to split
+//! them with, and assertions about what a reference borrows from.
+
+pub mod texts;
+
+/// Something which splits text into tokens that borrow from it. The tokens
+/// live as long as the text, however long the tokenizer lives.
+pub trait Tokenizer {
+    fn tokenize<'text>(&self, text: &'text str) -> Vec<&'text str>;
+}
+
+/// Splits text at each single space, the way the exercises do; two spaces in
+/// a row give an empty word between them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Words;
+
+impl Tokenizer for Words {
+    fn tokenize<'text>(&self, text: &'text str) -> Vec<&'text str> {
+        text.split(' ').collect()
+    }
+}
+
+/// Splits text at each occurrence of a delimiter, leaving out a final empty
+/// token, like the `split` in chapter 2.
+#[derive(Debug, Clone, Copy)]
+pub struct Delimited<'delimiter>(pub &'delimiter str);
+
+impl Tokenizer for Delimited<'_> {
+    fn tokenize<'text>(&self, text: &'text str) -> Vec<&'text str> {
+        let mut tokens: Vec<&str> = text.split(self.0).collect();
+        if tokens.last() == Some(&"") {
+            tokens.pop();
+        }
+        tokens
+    }
+}
+
+/// Whether `part` lies inside `whole`'s memory, which is the only way a
+/// `&str` can have been sliced from it.
+pub fn borrows_from(part: &str, whole: &str) -> bool {
+    let whole = whole.as_bytes().as_ptr_range();
+    let part = part.as_bytes().as_ptr_range();
+    whole.start <= part.start && part.end <= whole.end
+}
+
+/// Asserts that every string in `parts` was sliced from `whole`, rather than
+/// from some other string with the same text in it.
+///
+/// ```
+/// use lifetimekata_common::assert_borrows_from;
+///
+/// let sentence = String::from("the sand");
+/// let words: Vec<&str> = sentence.split(' ').collect();
+/// assert_borrows_from!(words, sentence);
+/// ```
+#[macro_export]
+macro_rules! assert_borrows_from {
+    ($parts:expr, $whole:expr $(,)?) => {{
+        let whole: &str = &$whole;
+        for part in $parts.iter() {
+            let part: &str = part;
+            assert!(
+                $crate::borrows_from(part, whole),
+                "{:?} doesn't borrow from `{}`",
+                part,
+                stringify!($whole),
+            );
+        }
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tokenizers_split_the_way_the_exercises_do() {
+        assert_eq!(Words.tokenize("the hound and"), ["the", "hound", "and"]);
+        assert_eq!(Delimited(", ").tokenize("a, b, "), ["a", "b"]);
+    }
+
+    #[test]
+    fn equal_text_elsewhere_doesnt_count() {
+        let sentence = String::from("the sand");
+        let copy = String::from("sand");
+        assert!(borrows_from(&sentence[4..], &sentence));
+        assert!(!borrows_from(&copy, &sentence));
+        assert!(borrows_from(&sentence[8..], &sentence));
+    }
+}
@@ -0,0 +1,13 @@
+//! The sentences the exercises' tests are written around.
+
+/// The sentence compared with [`SURF_LOVED`] and [`SNOW`] in chapter 5.
+pub const SURF_HATED: &str = "I hate the surf and the sand.";
+
+/// [`SURF_HATED`], but loved.
+pub const SURF_LOVED: &str = "I love the surf and the sand.";
+
+/// [`SURF_LOVED`], but in the snow.
+pub const SNOW: &str = "I love the snow and the sand.";
+
+/// The sentence whose unique words are found in chapter 7.
+pub const HOUND_AND_FOX: &str = "the hound and the fox liked the son of the fox";
@@ -0,0 +1,126 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use lifetimekata_matcher::{Matcher, Pattern};
+use regex::Regex;
+use std::hint::black_box;
+
+/// Each pattern, next to a regex which matches the same candidates, so the
+/// two can be compared.
+const PATTERNS: [(&str, &str, &str); 2] = [
+    ("short", "abc(d|e).", r"^abc[de]."),
+    (
+        "long",
+        r"(GET|POST|PUT) /api/v\d/(users|posts|comments)/\d\d\d(\d|)\w.",
+        r"^(?:GET|POST|PUT) /api/v\d/(?:users|posts|comments)/\d\d\d\d?\w.",
+    ),
+];
+
+/// Builds `count` candidates, about half of which match the long pattern.
+fn candidates(count: usize) -> Vec<String> {
+    let methods = ["GET", "POST", "PUT", "HEAD"];
+    let resources = ["users", "posts", "comments", "likes"];
+    (0..count)
+        .map(|i| {
+            format!(
+                "{} /api/v{}/{}/{}x!",
+                methods[i % methods.len()],
+                i % 10,
+                resources[i / 7 % resources.len()],
+                i % 10_000,
+            )
+        })
+        .collect()
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, pattern, _) in PATTERNS {
+        group.bench_with_input(BenchmarkId::from_parameter(name), pattern, |b, pattern| {
+            b.iter(|| Pattern::parse(black_box(pattern)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn match_all(c: &mut Criterion) {
+    let owned = candidates(10_000);
+    let candidates: Vec<&str> = owned.iter().map(String::as_str).collect();
+
+    let mut group = c.benchmark_group("match_all");
+    for (name, pattern, regex) in PATTERNS {
+        let mut matcher = Matcher::parse(pattern).unwrap();
+        group.bench_function(BenchmarkId::new("matcher", name), |b| {
+            b.iter(|| {
+                matcher
+                    .match_all(black_box(&candidates).iter().copied())
+                    .len()
+            })
+        });
+
+        let regex = Regex::new(regex).unwrap();
+        group.bench_function(BenchmarkId::new("regex", name), |b| {
+            b.iter(|| {
+                black_box(&candidates)
+                    .iter()
+                    .filter(|candidate| regex.is_match(candidate))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Matching candidates which mostly don't start with the pattern's literal
+/// prefix, which `match_all` can reject without trying the tokens.
+fn reject_by_prefix(c: &mut Criterion) {
+    let owned = candidates(10_000);
+    let candidates: Vec<&str> = owned.iter().map(String::as_str).collect();
+    let mut matcher = Matcher::parse(r"PUT /api/v\d/(users|posts)").unwrap();
+
+    let mut group = c.benchmark_group("reject_by_prefix");
+    group.bench_function("match_all", |b| {
+        b.iter(|| {
+            matcher
+                .match_all(black_box(&candidates).iter().copied())
+                .len()
+        })
+    });
+    group.bench_function("match_str", |b| {
+        b.iter(|| {
+            black_box(&candidates)
+                .iter()
+                .map(|candidate| matcher.match_str(candidate).len())
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+/// `match_all` next to `par_match_all`, on enough candidates for spreading
+/// them across threads to pay off. Run with `--features rayon`.
+#[cfg(feature = "rayon")]
+fn par_match_all(c: &mut Criterion) {
+    let owned = candidates(1_000_000);
+    let candidates: Vec<&str> = owned.iter().map(String::as_str).collect();
+    let (_, pattern, _) = PATTERNS[1];
+    let mut matcher = Matcher::parse(pattern).unwrap();
+
+    let mut group = c.benchmark_group("par_match_all");
+    group.sample_size(10);
+    group.bench_function("match_all", |b| {
+        b.iter(|| {
+            matcher
+                .match_all(black_box(&candidates).iter().copied())
+                .len()
+        })
+    });
+    group.bench_function("par_match_all", |b| {
+        b.iter(|| matcher.par_match_all(black_box(&candidates)).len())
+    });
+    group.finish();
+}
+
+#[cfg(not(feature = "rayon"))]
+criterion_group!(benches, parse, match_all, reject_by_prefix);
+#[cfg(feature = "rayon")]
+criterion_group!(benches, parse, match_all, reject_by_prefix, par_match_all);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lifetimekata_matcher::{validate_pattern, Matcher};
+
+// The input is a pattern, then a NUL, then the candidates to match against
+// it, separated by NULs. Anything before the first NUL is only a pattern.
+fuzz_target!(|data: &str| {
+    let mut parts = data.split('\0');
+    let pattern = parts.next().unwrap_or_default();
+
+    let Some(mut matcher) = Matcher::new(pattern) else {
+        assert!(!validate_pattern(pattern));
+        return;
+    };
+    assert!(validate_pattern(pattern));
+
+    for candidate in parts {
+        let matches = matcher.match_string(candidate);
+        let tokens_matched = matches.len();
+        // Every matched piece is a slice of the candidate, one after the
+        // other, starting at the beginning.
+        let mut start = 0;
+        for (_, text) in matches {
+            assert_eq!(&candidate[start..start + text.len()], text);
+            start += text.len();
+        }
+        assert!(matcher.most_tokens_matched() >= tokens_matched);
+    }
+});
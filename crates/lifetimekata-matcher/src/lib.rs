@@ -0,0 +1,3278 @@
+//! The pattern matcher from the finale of LifetimeKata, so that it can be
+//! used and tested outside of the exercise.
+
+// Nothing here needs more than `alloc`, so the matcher can be used without
+// `std` by turning off the default `std` feature.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Range;
+use require_lifetimes::require_lifetimes;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MatcherToken<'a> {
+    /// This is just text without anything special. To match one of the
+    /// special characters, escape it like `\.`, or quote a whole run of text
+    /// like `\Q(a.b)\E`.
+    RawText(#[cfg_attr(feature = "serde", serde(borrow))] Cow<'a, str>),
+    /// This is when text could match any one of several alternatives.
+    /// It looks like `(one|two|three)`, where `one`, `two` or `three`
+    /// are the alternatives. Each alternative is itself a sequence of
+    /// tokens, so groups can be nested, as in `(ab.|c(d|e))`.
+    ///
+    /// Alternatives are tried in order, and an empty alternative always
+    /// matches, so `(abc|)` means "`abc`, or nothing at all".
+    OneOfText(#[cfg_attr(feature = "serde", serde(borrow))] Vec<Vec<MatcherToken<'a>>>),
+    /// This is when you're happy to accept any single character.
+    /// It looks like `.`
+    WildCard,
+    /// This is when you're happy to accept any single character of a
+    /// certain kind. It looks like `\d`, `\w` or `\s`.
+    Class(CharClass),
+    /// This is when the text matched by an earlier group has to appear
+    /// again. It looks like `\1` to `\9`, where groups are numbered by the
+    /// order of their `(`, starting from 1.
+    Backreference(usize),
+}
+
+/// The kinds of character a `MatcherToken::Class` can accept. These are
+/// Unicode-aware, so `\d` accepts `٣` as well as `3`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CharClass {
+    /// `\d`: a numeric character.
+    Digit,
+    /// `\w`: an alphanumeric character, or `_`.
+    Word,
+    /// `\s`: a whitespace character.
+    Whitespace,
+    /// `\D`: anything but a numeric character.
+    NotDigit,
+    /// `\W`: anything but an alphanumeric character or `_`.
+    NotWord,
+    /// `\S`: anything but a whitespace character.
+    NotWhitespace,
+}
+
+/// The characters with a special meaning in patterns. Any of them can be
+/// matched literally by putting a `\` in front of it.
+const SPECIAL_CHARS: [char; 5] = ['\\', '.', '(', ')', '|'];
+
+/// Why a pattern failed to parse, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatcherParseError {
+    /// What was wrong with the pattern.
+    pub kind: ParseErrorKind,
+    /// The bytes of the pattern which caused the error.
+    pub span: Range<usize>,
+}
+
+/// Why `Pattern::from_bytes` couldn't decode its input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeError {
+    /// The offset of the first byte which couldn't be decoded.
+    pub offset: usize,
+}
+
+/// Why `Pattern::then` or `Pattern::or` couldn't combine two patterns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComposeError {
+    /// The group a backreference would have had to refer to once the
+    /// groups in front of it were counted. Backreferences are written as a
+    /// single digit, so only groups 1 to 9 can be referred to.
+    pub group: usize,
+}
+
+/// The reasons a pattern can fail to parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A `(` is never closed.
+    UnclosedGroup,
+    /// A `)` does not close any group.
+    UnmatchedClose,
+    /// A `\` is the last character of the pattern, so there is nothing for
+    /// it to escape.
+    TrailingBackslash,
+    /// A backreference like `\3` refers to a group which hasn't been opened
+    /// by that point in the pattern.
+    UnknownGroup,
+    /// The pattern has more tokens than `ParseLimits::max_tokens`, counting
+    /// the tokens inside groups.
+    TooManyTokens { limit: usize },
+    /// A group, or the pattern itself, has more alternatives than
+    /// `ParseLimits::max_alternatives`.
+    TooManyAlternatives { limit: usize },
+    /// Groups are nested more deeply than `ParseLimits::max_depth`.
+    TooDeep { limit: usize },
+}
+
+/// A parsed pattern. Unlike a `Matcher`, a `Pattern` never changes once it
+/// has been parsed, so it can be shared between threads and used to match
+/// many candidates at the same time.
+///
+/// Two patterns are equal when they have the same tokens and options,
+/// however their text was written, so `a\.b` equals `a\Q.\Eb`.
+///
+/// A deserialized pattern is only accepted if its text parses into its
+/// tokens, so that it can't hold tokens which parsing would never produce.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "PatternData<'a>"))]
+pub struct Pattern<'a> {
+    /// This is the actual text of the pattern
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    text: Cow<'a, str>,
+    /// These are the alternatives the pattern is made of, like `abc` and
+    /// `def` in `abc|def`. Most patterns have just one.
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    branches: Vec<Vec<MatcherToken<'a>>>,
+    /// These change how the tokens match candidates.
+    options: MatcherOptions,
+}
+
+/// A `Pattern` as it is deserialized, before it has been checked.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct PatternData<'a> {
+    #[serde(borrow)]
+    text: Cow<'a, str>,
+    #[serde(borrow)]
+    branches: Vec<Vec<MatcherToken<'a>>>,
+    options: MatcherOptions,
+}
+
+/// Settings which change how a pattern matches candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct MatcherOptions {
+    /// Whether text should match regardless of case. Only ASCII letters
+    /// are compared this way, unless the `unicode-case` feature is on.
+    pub case_insensitive: bool,
+    /// Whether `.` matches `\n`. This is on by default; turn it off to keep
+    /// a `.` from running onto the next line of a multi-line candidate.
+    pub dot_matches_newline: bool,
+}
+
+/// Limits on how big a pattern may be, for parsing patterns which come from
+/// someone who can't be trusted to keep them reasonable. Going over a limit
+/// is a parse error.
+///
+/// By default, groups may be nested `MAX_DECODE_DEPTH` deep, since parsing
+/// and matching a group goes one level deeper into the stack. There is no
+/// default limit on tokens or alternatives, which only cost memory and
+/// time. Any limit can be lifted by setting it to `usize::MAX`, but that
+/// should only be done for patterns which are trusted.
+///
+/// Patterns have no repetition, like `*` or `{n}` in a regex, so there is
+/// no repetition count to limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// The most tokens a pattern may have, including those inside groups.
+    pub max_tokens: usize,
+    /// The most alternatives a single group, or the pattern's top level,
+    /// may have.
+    pub max_alternatives: usize,
+    /// How deeply groups may be nested inside each other.
+    pub max_depth: usize,
+}
+
+/// A `Pattern`, together with statistics about what it has matched.
+///
+/// Two matchers are equal when their patterns are, whatever they have
+/// matched since, so matchers can be used as keys in sets and maps.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Matcher<'a> {
+    /// This is the pattern being matched.
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pattern: Pattern<'a>,
+    /// This keeps track of how well candidates have matched so far.
+    stats: MatchStats,
+}
+
+/// One token of a pattern, and the part of a candidate that it matched.
+///
+/// The token is borrowed from the pattern for `'m`, and the matched text
+/// is borrowed from the candidate for `'c`, so a `TokenMatch` can't outlive
+/// either of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenMatch<'m, 'a, 'c> {
+    /// The token which matched.
+    pub token: &'m MatcherToken<'a>,
+    /// The part of the candidate that the token matched.
+    pub text: &'c str,
+    /// Where `text` is in the candidate, in bytes.
+    pub span: Range<usize>,
+}
+
+/// Everything that matched in one of the candidates given to `match_all`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchResult<'m, 'a, 'c> {
+    /// The candidate which was matched.
+    pub candidate: &'c str,
+    /// The tokens which matched, in order.
+    pub matches: Vec<TokenMatch<'m, 'a, 'c>>,
+}
+
+/// How matching a candidate went, token by token, as returned by
+/// `Matcher::explain`. Displaying it gives a readable trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchTrace<'m, 'a, 'c> {
+    /// The candidate which was matched.
+    pub candidate: &'c str,
+    /// The tokens which matched before matching stopped, in order.
+    pub matches: Vec<TokenMatch<'m, 'a, 'c>>,
+    /// The token which stopped matching, or `None` if every token matched.
+    pub failure: Option<MatchFailure<'m, 'a>>,
+}
+
+/// The token which a candidate failed to match, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchFailure<'m, 'a> {
+    /// The token which didn't match.
+    pub token: &'m MatcherToken<'a>,
+    /// The byte offset in the candidate where the token was tried.
+    pub offset: usize,
+}
+
+/// Several patterns which are matched against each candidate together, a bit
+/// like a very small `RegexSet`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct MatcherSet<'a> {
+    /// The patterns in the set, in the order they were given.
+    patterns: Vec<Pattern<'a>>,
+}
+
+/// How far one of the patterns in a `MatcherSet` got through a candidate.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SetMatch {
+    /// The index of the pattern within the set.
+    pub index: usize,
+    /// How many of the pattern's tokens matched.
+    pub tokens_matched: usize,
+    /// Whether every token matched, using up the whole candidate.
+    pub is_match: bool,
+}
+
+/// An iterator over the tokens of a pattern, made by `Pattern::iter` or
+/// `Matcher::iter`. The tokens of each branch come one branch after
+/// another; tokens nested inside groups aren't included.
+#[derive(Debug, Clone)]
+pub struct Tokens<'m, 'a> {
+    inner: core::iter::Flatten<core::slice::Iter<'m, Vec<MatcherToken<'a>>>>,
+}
+
+/// Something which looks at each token of a pattern in turn, as it is
+/// walked by `Pattern::walk` or `Matcher::walk`. Every method does nothing
+/// unless it is overridden, so a visitor only implements what it needs.
+///
+/// Groups are walked depth first: `enter_group`, then the tokens of each
+/// alternative with `next_alternative` between them, then `exit_group`.
+pub trait TokenVisitor<'a> {
+    /// Called for each `MatcherToken::RawText`.
+    fn visit_raw_text(&mut self, _text: &str) {}
+
+    /// Called for each `MatcherToken::WildCard`.
+    fn visit_wildcard(&mut self) {}
+
+    /// Called for each `MatcherToken::Class`.
+    fn visit_class(&mut self, _class: CharClass) {}
+
+    /// Called for each `MatcherToken::Backreference`.
+    fn visit_backreference(&mut self, _group: usize) {}
+
+    /// Called when a `MatcherToken::OneOfText` is reached, before any of
+    /// the tokens inside it.
+    fn enter_group(&mut self, _alternatives: &[Vec<MatcherToken<'a>>]) {}
+
+    /// Called between two alternatives of a group.
+    fn next_alternative(&mut self) {}
+
+    /// Called once all the tokens inside a group have been visited.
+    fn exit_group(&mut self) {}
+
+    /// Called between two branches of a pattern, like the `|` in `ab|cd`.
+    fn next_branch(&mut self) {}
+}
+
+/// Statistics about the candidates a `Matcher` has been given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchStats {
+    /// How many candidates have been matched against.
+    pub candidates_tried: usize,
+    /// How many candidates matched each token, indexed by the token's
+    /// position in whichever branch of the pattern matched best.
+    pub token_hits: Vec<usize>,
+    /// The most tokens that any one candidate has matched.
+    pub most_tokens_matched: usize,
+    /// The length in bytes of the longest matched part of any candidate.
+    pub longest_match: usize,
+}
+
+impl Default for MatcherOptions {
+    fn default() -> MatcherOptions {
+        MatcherOptions {
+            case_insensitive: false,
+            dot_matches_newline: true,
+        }
+    }
+}
+
+impl Default for ParseLimits {
+    fn default() -> ParseLimits {
+        ParseLimits {
+            max_tokens: usize::MAX,
+            max_alternatives: usize::MAX,
+            max_depth: MAX_DECODE_DEPTH,
+        }
+    }
+}
+
+impl MatchStats {
+    /// Creates empty statistics for a matcher with `token_count` tokens.
+    fn new(token_count: usize) -> MatchStats {
+        MatchStats {
+            candidates_tried: 0,
+            token_hits: vec![0; token_count],
+            most_tokens_matched: 0,
+            longest_match: 0,
+        }
+    }
+
+    /// Records a candidate which matched the first `tokens_matched` tokens,
+    /// covering `match_len` bytes of the candidate.
+    ///
+    /// Statistics read back with serde may have fewer `token_hits` than the
+    /// matcher has tokens, so hits past the end of them aren't counted.
+    fn record(&mut self, tokens_matched: usize, match_len: usize) {
+        self.candidates_tried += 1;
+        let counted = tokens_matched.min(self.token_hits.len());
+        for hits in &mut self.token_hits[..counted] {
+            *hits += 1;
+        }
+        self.most_tokens_matched = self.most_tokens_matched.max(tokens_matched);
+        self.longest_match = self.longest_match.max(match_len);
+    }
+}
+
+impl<'a> Pattern<'a> {
+    /// Parses `text` into a pattern, or returns `None` if it is malformed.
+    pub fn new(text: &'a str) -> Option<Pattern<'a>> {
+        Pattern::parse(text).ok()
+    }
+
+    /// Parses `text` into a pattern, explaining what is wrong with it if it
+    /// is malformed.
+    pub fn parse(text: &'a str) -> Result<Pattern<'a>, MatcherParseError> {
+        Pattern::with_options(text, MatcherOptions::default())
+    }
+
+    /// Like `parse`, but the pattern will match according to `options`.
+    pub fn with_options(
+        text: &'a str,
+        options: MatcherOptions,
+    ) -> Result<Pattern<'a>, MatcherParseError> {
+        Pattern::with_limits(text, options, ParseLimits::default())
+    }
+
+    /// Like `with_options`, but fails to parse any pattern which goes over
+    /// one of `limits`.
+    pub fn with_limits(
+        text: &'a str,
+        options: MatcherOptions,
+        limits: ParseLimits,
+    ) -> Result<Pattern<'a>, MatcherParseError> {
+        let mut parser = Parser::new(text, false, limits);
+        let branches = parser.parse_branches()?;
+
+        Ok(Pattern {
+            text: Cow::Borrowed(text),
+            branches,
+            options,
+        })
+    }
+
+    /// Parses as much of `text` as possible, skipping over anything which is
+    /// malformed, and returns every error found along the way. A group which
+    /// is never closed is closed at the end of the pattern, and a group
+    /// nested too deeply is skipped along with everything inside it.
+    pub fn parse_lossy(text: &'a str) -> (Pattern<'a>, Vec<MatcherParseError>) {
+        let mut parser = Parser::new(text, true, ParseLimits::default());
+        let branches = parser
+            .parse_branches()
+            .expect("a lossy parse records errors instead of returning them");
+
+        let pattern = Pattern {
+            text: Cow::Borrowed(text),
+            branches,
+            options: MatcherOptions::default(),
+        };
+        (pattern, parser.errors)
+    }
+
+    /// The tokens of this pattern's first branch. Unless the pattern has a
+    /// `|` outside of any group, those are all of its tokens.
+    pub fn tokens(&self) -> &[MatcherToken<'a>] {
+        &self.branches[0]
+    }
+
+    /// The alternatives this pattern was parsed into, which are separated by
+    /// a `|` outside of any group.
+    pub fn branches(&self) -> &[Vec<MatcherToken<'a>>] {
+        &self.branches
+    }
+
+    /// The text every match has to start with, if the pattern has just one
+    /// branch and it starts with some text. A candidate which doesn't start
+    /// with this can't match any tokens at all.
+    ///
+    /// This borrows from the pattern, like `text`, since the text may not
+    /// be borrowed from the original string any more.
+    pub fn literal_prefix(&self) -> Option<&str> {
+        match self.branches.as_slice() {
+            [tokens] => match tokens.first()? {
+                MatcherToken::RawText(text) if !text.is_empty() => Some(text),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Matches `candidate` for `match_all` and `par_match_all`. Candidates
+    /// which don't start with the literal prefix are rejected straight away,
+    /// without going through the tokens.
+    fn match_candidate<'m, 'c>(&'m self, candidate: &'c str) -> MatchResult<'m, 'a, 'c> {
+        let rejected = !self.options.case_insensitive
+            && self
+                .literal_prefix()
+                .is_some_and(|prefix| !candidate.starts_with(prefix));
+        MatchResult {
+            candidate,
+            matches: if rejected {
+                vec![]
+            } else {
+                self.match_spans(candidate)
+            },
+        }
+    }
+
+    /// Iterates over the tokens of every branch of this pattern.
+    pub fn iter(&self) -> Tokens<'_, 'a> {
+        Tokens {
+            inner: self.branches.iter().flatten(),
+        }
+    }
+
+    /// Shows every token of this pattern to `visitor`, including the
+    /// tokens inside groups.
+    pub fn walk(&self, visitor: &mut impl TokenVisitor<'a>) {
+        for (i, tokens) in self.branches.iter().enumerate() {
+            if i > 0 {
+                visitor.next_branch();
+            }
+            walk_tokens(tokens, visitor);
+        }
+    }
+
+    /// The text this pattern was parsed from.
+    ///
+    /// This borrows from the pattern rather than the original string, since
+    /// after `into_owned` the pattern keeps its own copy of the text.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Matches `string` against this pattern, returning each token that
+    /// matched along with the part of `string` it matched.
+    pub fn match_str<'b, 'c>(&'b self, string: &'c str) -> Vec<(&'b MatcherToken<'a>, &'c str)> {
+        self.match_spans(string)
+            .into_iter()
+            .map(|token_match| (token_match.token, token_match.text))
+            .collect()
+    }
+
+    /// Like `match_str`, but also says where in `string` each token matched.
+    ///
+    /// If the pattern has several branches, this is what the best of them
+    /// matched: see `best_branch_match`.
+    pub fn match_spans<'m, 'c>(&'m self, string: &'c str) -> Vec<TokenMatch<'m, 'a, 'c>> {
+        self.best_branch_match(string).1
+    }
+
+    /// Matches `string` against every branch, returning the index of the
+    /// one which matched best along with what it matched. A branch whose
+    /// tokens all matched beats one which didn't; after that, the branch
+    /// which matched the most tokens wins, and the earliest one on a tie.
+    fn best_branch_match<'m, 'c>(
+        &'m self,
+        string: &'c str,
+    ) -> (usize, Vec<TokenMatch<'m, 'a, 'c>>) {
+        let group_count = self
+            .branches
+            .iter()
+            .flatten()
+            .map(MatcherToken::group_count);
+        let mut state = MatchState {
+            candidate: string,
+            options: self.options,
+            groups: vec![None; group_count.sum()],
+        };
+        let rank = |branch: usize, answer: &[TokenMatch]| {
+            (answer.len() == self.branches[branch].len(), answer.len())
+        };
+
+        // Groups are numbered across the whole pattern, so the groups of
+        // each branch are numbered after those of the branches before it.
+        let mut group = 1;
+        let mut best: Option<(usize, Vec<TokenMatch>)> = None;
+        for (branch, tokens) in self.branches.iter().enumerate() {
+            state.groups.fill(None);
+            let answer = Pattern::match_branch(tokens, &mut state, group);
+            group += tokens.iter().map(MatcherToken::group_count).sum::<usize>();
+            if best
+                .as_ref()
+                .is_none_or(|(best, best_answer)| rank(branch, &answer) > rank(*best, best_answer))
+            {
+                best = Some((branch, answer));
+            }
+        }
+        best.expect("a pattern always has at least one branch")
+    }
+
+    /// Matches the tokens of one branch against the candidate in `state`,
+    /// stopping at the first token which doesn't match. The first group in
+    /// the branch is numbered `group`.
+    fn match_branch<'m, 'c>(
+        tokens: &'m [MatcherToken<'a>],
+        state: &mut MatchState<'c>,
+        mut group: usize,
+    ) -> Vec<TokenMatch<'m, 'a, 'c>> {
+        let mut start = 0;
+        let mut answer = vec![];
+
+        // Even once the candidate is used up, later tokens may still match
+        // nothing at all (like `(abc|)`), so keep going until one fails.
+        for token in tokens {
+            let Some(len) = token.match_len(state, start, group) else {
+                break;
+            };
+            group += token.group_count();
+            let span = start..start + len;
+            answer.push(TokenMatch {
+                token,
+                text: &state.candidate[span.clone()],
+                span,
+            });
+            start += len;
+        }
+
+        answer
+    }
+
+    /// How many tokens are in the longest branch.
+    fn token_count(&self) -> usize {
+        self.branches.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Matches every string in `candidates`, spreading the work across
+    /// rayon's thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_match_all<'m, 'c>(&'m self, candidates: &[&'c str]) -> Vec<MatchResult<'m, 'a, 'c>> {
+        use rayon::prelude::*;
+
+        candidates
+            .par_iter()
+            .map(|&candidate| self.match_candidate(candidate))
+            .collect()
+    }
+
+    /// Copies any borrowed text into the pattern, so that it no longer
+    /// depends on the string it was parsed from.
+    pub fn into_owned(self) -> Pattern<'static> {
+        Pattern {
+            text: Cow::Owned(self.text.into_owned()),
+            branches: self
+                .branches
+                .into_iter()
+                .map(|tokens| tokens.into_iter().map(MatcherToken::into_owned).collect())
+                .collect(),
+            options: self.options,
+        }
+    }
+
+    /// Encodes this pattern as compact bytes, which `from_bytes` can turn
+    /// back into the same pattern without parsing it again.
+    ///
+    /// After a version byte come the pattern's text, a byte of option flags
+    /// and its branches. Each branch is a sequence: a count, then that many
+    /// tokens, each a tag byte followed by the token's contents. Counts,
+    /// lengths and numbers are LEB128 varints, and text is a length followed
+    /// by UTF-8.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoder = Encoder {
+            bytes: vec![FORMAT_VERSION],
+        };
+        encoder.str(&self.text);
+        let flags = u8::from(self.options.case_insensitive)
+            | u8::from(self.options.dot_matches_newline) << 1;
+        encoder.bytes.push(flags);
+        encoder.varint(self.branches.len());
+        for tokens in &self.branches {
+            encoder.sequence(tokens);
+        }
+        encoder.bytes
+    }
+
+    /// Decodes a pattern written by `to_bytes`.
+    ///
+    /// No text is copied: the pattern's text and every `RawText` token
+    /// borrow from `bytes`, which is why the pattern can't outlive them.
+    ///
+    /// Groups nested more than `MAX_DECODE_DEPTH` deep are an error, as
+    /// when parsing, so that bytes from anywhere can be decoded without
+    /// running out of stack; `from_bytes_with_limits` can allow more.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Pattern<'a>, DecodeError> {
+        Pattern::from_bytes_with_limits(bytes, ParseLimits::default())
+    }
+
+    /// Like `from_bytes`, but fails to decode any pattern which goes over
+    /// one of `limits`, as `with_limits` would fail to parse it.
+    pub fn from_bytes_with_limits(
+        bytes: &'a [u8],
+        limits: ParseLimits,
+    ) -> Result<Pattern<'a>, DecodeError> {
+        let mut decoder = Decoder {
+            bytes,
+            position: 0,
+            limits,
+            tokens: 0,
+            groups: 0,
+            depth: 0,
+        };
+        if decoder.byte()? != FORMAT_VERSION {
+            return Err(DecodeError { offset: 0 });
+        }
+        let text = decoder.str()?;
+
+        let flags_at = decoder.position;
+        let flags = decoder.byte()?;
+        if flags & !0b11 != 0 {
+            return Err(DecodeError { offset: flags_at });
+        }
+        let options = MatcherOptions {
+            case_insensitive: flags & 1 != 0,
+            dot_matches_newline: flags & 2 != 0,
+        };
+
+        let branches_at = decoder.position;
+        let count = decoder.varint()?;
+        if count > limits.max_alternatives {
+            return Err(DecodeError {
+                offset: branches_at,
+            });
+        }
+        let mut branches = vec![];
+        for _ in 0..count {
+            branches.push(decoder.sequence()?);
+        }
+        if branches.is_empty() {
+            return Err(DecodeError {
+                offset: branches_at,
+            });
+        }
+        if decoder.position != bytes.len() {
+            return Err(decoder.error());
+        }
+
+        Ok(Pattern {
+            text: Cow::Borrowed(text),
+            branches,
+            options,
+        })
+    }
+
+    /// A pattern which matches this pattern, then `other` straight after.
+    ///
+    /// The new pattern keeps this pattern's options. Its text is written out
+    /// from its tokens, since it wasn't parsed from any one string, so this
+    /// fails if a backreference in `other` would end up referring to a group
+    /// after the ninth, which can't be written.
+    pub fn then(self, other: Pattern<'a>) -> Result<Pattern<'a>, ComposeError> {
+        let options = self.options;
+        let mut tokens = self.into_sequence(0);
+        let groups = tokens.iter().map(MatcherToken::group_count).sum();
+        tokens.extend(other.into_sequence(groups));
+        Pattern::from_branches(vec![tokens], options)
+    }
+
+    /// A pattern which matches either this pattern or `other`, as if they
+    /// were written with a `|` between them.
+    ///
+    /// The new pattern keeps this pattern's options, and fails to be made
+    /// for the same reason as with `then`.
+    pub fn or(self, other: Pattern<'a>) -> Result<Pattern<'a>, ComposeError> {
+        let groups = self.group_count();
+        let mut branches = self.branches;
+        branches.extend(other.branches.into_iter().map(|mut tokens| {
+            shift_backreferences(&mut tokens, groups);
+            tokens
+        }));
+        Pattern::from_branches(branches, self.options)
+    }
+
+    /// Builds a pattern out of tokens which weren't parsed from one string,
+    /// as long as its text can be written so that it parses back into them.
+    fn from_branches(
+        branches: Vec<Vec<MatcherToken<'a>>>,
+        options: MatcherOptions,
+    ) -> Result<Pattern<'a>, ComposeError> {
+        let group = branches
+            .iter()
+            .map(|tokens| highest_backreference(tokens))
+            .max()
+            .unwrap_or(0);
+        if group > 9 {
+            return Err(ComposeError { group });
+        }
+        let mut pattern = Pattern {
+            text: Cow::Borrowed(""),
+            branches,
+            options,
+        };
+        pattern.text = Cow::Owned(format!("{pattern}"));
+        Ok(pattern)
+    }
+
+    /// How many groups there are in the whole pattern.
+    fn group_count(&self) -> usize {
+        self.branches
+            .iter()
+            .flatten()
+            .map(MatcherToken::group_count)
+            .sum()
+    }
+
+    /// Turns this pattern into one sequence of tokens, to go after
+    /// `groups_before` other groups. A pattern with several branches
+    /// becomes a single group, which comes before the groups inside it.
+    fn into_sequence(self, groups_before: usize) -> Vec<MatcherToken<'a>> {
+        let mut branches = self.branches;
+        let mut tokens = if branches.len() == 1 {
+            branches.remove(0)
+        } else {
+            for tokens in &mut branches {
+                shift_backreferences(tokens, 1);
+            }
+            vec![MatcherToken::OneOfText(branches)]
+        };
+        shift_backreferences(&mut tokens, groups_before);
+        tokens
+    }
+}
+
+/// The highest group any backreference in `tokens` refers to, or 0 if there
+/// are none.
+fn highest_backreference(tokens: &[MatcherToken<'_>]) -> usize {
+    tokens
+        .iter()
+        .map(|token| match token {
+            MatcherToken::Backreference(group) => *group,
+            MatcherToken::OneOfText(alternatives) => alternatives
+                .iter()
+                .map(|tokens| highest_backreference(tokens))
+                .max()
+                .unwrap_or(0),
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Shows each of `tokens` to `visitor`, and everything inside them.
+fn walk_tokens<'a>(tokens: &[MatcherToken<'a>], visitor: &mut impl TokenVisitor<'a>) {
+    for token in tokens {
+        match token {
+            MatcherToken::RawText(text) => visitor.visit_raw_text(text),
+            MatcherToken::WildCard => visitor.visit_wildcard(),
+            MatcherToken::Class(class) => visitor.visit_class(*class),
+            MatcherToken::Backreference(group) => visitor.visit_backreference(*group),
+            MatcherToken::OneOfText(alternatives) => {
+                visitor.enter_group(alternatives);
+                for (i, tokens) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        visitor.next_alternative();
+                    }
+                    walk_tokens(tokens, visitor);
+                }
+                visitor.exit_group();
+            }
+        }
+    }
+}
+
+/// Renumbers every backreference in `tokens` to refer to the group `by`
+/// groups later, for when `by` groups are put in front of `tokens`.
+fn shift_backreferences(tokens: &mut [MatcherToken<'_>], by: usize) {
+    for token in tokens {
+        match token {
+            MatcherToken::Backreference(group) => *group += by,
+            MatcherToken::OneOfText(alternatives) => {
+                for tokens in alternatives {
+                    shift_backreferences(tokens, by);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'a> Matcher<'a> {
+    /// This should take a string reference, and return
+    /// an `Matcher` which has parsed that reference.
+    #[require_lifetimes]
+    pub fn new(text: &'a str) -> Option<Matcher<'a>> {
+        Matcher::parse(text).ok()
+    }
+
+    /// Like `new`, but explains what is wrong with `text` if it is malformed.
+    pub fn parse(text: &'a str) -> Result<Matcher<'a>, MatcherParseError> {
+        Matcher::with_options(text, MatcherOptions::default())
+    }
+
+    /// Like `parse`, but the matcher will match according to `options`.
+    pub fn with_options(
+        text: &'a str,
+        options: MatcherOptions,
+    ) -> Result<Matcher<'a>, MatcherParseError> {
+        Matcher::with_limits(text, options, ParseLimits::default())
+    }
+
+    /// Like `with_options`, but fails to parse any pattern which goes over
+    /// one of `limits`.
+    pub fn with_limits(
+        text: &'a str,
+        options: MatcherOptions,
+        limits: ParseLimits,
+    ) -> Result<Matcher<'a>, MatcherParseError> {
+        let pattern = Pattern::with_limits(text, options, limits)?;
+        Ok(Matcher::from_pattern(pattern))
+    }
+
+    /// Like `parse`, but skips over anything malformed instead of giving up;
+    /// see `Pattern::parse_lossy`. This suits patterns which are still being
+    /// typed, and so are often incomplete.
+    pub fn parse_lossy(text: &'a str) -> (Matcher<'a>, Vec<MatcherParseError>) {
+        let (pattern, errors) = Pattern::parse_lossy(text);
+        let matcher = Matcher {
+            stats: MatchStats::new(pattern.token_count()),
+            pattern,
+        };
+        (matcher, errors)
+    }
+
+    /// This should take a string, and return a vector of tokens, and the corresponding part
+    /// of the given string. For examples, see the test cases below.
+    #[require_lifetimes]
+    pub fn match_string<'b, 'c>(
+        &'b mut self,
+        string: &'c str,
+    ) -> Vec<(&'b MatcherToken<'a>, &'c str)> {
+        self.match_spans(string)
+            .into_iter()
+            .map(|token_match| (token_match.token, token_match.text))
+            .collect()
+    }
+
+    /// Like `match_string`, but also says where in `string` each token
+    /// matched.
+    pub fn match_spans<'m, 'c>(&'m mut self, string: &'c str) -> Vec<TokenMatch<'m, 'a, 'c>> {
+        let answer = self.pattern.match_spans(string);
+        let match_len = answer.last().map_or(0, |token_match| token_match.span.end);
+        self.stats.record(answer.len(), match_len);
+
+        answer
+    }
+
+    /// Matches `string`, returning how many bytes at its start were matched
+    /// and the rest of `string` after them. Another matcher can then carry
+    /// on from where this one stopped.
+    pub fn match_prefix<'c>(&mut self, string: &'c str) -> (usize, &'c str) {
+        let len = self
+            .match_spans(string)
+            .last()
+            .map_or(0, |token_match| token_match.span.end);
+        (len, &string[len..])
+    }
+
+    /// Matches `string` like `match_string`, but without recording it in the
+    /// statistics. This only needs `&self`, so it can be used from several
+    /// threads at once.
+    pub fn match_str<'b, 'c>(&'b self, string: &'c str) -> Vec<(&'b MatcherToken<'a>, &'c str)> {
+        self.pattern.match_str(string)
+    }
+
+    /// Matches `candidate` like `match_spans`, but also says which token
+    /// stopped matching, and where.
+    pub fn explain<'m, 'c>(&'m mut self, candidate: &'c str) -> MatchTrace<'m, 'a, 'c> {
+        let Matcher { pattern, stats } = self;
+        let (branch, matches) = pattern.best_branch_match(candidate);
+        let offset = matches.last().map_or(0, |token_match| token_match.span.end);
+        stats.record(matches.len(), offset);
+
+        let failure = pattern.branches[branch]
+            .get(matches.len())
+            .map(|token| MatchFailure { token, offset });
+        MatchTrace {
+            candidate,
+            matches,
+            failure,
+        }
+    }
+
+    /// Matches every string in `candidates`, recording them all in the
+    /// statistics once matching is done.
+    pub fn match_all<'m, 'c, I>(&'m mut self, candidates: I) -> Vec<MatchResult<'m, 'a, 'c>>
+    where
+        I: IntoIterator<Item = &'c str>,
+    {
+        // The results borrow the pattern for all of 'm, so borrow the
+        // pattern and the statistics separately to still update the latter.
+        let Matcher { pattern, stats } = self;
+        let pattern: &'m Pattern<'a> = pattern;
+
+        let results: Vec<_> = candidates
+            .into_iter()
+            .map(|candidate| pattern.match_candidate(candidate))
+            .collect();
+        for result in &results {
+            stats.record(result.tokens_matched(), result.match_len());
+        }
+
+        results
+    }
+
+    /// Matches each line of `text` as a separate candidate, like
+    /// `match_string`. Each line's matches come with its line number,
+    /// counting from 1, and borrow from `text` itself.
+    pub fn match_lines<'m, 'c>(
+        &'m mut self,
+        text: &'c str,
+    ) -> Vec<(usize, Vec<(&'m MatcherToken<'a>, &'c str)>)> {
+        self.match_all(text.lines())
+            .into_iter()
+            .enumerate()
+            .map(|(i, result)| {
+                let matches = result
+                    .matches
+                    .into_iter()
+                    .map(|token_match| (token_match.token, token_match.text))
+                    .collect();
+                (i + 1, matches)
+            })
+            .collect()
+    }
+
+    /// Like `match_all`, but matches the candidates in parallel. Only the
+    /// matching is parallel: the statistics are updated afterwards.
+    #[cfg(feature = "rayon")]
+    pub fn par_match_all<'m, 'c>(
+        &'m mut self,
+        candidates: &[&'c str],
+    ) -> Vec<MatchResult<'m, 'a, 'c>> {
+        let Matcher { pattern, stats } = self;
+        let pattern: &'m Pattern<'a> = pattern;
+
+        let results = pattern.par_match_all(candidates);
+        for result in &results {
+            stats.record(result.tokens_matched(), result.match_len());
+        }
+
+        results
+    }
+
+    /// Copies any borrowed text into the matcher, so that it no longer
+    /// depends on the string it was parsed from.
+    pub fn into_owned(self) -> Matcher<'static> {
+        Matcher {
+            pattern: self.pattern.into_owned(),
+            stats: self.stats,
+        }
+    }
+
+    /// Encodes this matcher's pattern as compact bytes; see
+    /// `Pattern::to_bytes`. The statistics aren't included.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.pattern.to_bytes()
+    }
+
+    /// Decodes a matcher written by `to_bytes`, borrowing its text from
+    /// `bytes`; see `Pattern::from_bytes`.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Matcher<'a>, DecodeError> {
+        Pattern::from_bytes(bytes).map(Matcher::from_pattern)
+    }
+
+    /// A matcher which matches this matcher's pattern, then `other`'s
+    /// straight after; see `Pattern::then`. It starts without statistics.
+    pub fn then(self, other: Matcher<'a>) -> Result<Matcher<'a>, ComposeError> {
+        self.pattern.then(other.pattern).map(Matcher::from_pattern)
+    }
+
+    /// A matcher which matches either this matcher's pattern or `other`'s;
+    /// see `Pattern::or`. It starts without statistics.
+    pub fn or(self, other: Matcher<'a>) -> Result<Matcher<'a>, ComposeError> {
+        self.pattern.or(other.pattern).map(Matcher::from_pattern)
+    }
+
+    fn from_pattern(pattern: Pattern<'a>) -> Matcher<'a> {
+        Matcher {
+            stats: MatchStats::new(pattern.token_count()),
+            pattern,
+        }
+    }
+
+    /// The tokens this matcher's pattern was parsed into.
+    pub fn tokens(&self) -> &[MatcherToken<'a>] {
+        self.pattern.tokens()
+    }
+
+    /// The alternatives this matcher's pattern was parsed into.
+    pub fn branches(&self) -> &[Vec<MatcherToken<'a>>] {
+        self.pattern.branches()
+    }
+
+    /// The text every match has to start with; see `Pattern::literal_prefix`.
+    pub fn literal_prefix(&self) -> Option<&str> {
+        self.pattern.literal_prefix()
+    }
+
+    /// Iterates over the tokens of every branch of this matcher's pattern.
+    pub fn iter(&self) -> Tokens<'_, 'a> {
+        self.pattern.iter()
+    }
+
+    /// Shows every token of this matcher's pattern to `visitor`; see
+    /// `Pattern::walk`.
+    pub fn walk(&self, visitor: &mut impl TokenVisitor<'a>) {
+        self.pattern.walk(visitor);
+    }
+
+    /// The text this matcher's pattern was parsed from.
+    pub fn text(&self) -> &str {
+        self.pattern.text()
+    }
+
+    /// The statistics gathered by `match_string` so far.
+    pub fn stats(&self) -> &MatchStats {
+        &self.stats
+    }
+
+    /// Copies this matcher's pattern into a new matcher which hasn't matched
+    /// anything yet, so the same pattern can be scored separately against
+    /// another set of candidates.
+    pub fn fork(&self) -> Matcher<'a> {
+        Matcher {
+            pattern: self.pattern.clone(),
+            stats: MatchStats::new(self.pattern.token_count()),
+        }
+    }
+
+    /// Forgets every candidate matched so far.
+    pub fn reset_stats(&mut self) {
+        self.stats = MatchStats::new(self.pattern.token_count());
+    }
+
+    /// The most tokens that any candidate has matched.
+    ///
+    /// This used to be a field of `Matcher`; it is now part of `stats()`.
+    pub fn most_tokens_matched(&self) -> usize {
+        self.stats.most_tokens_matched
+    }
+}
+
+impl MatcherToken<'_> {
+    /// Copies any borrowed text into the token.
+    pub fn into_owned(self) -> MatcherToken<'static> {
+        match self {
+            MatcherToken::RawText(text) => MatcherToken::RawText(Cow::Owned(text.into_owned())),
+            MatcherToken::OneOfText(alternatives) => MatcherToken::OneOfText(
+                alternatives
+                    .into_iter()
+                    .map(|tokens| tokens.into_iter().map(MatcherToken::into_owned).collect())
+                    .collect(),
+            ),
+            MatcherToken::WildCard => MatcherToken::WildCard,
+            MatcherToken::Class(class) => MatcherToken::Class(class),
+            MatcherToken::Backreference(group) => MatcherToken::Backreference(group),
+        }
+    }
+
+    /// How many groups this token contains, counting itself if it is one.
+    fn group_count(&self) -> usize {
+        match self {
+            MatcherToken::OneOfText(alternatives) => {
+                1 + alternatives
+                    .iter()
+                    .flatten()
+                    .map(MatcherToken::group_count)
+                    .sum::<usize>()
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns how many bytes of the candidate this token matches from
+    /// `start` on, or `None` if it doesn't match. If this token is a group,
+    /// `group` is its number.
+    fn match_len(&self, state: &mut MatchState<'_>, start: usize, group: usize) -> Option<usize> {
+        let string = &state.candidate[start..];
+        let options = &state.options;
+        match self {
+            // Getting the number of bytes of the first character of a
+            // str is tricky. However, once we have the first char, we
+            // can use len_utf8() to find out how many bytes it takes up.
+            MatcherToken::WildCard => string
+                .chars()
+                .next()
+                .filter(|&c| c != '\n' || options.dot_matches_newline)
+                .map(char::len_utf8),
+            MatcherToken::Class(class) => string
+                .chars()
+                .next()
+                .filter(|&c| class.matches(c))
+                .map(char::len_utf8),
+            MatcherToken::OneOfText(alternatives) => {
+                // Groups inside an alternative are numbered after the ones
+                // inside the alternatives before it.
+                let mut inner_group = group + 1;
+                for tokens in alternatives {
+                    // An alternative which fails partway through may have
+                    // captured some groups already, which must be undone.
+                    let captured = state.groups.clone();
+                    if let Some(len) = sequence_match_len(tokens, state, start, inner_group) {
+                        state.groups[group - 1] = Some(start..start + len);
+                        return Some(len);
+                    }
+                    state.groups = captured;
+                    inner_group += tokens.iter().map(MatcherToken::group_count).sum::<usize>();
+                }
+                None
+            }
+            MatcherToken::RawText(text) => text_match_len(string, text, options),
+            // A group which hasn't matched anything yet can't be matched
+            // again, so neither can a backreference to it.
+            MatcherToken::Backreference(group) => {
+                let captured = state.groups.get(group - 1)?.clone()?;
+                text_match_len(string, &state.candidate[captured], options)
+            }
+        }
+    }
+}
+
+impl CharClass {
+    /// The class written as `\` followed by `letter`, if there is one.
+    fn from_letter(letter: char) -> Option<CharClass> {
+        match letter {
+            'd' => Some(CharClass::Digit),
+            'w' => Some(CharClass::Word),
+            's' => Some(CharClass::Whitespace),
+            'D' => Some(CharClass::NotDigit),
+            'W' => Some(CharClass::NotWord),
+            'S' => Some(CharClass::NotWhitespace),
+            _ => None,
+        }
+    }
+
+    /// The letter which follows the `\` when this class is written out.
+    fn letter(self) -> char {
+        match self {
+            CharClass::Digit => 'd',
+            CharClass::Word => 'w',
+            CharClass::Whitespace => 's',
+            CharClass::NotDigit => 'D',
+            CharClass::NotWord => 'W',
+            CharClass::NotWhitespace => 'S',
+        }
+    }
+
+    /// Describes the characters of this class in words.
+    fn description(self) -> &'static str {
+        match self {
+            CharClass::Digit => "a digit",
+            CharClass::Word => "a word character",
+            CharClass::Whitespace => "whitespace",
+            CharClass::NotDigit => "anything but a digit",
+            CharClass::NotWord => "anything but a word character",
+            CharClass::NotWhitespace => "anything but whitespace",
+        }
+    }
+
+    /// Whether `c` is a character of this class.
+    pub fn matches(self, c: char) -> bool {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        match self {
+            CharClass::Digit => c.is_numeric(),
+            CharClass::Word => is_word(c),
+            CharClass::Whitespace => c.is_whitespace(),
+            CharClass::NotDigit => !c.is_numeric(),
+            CharClass::NotWord => !is_word(c),
+            CharClass::NotWhitespace => !c.is_whitespace(),
+        }
+    }
+}
+
+/// What matching a candidate needs to keep track of besides the tokens
+/// themselves.
+struct MatchState<'c> {
+    /// The whole candidate being matched.
+    candidate: &'c str,
+    /// The options of the pattern being matched.
+    options: MatcherOptions,
+    /// The part of `candidate` each group matched, so that backreferences
+    /// can match it again. Group 1 is at index 0.
+    groups: Vec<Option<Range<usize>>>,
+}
+
+/// Returns how many bytes of the candidate are matched from `start` by all
+/// of `tokens` one after another, or `None` if any of them doesn't match.
+/// The first group among `tokens` is numbered `group`.
+fn sequence_match_len(
+    tokens: &[MatcherToken<'_>],
+    state: &mut MatchState<'_>,
+    start: usize,
+    mut group: usize,
+) -> Option<usize> {
+    let mut len = 0;
+    for token in tokens {
+        len += token.match_len(state, start + len, group)?;
+        group += token.group_count();
+    }
+    Some(len)
+}
+
+/// Returns how many bytes at the start of `string` match `text`.
+fn text_match_len(string: &str, text: &str, options: &MatcherOptions) -> Option<usize> {
+    if options.case_insensitive {
+        prefix_len_ignoring_case(string, text)
+    } else {
+        string.starts_with(text).then_some(text.len())
+    }
+}
+
+/// Returns how many bytes at the start of `string` match `text` if ASCII
+/// case is ignored. Other characters must match exactly, so a match always
+/// ends on a character boundary of `string`.
+#[cfg(not(feature = "unicode-case"))]
+fn prefix_len_ignoring_case(string: &str, text: &str) -> Option<usize> {
+    let prefix = string.as_bytes().get(..text.len())?;
+    prefix
+        .eq_ignore_ascii_case(text.as_bytes())
+        .then_some(text.len())
+}
+
+/// Returns how many bytes at the start of `string` match `text` if case is
+/// ignored. Characters are compared one at a time by their lowercase forms,
+/// so this never allocates, but it won't match `ß` with `ss`.
+#[cfg(feature = "unicode-case")]
+fn prefix_len_ignoring_case(string: &str, text: &str) -> Option<usize> {
+    let mut candidate = string.char_indices();
+    for expected in text.chars() {
+        let (_, c) = candidate.next()?;
+        if !c.to_lowercase().eq(expected.to_lowercase()) {
+            return None;
+        }
+    }
+    Some(candidate.next().map_or(string.len(), |(i, _)| i))
+}
+
+/// A recursive descent parser for patterns.
+struct Parser<'a> {
+    /// The whole pattern being parsed.
+    text: &'a str,
+    /// The byte offset in `text` of the next character to parse.
+    position: usize,
+    /// How many groups have been opened so far.
+    groups: usize,
+    /// Whether to carry on past errors, recording them in `errors`.
+    lossy: bool,
+    /// The errors skipped over so far, if `lossy` is set.
+    errors: Vec<MatcherParseError>,
+    /// How big the pattern is allowed to be.
+    limits: ParseLimits,
+    /// How many tokens have been parsed so far.
+    tokens: usize,
+    /// How many groups the parser is currently inside.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str, lossy: bool, limits: ParseLimits) -> Parser<'a> {
+        Parser {
+            text,
+            position: 0,
+            groups: 0,
+            lossy,
+            errors: vec![],
+            limits,
+            tokens: 0,
+            depth: 0,
+        }
+    }
+
+    /// Parses the whole pattern, as branches separated by `|`.
+    fn parse_branches(&mut self) -> Result<Vec<Vec<MatcherToken<'a>>>, MatcherParseError> {
+        let mut branches = vec![self.parse_sequence(false)?];
+        while self.rest().starts_with('|') {
+            self.check_alternatives(branches.len())?;
+            self.position += 1;
+            branches.push(self.parse_sequence(false)?);
+        }
+        Ok(branches)
+    }
+
+    /// Returns `error`, unless parsing is lossy, in which case it is
+    /// recorded so the caller can skip past it.
+    fn recover(&mut self, error: MatcherParseError) -> Result<(), MatcherParseError> {
+        if !self.lossy {
+            return Err(error);
+        }
+        self.errors.push(error);
+        Ok(())
+    }
+
+    /// The part of the pattern which hasn't been parsed yet.
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    /// Parses tokens until the end of the pattern, or the `|` which ends
+    /// the current alternative. Inside a group, this also stops at the `)`
+    /// which ends the group. Either is left for the caller to deal with.
+    fn parse_sequence(
+        &mut self,
+        in_group: bool,
+    ) -> Result<Vec<MatcherToken<'a>>, MatcherParseError> {
+        let mut tokens = vec![];
+        loop {
+            let rest = self.rest();
+            match rest.chars().next() {
+                None => break,
+                Some('|') => break,
+                Some(')') if in_group => break,
+                Some(')') => {
+                    self.recover(self.error(ParseErrorKind::UnmatchedClose, self.position))?;
+                    self.position += 1;
+                }
+                Some(c) => {
+                    self.tokens += 1;
+                    if self.tokens > self.limits.max_tokens {
+                        let limit = self.limits.max_tokens;
+                        return Err(
+                            self.error(ParseErrorKind::TooManyTokens { limit }, self.position)
+                        );
+                    }
+
+                    match c {
+                        '.' => {
+                            tokens.push(MatcherToken::WildCard);
+                            self.position += 1;
+                        }
+                        '(' => tokens.extend(self.parse_group()?),
+                        '\\' => tokens.extend(self.parse_escape()?),
+                        _ => {
+                            let len = rest.find(SPECIAL_CHARS).unwrap_or(rest.len());
+                            tokens.push(MatcherToken::RawText(Cow::Borrowed(&rest[..len])));
+                            self.position += len;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(tokens)
+    }
+
+    /// Fails if there can't be another alternative after the `|` at the
+    /// current position, because there are already `alternatives`.
+    fn check_alternatives(&self, alternatives: usize) -> Result<(), MatcherParseError> {
+        if alternatives < self.limits.max_alternatives {
+            return Ok(());
+        }
+        let limit = self.limits.max_alternatives;
+        Err(self.error(ParseErrorKind::TooManyAlternatives { limit }, self.position))
+    }
+
+    /// Parses a `\` and the character after it: either a class like `\d`, a
+    /// backreference like `\1`, the start of quoted text like `\Q...\E`, or
+    /// a special character which should be matched literally.
+    ///
+    /// Returns `None` if the escape was malformed and has been skipped.
+    fn parse_escape(&mut self) -> Result<Option<MatcherToken<'a>>, MatcherParseError> {
+        let backslash = self.position;
+        self.position += 1;
+
+        let rest = self.rest();
+        let Some(escaped) = rest.chars().next() else {
+            self.recover(self.error(ParseErrorKind::TrailingBackslash, backslash))?;
+            return Ok(None);
+        };
+        self.position += escaped.len_utf8();
+
+        // Everything up to the next `\E` is matched literally, or up to the
+        // end of the pattern if there isn't one.
+        if escaped == 'Q' {
+            let quoted = self.rest();
+            let len = quoted.find(r"\E").unwrap_or(quoted.len());
+            self.position += (len + 2).min(quoted.len());
+            return Ok(Some(MatcherToken::RawText(Cow::Borrowed(&quoted[..len]))));
+        }
+
+        if let Some(group) = escaped.to_digit(10).filter(|&group| group > 0) {
+            let group = group as usize;
+            if group > self.groups {
+                self.recover(MatcherParseError {
+                    kind: ParseErrorKind::UnknownGroup,
+                    span: backslash..self.position,
+                })?;
+                return Ok(None);
+            }
+            return Ok(Some(MatcherToken::Backreference(group)));
+        }
+
+        Ok(Some(match CharClass::from_letter(escaped) {
+            Some(class) => MatcherToken::Class(class),
+            None => MatcherToken::RawText(Cow::Borrowed(&rest[..escaped.len_utf8()])),
+        }))
+    }
+
+    /// Creates an error about the character at byte offset `at`.
+    fn error(&self, kind: ParseErrorKind, at: usize) -> MatcherParseError {
+        let len = self.text[at..].chars().next().map_or(0, char::len_utf8);
+        MatcherParseError {
+            kind,
+            span: at..at + len,
+        }
+    }
+
+    /// Parses a group, starting at its `(`.
+    ///
+    /// Returns `None` if the group was nested too deeply and has been
+    /// skipped, along with everything inside it.
+    fn parse_group(&mut self) -> Result<Option<MatcherToken<'a>>, MatcherParseError> {
+        let open = self.position;
+        self.position += 1;
+        self.groups += 1;
+        self.depth += 1;
+        if self.depth > self.limits.max_depth {
+            let limit = self.limits.max_depth;
+            self.recover(self.error(ParseErrorKind::TooDeep { limit }, open))?;
+            self.skip_group();
+            self.groups -= 1;
+            self.depth -= 1;
+            return Ok(None);
+        }
+
+        let mut alternatives = vec![];
+        loop {
+            alternatives.push(self.parse_sequence(true)?);
+            match self.rest().chars().next() {
+                Some('|') => {
+                    self.check_alternatives(alternatives.len())?;
+                    self.position += 1;
+                }
+                Some(')') => {
+                    self.position += 1;
+                    break;
+                }
+                _ => {
+                    self.recover(self.error(ParseErrorKind::UnclosedGroup, open))?;
+                    break;
+                }
+            }
+        }
+
+        self.depth -= 1;
+        Ok(Some(MatcherToken::OneOfText(alternatives)))
+    }
+
+    /// Moves past the `)` which closes the group just opened, or to the end
+    /// of the pattern if there isn't one. Nothing in between is parsed, so
+    /// this doesn't go any deeper however many groups are nested inside.
+    fn skip_group(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            let Some(c) = self.rest().chars().next() else {
+                break;
+            };
+            self.position += c.len_utf8();
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                '\\' if self.rest().starts_with('Q') => {
+                    let quoted = &self.rest()[1..];
+                    self.position += 1 + quoted.find(r"\E").map_or(quoted.len(), |len| len + 2);
+                }
+                '\\' => self.position += self.rest().chars().next().map_or(0, char::len_utf8),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The version of the format written by `Pattern::to_bytes`, which is the
+/// first byte of its output.
+const FORMAT_VERSION: u8 = 1;
+
+/// How deeply groups may nest in a pattern which is parsed or decoded with
+/// the default `ParseLimits`.
+pub const MAX_DECODE_DEPTH: usize = 256;
+
+/// Writes patterns in the format `Pattern::to_bytes` describes.
+struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    /// Writes `n` as a LEB128 varint: seven bits at a time, lowest first,
+    /// with the top bit set on every byte but the last.
+    fn varint(&mut self, mut n: usize) {
+        while n >= 0x80 {
+            self.bytes.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.bytes.push(n as u8);
+    }
+
+    fn str(&mut self, text: &str) {
+        self.varint(text.len());
+        self.bytes.extend_from_slice(text.as_bytes());
+    }
+
+    fn sequence(&mut self, tokens: &[MatcherToken<'_>]) {
+        self.varint(tokens.len());
+        for token in tokens {
+            match token {
+                MatcherToken::RawText(text) => {
+                    self.bytes.push(0);
+                    self.str(text);
+                }
+                MatcherToken::OneOfText(alternatives) => {
+                    self.bytes.push(1);
+                    self.varint(alternatives.len());
+                    for tokens in alternatives {
+                        self.sequence(tokens);
+                    }
+                }
+                MatcherToken::WildCard => self.bytes.push(2),
+                MatcherToken::Class(class) => {
+                    self.bytes.push(3);
+                    self.bytes.push(class.letter() as u8);
+                }
+                MatcherToken::Backreference(group) => {
+                    self.bytes.push(4);
+                    self.varint(*group);
+                }
+            }
+        }
+    }
+}
+
+/// Reads patterns written by `Encoder`, borrowing their text from `bytes`.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    /// The offset in `bytes` of the next byte to read.
+    position: usize,
+    /// How big the pattern is allowed to be.
+    limits: ParseLimits,
+    /// How many tokens have been decoded so far.
+    tokens: usize,
+    /// How many groups have been started so far.
+    groups: usize,
+    /// How many groups the decoder is currently inside.
+    depth: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn error(&self) -> DecodeError {
+        DecodeError {
+            offset: self.position,
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self.bytes.get(self.position).ok_or_else(|| self.error())?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<usize, DecodeError> {
+        let start = self.position;
+        let mut n = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.byte()?;
+            n |= usize::from(byte & 0x7f)
+                .checked_shl(shift)
+                .filter(|bits| bits >> shift == usize::from(byte & 0x7f))
+                .ok_or(DecodeError { offset: start })?;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(DecodeError { offset: start })
+    }
+
+    /// Reads a string, borrowing it straight out of `bytes`.
+    fn str(&mut self) -> Result<&'a str, DecodeError> {
+        let start = self.position;
+        let len = self.varint()?;
+        let error = DecodeError { offset: start };
+        let end = self.position.checked_add(len).ok_or(error.clone())?;
+        let text = self.bytes.get(self.position..end).ok_or(error.clone())?;
+        let text = core::str::from_utf8(text).map_err(|_| error)?;
+        self.position = end;
+        Ok(text)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<MatcherToken<'a>>, DecodeError> {
+        // Lengths aren't trusted for preallocating, since every token takes
+        // at least one byte and a bad length could ask for far more.
+        let count = self.varint()?;
+        let mut tokens = vec![];
+        for _ in 0..count {
+            let start = self.position;
+            self.tokens += 1;
+            if self.tokens > self.limits.max_tokens {
+                return Err(self.error());
+            }
+            let token = match self.byte()? {
+                0 => MatcherToken::RawText(Cow::Borrowed(self.str()?)),
+                1 => {
+                    self.groups += 1;
+                    self.depth += 1;
+                    if self.depth > self.limits.max_depth {
+                        return Err(DecodeError { offset: start });
+                    }
+                    let count_at = self.position;
+                    let count = self.varint()?;
+                    if count > self.limits.max_alternatives {
+                        return Err(DecodeError { offset: count_at });
+                    }
+                    let mut alternatives = vec![];
+                    for _ in 0..count {
+                        alternatives.push(self.sequence()?);
+                    }
+                    self.depth -= 1;
+                    MatcherToken::OneOfText(alternatives)
+                }
+                2 => MatcherToken::WildCard,
+                3 => CharClass::from_letter(char::from(self.byte()?))
+                    .map(MatcherToken::Class)
+                    .ok_or(DecodeError { offset: start + 1 })?,
+                // Groups are numbered from 1, and as when parsing, a
+                // backreference has to come after the start of its group.
+                4 => match self.varint()? {
+                    group if (1..=self.groups).contains(&group) => {
+                        MatcherToken::Backreference(group)
+                    }
+                    _ => return Err(DecodeError { offset: start + 1 }),
+                },
+                _ => return Err(DecodeError { offset: start }),
+            };
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+}
+
+/// Checks whether `pattern` would parse, without allocating.
+///
+/// This accepts exactly the patterns `Pattern::parse` accepts, but because
+/// it's a `const fn` it can check patterns which are baked into a program
+/// while that program is being compiled:
+///
+/// ```
+/// const _: () = assert!(lifetimekata_matcher::validate_pattern("abc(d|e)."));
+/// ```
+pub const fn validate_pattern(pattern: &str) -> bool {
+    let bytes = pattern.as_bytes();
+    let mut depth = 0usize;
+    let mut groups = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if i + 1 == bytes.len() => return false,
+            // A backreference must come after the `(` of its group.
+            b'\\' if matches!(bytes[i + 1], b'1'..=b'9') => {
+                if (bytes[i + 1] - b'0') as usize > groups {
+                    return false;
+                }
+                i += 1;
+            }
+            // Nothing is special inside `\Q...\E`, and without an `\E`
+            // the rest of the pattern is quoted.
+            b'\\' if bytes[i + 1] == b'Q' => {
+                i += 2;
+                while i + 1 < bytes.len() && !(bytes[i] == b'\\' && bytes[i + 1] == b'E') {
+                    i += 1;
+                }
+                if i + 1 >= bytes.len() {
+                    return depth == 0;
+                }
+                i += 1;
+            }
+            // Whatever else follows a `\` is taken literally. Skipping one
+            // byte is enough even if it starts a longer character, since the
+            // bytes after it can't be mistaken for anything special.
+            b'\\' => i += 1,
+            b'(' if depth == MAX_DECODE_DEPTH => return false,
+            b'(' => {
+                depth += 1;
+                groups += 1;
+            }
+            b')' if depth == 0 => return false,
+            b')' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    depth == 0
+}
+
+impl MatchResult<'_, '_, '_> {
+    /// How many tokens of the pattern matched the candidate.
+    pub fn tokens_matched(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// How many bytes at the start of the candidate were matched.
+    pub fn match_len(&self) -> usize {
+        self.matches
+            .last()
+            .map_or(0, |token_match| token_match.span.end)
+    }
+}
+
+impl<'a> MatcherSet<'a> {
+    /// Parses every pattern in `patterns`, or returns `None` if any of them
+    /// is malformed.
+    pub fn new<I>(patterns: I) -> Option<MatcherSet<'a>>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let patterns = patterns
+            .into_iter()
+            .map(Pattern::new)
+            .collect::<Option<Vec<_>>>()?;
+        Some(MatcherSet { patterns })
+    }
+
+    /// Matches `candidate` against every pattern in the set, in order.
+    pub fn matches(&self, candidate: &str) -> Vec<SetMatch> {
+        self.patterns
+            .iter()
+            .enumerate()
+            .map(|(index, pattern)| {
+                let (branch, answer) = pattern.best_branch_match(candidate);
+                let match_len = answer.last().map_or(0, |token_match| token_match.span.end);
+                SetMatch {
+                    index,
+                    tokens_matched: answer.len(),
+                    is_match: answer.len() == pattern.branches[branch].len()
+                        && match_len == candidate.len(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the index of the pattern which matched the most tokens of
+    /// `candidate`, preferring earlier patterns when there is a tie. Returns
+    /// `None` if no pattern matched anything at all.
+    pub fn best_match(&self, candidate: &str) -> Option<usize> {
+        self.matches(candidate)
+            .into_iter()
+            .filter(|set_match| set_match.tokens_matched > 0)
+            .max_by(|a, b| {
+                a.tokens_matched
+                    .cmp(&b.tokens_matched)
+                    .then(b.index.cmp(&a.index))
+            })
+            .map(|set_match| set_match.index)
+    }
+}
+
+/// Parses a pattern with `Matcher::try_from(pattern)?`.
+///
+/// This is `TryFrom` rather than `FromStr` because of lifetimes. `FromStr`
+/// has to work for a `&str` of any lifetime, and it has no way to say that
+/// the `Self` it returns borrows from that `&str`. A `Matcher<'a>` does
+/// borrow its pattern, so the only way it could implement `FromStr` would
+/// be to copy the pattern into a `Matcher<'static>`. `TryFrom<&'a str>`
+/// names the lifetime, so it can tie the `Matcher` to it instead.
+impl<'a> TryFrom<&'a str> for Matcher<'a> {
+    type Error = MatcherParseError;
+
+    fn try_from(text: &'a str) -> Result<Matcher<'a>, MatcherParseError> {
+        Matcher::parse(text)
+    }
+}
+
+/// Parses a pattern with `Pattern::try_from(pattern)?`; see the `TryFrom`
+/// implementation for `Matcher` for why this isn't `FromStr`.
+impl<'a> TryFrom<&'a str> for Pattern<'a> {
+    type Error = MatcherParseError;
+
+    fn try_from(text: &'a str) -> Result<Pattern<'a>, MatcherParseError> {
+        Pattern::parse(text)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> TryFrom<PatternData<'a>> for Pattern<'a> {
+    type Error = &'static str;
+
+    /// Checks that `data.text` parses into `data.branches`. The parse is a
+    /// lossy one, since a pattern from `parse_lossy` keeps the text it was
+    /// parsed from, errors and all.
+    fn try_from(data: PatternData<'a>) -> Result<Pattern<'a>, &'static str> {
+        if Pattern::parse_lossy(&data.text).0.branches != data.branches {
+            return Err("the pattern's tokens aren't the ones its text parses into");
+        }
+        Ok(Pattern {
+            text: data.text,
+            branches: data.branches,
+            options: data.options,
+        })
+    }
+}
+
+impl<'m, 'a> Iterator for Tokens<'m, 'a> {
+    type Item = &'m MatcherToken<'a>;
+
+    fn next(&mut self) -> Option<&'m MatcherToken<'a>> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Tokens<'_, '_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl core::iter::FusedIterator for Tokens<'_, '_> {}
+
+impl<'m, 'a> IntoIterator for &'m Pattern<'a> {
+    type Item = &'m MatcherToken<'a>;
+    type IntoIter = Tokens<'m, 'a>;
+
+    fn into_iter(self) -> Tokens<'m, 'a> {
+        self.iter()
+    }
+}
+
+impl<'m, 'a> IntoIterator for &'m Matcher<'a> {
+    type Item = &'m MatcherToken<'a>;
+    type IntoIter = Tokens<'m, 'a>;
+
+    fn into_iter(self) -> Tokens<'m, 'a> {
+        self.iter()
+    }
+}
+
+/// Writes `tokens` one after another, so that they parse back into the
+/// same tokens.
+fn write_sequence(f: &mut fmt::Formatter<'_>, tokens: &[MatcherToken<'_>]) -> fmt::Result {
+    let mut after_text = false;
+    for token in tokens {
+        after_text = match token {
+            MatcherToken::RawText(text) => write_raw_text(f, text, after_text)?,
+            token => {
+                write!(f, "{token}")?;
+                false
+            }
+        };
+    }
+    Ok(())
+}
+
+/// Writes `text` so that it parses back into a single `RawText`, returning
+/// whether it was written just as it is.
+///
+/// Text written as it is would run on into text written the same way just
+/// before it, which `after_text` says there is, and would stop at any
+/// special character, so otherwise it is quoted as `\Q...\E`. A lone special
+/// character is escaped instead, since that reads better. Parsing never
+/// produces text containing `\E`, which can't be quoted.
+fn write_raw_text(
+    f: &mut fmt::Formatter<'_>,
+    text: &str,
+    after_text: bool,
+) -> Result<bool, fmt::Error> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if SPECIAL_CHARS.contains(&c) => write!(f, "\\{c}")?,
+        (Some(_), _) if !after_text && !text.contains(SPECIAL_CHARS) => {
+            f.write_str(text)?;
+            return Ok(true);
+        }
+        _ => write!(f, "\\Q{text}\\E")?,
+    }
+    Ok(false)
+}
+
+impl fmt::Display for MatcherToken<'_> {
+    /// Writes the token back out in the same syntax `Matcher::new` parses.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatcherToken::RawText(text) => write_raw_text(f, text, false).map(|_| ()),
+            MatcherToken::OneOfText(alternatives) => {
+                write!(f, "(")?;
+                for (i, tokens) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "|")?;
+                    }
+                    write_sequence(f, tokens)?;
+                }
+                write!(f, ")")
+            }
+            MatcherToken::WildCard => write!(f, "."),
+            MatcherToken::Class(class) => write!(f, "\\{}", class.letter()),
+            MatcherToken::Backreference(group) => write!(f, "\\{group}"),
+        }
+    }
+}
+
+impl MatcherParseError {
+    /// Describes the error, pointing at the problem with a caret underneath
+    /// `pattern` in the same way rustc does:
+    ///
+    /// ```text
+    /// error: this group is never closed
+    ///   |
+    ///   | abc(d|e|f.
+    ///   |    ^
+    /// ```
+    ///
+    /// `pattern` should be the pattern which failed to parse.
+    pub fn render(&self, pattern: &str) -> String {
+        // Count characters rather than bytes, so the caret lines up with
+        // the right character when the pattern isn't ASCII.
+        let start = self.span.start.min(pattern.len());
+        let end = self.span.end.clamp(start, pattern.len());
+        let padding = pattern[..start].chars().count();
+        let carets = pattern[start..end].chars().count().max(1);
+
+        format!(
+            "error: {}\n  |\n  | {pattern}\n  | {}{}\n",
+            self.kind,
+            " ".repeat(padding),
+            "^".repeat(carets),
+        )
+    }
+}
+
+impl fmt::Display for MatchTrace<'_, '_, '_> {
+    /// Writes one line for each token which matched, then one saying why
+    /// matching stopped:
+    ///
+    /// ```text
+    /// `ab` matched "ab" at bytes 0..2
+    /// `(c|d)` failed at byte 2: expected one of the alternatives in `(c|d)`, found "x"
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token_match in &self.matches {
+            writeln!(
+                f,
+                "`{}` matched {:?} at bytes {}..{}",
+                token_match.token, token_match.text, token_match.span.start, token_match.span.end
+            )?;
+        }
+
+        let Some(MatchFailure { token, offset }) = self.failure else {
+            return write!(f, "every token matched");
+        };
+        write!(f, "`{token}` failed at byte {offset}: expected ")?;
+        match token {
+            MatcherToken::RawText(text) => write!(f, "{text:?}")?,
+            MatcherToken::OneOfText(_) => write!(f, "one of the alternatives in `{token}`")?,
+            MatcherToken::WildCard => write!(f, "any character")?,
+            MatcherToken::Class(class) => write!(f, "{}", class.description())?,
+            MatcherToken::Backreference(group) => write!(f, "the text group {group} matched")?,
+        }
+        match &self.candidate[offset..] {
+            "" => write!(f, ", found the end of the candidate"),
+            rest => write!(f, ", found {rest:?}"),
+        }
+    }
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnclosedGroup => write!(f, "this group is never closed"),
+            ParseErrorKind::UnmatchedClose => write!(f, "this `)` does not close any group"),
+            ParseErrorKind::TrailingBackslash => {
+                write!(f, "this `\\` has nothing after it to escape")
+            }
+            ParseErrorKind::UnknownGroup => {
+                write!(f, "this refers to a group which hasn't been opened yet")
+            }
+            ParseErrorKind::TooManyTokens { limit } => {
+                write!(f, "this goes over the limit of {limit} tokens")
+            }
+            ParseErrorKind::TooManyAlternatives { limit } => {
+                write!(f, "this goes over the limit of {limit} alternatives")
+            }
+            ParseErrorKind::TooDeep { limit } => {
+                write!(f, "this goes over the limit of {limit} nested groups")
+            }
+        }
+    }
+}
+
+impl fmt::Display for MatcherParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at byte {})", self.kind, self.span.start)
+    }
+}
+
+impl core::error::Error for MatcherParseError {}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed pattern bytes (at byte {})", self.offset)
+    }
+}
+
+impl core::error::Error for DecodeError {}
+
+impl fmt::Display for ComposeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a backreference would refer to group {}, but only groups 1 to 9 can be referred to",
+            self.group
+        )
+    }
+}
+
+impl core::error::Error for ComposeError {}
+
+impl fmt::Display for Pattern<'_> {
+    /// Writes out the canonical form of this pattern, built from its
+    /// tokens rather than copied from `text`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, tokens) in self.branches.iter().enumerate() {
+            if i > 0 {
+                write!(f, "|")?;
+            }
+            write_sequence(f, tokens)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Matcher<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pattern.fmt(f)
+    }
+}
+
+impl PartialEq for Pattern<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.branches == other.branches && self.options == other.options
+    }
+}
+
+impl Eq for Pattern<'_> {}
+
+impl Hash for Pattern<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.branches.hash(state);
+        self.options.hash(state);
+    }
+}
+
+impl PartialEq for Matcher<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for Matcher<'_> {}
+
+impl Hash for Matcher<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pattern.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        validate_pattern, CharClass, ComposeError, DecodeError, MatchFailure, MatchResult, Matcher,
+        MatcherOptions, MatcherParseError, MatcherSet, MatcherToken, ParseErrorKind, ParseLimits,
+        Pattern, SetMatch, TokenMatch, TokenVisitor, MAX_DECODE_DEPTH,
+    };
+    use proptest::prelude::*;
+    use std::borrow::Cow;
+    use std::collections::HashSet;
+
+    fn raw(text: &str) -> MatcherToken<'_> {
+        MatcherToken::RawText(text.into())
+    }
+
+    fn one_of<'a>(alternatives: &[&'a str]) -> MatcherToken<'a> {
+        MatcherToken::OneOfText(alternatives.iter().map(|text| vec![raw(text)]).collect())
+    }
+
+    #[test]
+    fn simple_test() {
+        let match_string = "abc(d|e|f).".to_string();
+        let mut matcher = Matcher::new(&match_string).unwrap();
+
+        assert_eq!(matcher.most_tokens_matched(), 0);
+
+        {
+            let candidate1 = "abcge".to_string();
+            let result = matcher.match_string(&candidate1);
+            assert_eq!(result, vec![(&MatcherToken::RawText("abc".into()), "abc"),]);
+            assert_eq!(matcher.most_tokens_matched(), 1);
+        }
+
+        {
+            // Change 'e' to '💪' if you want to test unicode.
+            let candidate1 = "abcde".to_string();
+            let result = matcher.match_string(&candidate1);
+            assert_eq!(
+                result,
+                vec![
+                    (&MatcherToken::RawText("abc".into()), "abc"),
+                    (&one_of(&["d", "e", "f"]), "d"),
+                    (&MatcherToken::WildCard, "e") // or '💪'
+                ]
+            );
+            assert_eq!(matcher.most_tokens_matched(), 3);
+        }
+    }
+
+    #[test]
+    fn broken_matcher() {
+        let match_string = "abc(d|e|f.".to_string();
+        let matcher = Matcher::new(&match_string);
+        assert_eq!(matcher, None);
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for pattern in [
+            "abc(d|e|f).",
+            "(Black|Bridge)(rock|stone|water).company",
+            "..()x",
+            "(ab.|c(d|e))f",
+            r"\d\W(\s|x)\.\\",
+            r"\Qa.b\E(c|\Q\E)",
+        ] {
+            let matcher = Matcher::new(pattern).unwrap();
+            let displayed = matcher.to_string();
+            assert_eq!(displayed, pattern);
+            assert_eq!(Matcher::new(&displayed).unwrap(), matcher);
+        }
+
+        // Raw text is written so that it parses back into the same tokens,
+        // even when it was written some other way.
+        for (pattern, displayed) in [
+            (r"a\b", r"a\Qb\E"),
+            (r"\Qab\Ecd", r"ab\Qcd\E"),
+            (r"\Qa\E\.\Qb|c\E", r"a\.\Qb|c\E"),
+        ] {
+            let matcher = Matcher::new(pattern).unwrap();
+            assert_eq!(matcher.to_string(), displayed);
+            assert_eq!(Matcher::new(displayed).unwrap(), matcher);
+        }
+        assert_ne!(Matcher::new("ab"), Matcher::new(r"a\b"));
+        assert_eq!(one_of(&["d", "e"]).to_string(), "(d|e)");
+        assert_eq!(MatcherToken::WildCard.to_string(), ".");
+    }
+
+    #[test]
+    fn nested_groups() {
+        let mut matcher = Matcher::new("(ab.|c(d|e))f").unwrap();
+        assert_eq!(
+            matcher.pattern.branches[0],
+            vec![
+                MatcherToken::OneOfText(vec![
+                    vec![raw("ab"), MatcherToken::WildCard],
+                    vec![raw("c"), one_of(&["d", "e"])],
+                ]),
+                raw("f"),
+            ]
+        );
+
+        assert_eq!(matcher.match_string("abzf").len(), 2);
+        assert_eq!(matcher.match_string("cef")[0].1, "ce");
+        assert_eq!(matcher.match_string("cff").len(), 0);
+    }
+
+    #[test]
+    fn empty_alternative_is_optional() {
+        let mut matcher = Matcher::new("a(bc|)d").unwrap();
+        let optional = MatcherToken::OneOfText(vec![vec![raw("bc")], vec![]]);
+        assert_eq!(matcher.pattern.branches[0][1], optional);
+
+        assert_eq!(
+            matcher.match_string("abcd"),
+            vec![(&raw("a"), "a"), (&optional, "bc"), (&raw("d"), "d")]
+        );
+        assert_eq!(
+            matcher.match_string("ad"),
+            vec![(&raw("a"), "a"), (&optional, ""), (&raw("d"), "d")]
+        );
+        assert_eq!(matcher.match_string("abd").len(), 2);
+    }
+
+    #[test]
+    fn empty_alternative_matches_at_end_of_candidate() {
+        let set = MatcherSet::new(["ab(c|)"]).unwrap();
+        assert!(set.matches("ab")[0].is_match);
+        assert!(set.matches("abc")[0].is_match);
+        assert!(!set.matches("abd")[0].is_match);
+    }
+
+    #[test]
+    fn perl_classes() {
+        let mut matcher = Matcher::new(r"\d\d\s\w\S").unwrap();
+        assert_eq!(
+            matcher.pattern.branches[0],
+            vec![
+                MatcherToken::Class(CharClass::Digit),
+                MatcherToken::Class(CharClass::Digit),
+                MatcherToken::Class(CharClass::Whitespace),
+                MatcherToken::Class(CharClass::Word),
+                MatcherToken::Class(CharClass::NotWhitespace),
+            ]
+        );
+        assert_eq!(matcher.match_string("42 _!").len(), 5);
+        assert_eq!(matcher.match_string("٤٢\u{3000}é💪").len(), 5);
+        assert_eq!(matcher.match_string("4x").len(), 1);
+
+        let mut negated = Matcher::new(r"\D\W").unwrap();
+        assert_eq!(negated.match_string("a ").len(), 2);
+        assert_eq!(negated.match_string("1 ").len(), 0);
+        assert_eq!(negated.match_string("ab").len(), 1);
+    }
+
+    #[test]
+    fn escaped_special_characters() {
+        let mut matcher = Matcher::new(r"a\.b\(").unwrap();
+        assert_eq!(
+            matcher.pattern.branches[0],
+            vec![raw("a"), raw("."), raw("b"), raw("(")]
+        );
+        assert_eq!(matcher.match_string("a.b(").len(), 4);
+        assert_eq!(matcher.match_string("axb(").len(), 1);
+    }
+
+    #[test]
+    fn case_insensitive_matching() {
+        let options = MatcherOptions {
+            case_insensitive: true,
+            ..MatcherOptions::default()
+        };
+        let mut matcher = Matcher::with_options("Abc(D|e).", options).unwrap();
+        assert_eq!(
+            matcher
+                .match_string("aBCdz")
+                .into_iter()
+                .map(|(_, matched)| matched)
+                .collect::<Vec<_>>(),
+            vec!["aBC", "d", "z"]
+        );
+        assert_eq!(matcher.match_string("ABCE!").len(), 3);
+        assert_eq!(matcher.match_string("ab").len(), 0);
+
+        let mut sensitive = Matcher::new("Abc").unwrap();
+        assert_eq!(sensitive.match_string("aBC").len(), 0);
+    }
+
+    #[test]
+    fn dot_matches_newline_option() {
+        let mut matcher = Matcher::new("a.b").unwrap();
+        assert_eq!(matcher.match_string("a\nb").len(), 3);
+
+        let options = MatcherOptions {
+            dot_matches_newline: false,
+            ..MatcherOptions::default()
+        };
+        let mut matcher = Matcher::with_options("a.b", options).unwrap();
+        assert_eq!(matcher.match_string("a\nb").len(), 1);
+        assert_eq!(matcher.match_string("a\rb").len(), 3);
+    }
+
+    #[cfg(feature = "unicode-case")]
+    #[test]
+    fn unicode_case_insensitive_matching() {
+        let options = MatcherOptions {
+            case_insensitive: true,
+            ..MatcherOptions::default()
+        };
+        let mut matcher = Matcher::with_options("ÉTÉ(Σ|x)", options).unwrap();
+        assert_eq!(matcher.match_string("étéσ").len(), 2);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |kind, span| MatcherParseError { kind, span };
+        assert_eq!(
+            Matcher::parse("abc(d|e|f.").unwrap_err(),
+            error(ParseErrorKind::UnclosedGroup, 3..4)
+        );
+        assert_eq!(
+            Matcher::parse("(a|(b)").unwrap_err(),
+            error(ParseErrorKind::UnclosedGroup, 0..1)
+        );
+        assert_eq!(
+            Matcher::parse("a)b").unwrap_err(),
+            error(ParseErrorKind::UnmatchedClose, 1..2)
+        );
+        assert_eq!(
+            Matcher::parse(r"ab\").unwrap_err(),
+            error(ParseErrorKind::TrailingBackslash, 2..3)
+        );
+        assert_eq!(
+            Matcher::parse(r"(a)b\2").unwrap_err(),
+            error(ParseErrorKind::UnknownGroup, 4..6)
+        );
+    }
+
+    #[test]
+    fn iterate_over_tokens() {
+        let matcher = Matcher::new(r"ab.(c|.)\d|.x").unwrap();
+        let wildcards = matcher
+            .iter()
+            .filter(|token| matches!(token, MatcherToken::WildCard))
+            .count();
+        assert_eq!(wildcards, 2);
+
+        let mut tokens = vec![];
+        for token in &matcher {
+            tokens.push(token.to_string());
+        }
+        assert_eq!(tokens, ["ab", ".", "(c|.)", r"\d", ".", "x"]);
+        assert_eq!(matcher.iter().next_back(), Some(&raw("x")));
+    }
+
+    #[test]
+    fn quoted_text() {
+        let pattern = r"a\Q(b.|\d)\E.\Q)";
+        let mut matcher = Matcher::new(pattern).unwrap();
+        assert_eq!(
+            matcher.tokens(),
+            [raw("a"), raw(r"(b.|\d)"), MatcherToken::WildCard, raw(")")]
+        );
+        match &matcher.tokens()[1] {
+            MatcherToken::RawText(Cow::Borrowed(text)) => {
+                assert_eq!(text.as_ptr(), pattern[3..].as_ptr())
+            }
+            token => panic!("expected borrowed RawText, got {token:?}"),
+        }
+        assert_eq!(matcher.match_string(r"a(b.|\d)!)").len(), 4);
+        assert_eq!(matcher.match_string("a(bx|1)").len(), 1);
+
+        assert_eq!(Matcher::new(r"\Q\E").unwrap().tokens(), [raw("")]);
+    }
+
+    #[test]
+    fn top_level_alternation() {
+        let mut matcher = Matcher::new("ab.|abc|x(y|z)").unwrap();
+        assert_eq!(matcher.branches().len(), 3);
+        assert_eq!(matcher.tokens(), [raw("ab"), MatcherToken::WildCard]);
+        assert_eq!(matcher.to_string(), "ab.|abc|x(y|z)");
+
+        // The first branch to match all of its tokens wins.
+        assert_eq!(
+            matcher.match_string("abc"),
+            vec![(&raw("ab"), "ab"), (&MatcherToken::WildCard, "c")]
+        );
+        assert_eq!(matcher.match_string("xz").len(), 2);
+        // Otherwise, the branch which got furthest is reported.
+        assert_eq!(matcher.match_string("xa"), vec![(&raw("x"), "x")]);
+        assert_eq!(matcher.match_string("q"), vec![]);
+
+        let set = MatcherSet::new(["a|bc"]).unwrap();
+        assert!(set.matches("bc")[0].is_match);
+
+        // Groups are numbered across branches.
+        let mut matcher = Matcher::new(r"(a)\1|(b)\2").unwrap();
+        assert_eq!(matcher.match_string("bb").len(), 2);
+    }
+
+    #[test]
+    fn backreferences() {
+        let mut matcher = Matcher::new(r"(a|b)(c|d)-\2\1").unwrap();
+        assert_eq!(
+            matcher.pattern.branches[0][3],
+            MatcherToken::Backreference(2)
+        );
+        assert_eq!(matcher.to_string(), r"(a|b)(c|d)-\2\1");
+        assert_eq!(matcher.match_string("bc-cb").len(), 5);
+        assert_eq!(
+            matcher.match_string("ad-ab"),
+            vec![
+                (&one_of(&["a", "b"]), "a"),
+                (&one_of(&["c", "d"]), "d"),
+                (&raw("-"), "-")
+            ]
+        );
+
+        // Groups are numbered by their `(`, so nested groups come after
+        // the group around them, and before any group after it.
+        let mut matcher = Matcher::new(r"((x)|(y)z)(w)\3\4").unwrap();
+        assert_eq!(matcher.match_string("yzwyw").len(), 4);
+        assert_eq!(matcher.match_string("xwyw").len(), 2);
+
+        // A group inside an alternative which didn't match captures nothing.
+        let mut matcher = Matcher::new(r"((a)b|a)\2").unwrap();
+        assert_eq!(matcher.match_string("aa").len(), 1);
+
+        let options = MatcherOptions {
+            case_insensitive: true,
+            ..MatcherOptions::default()
+        };
+        let mut matcher = Matcher::with_options(r"(ab|cd)=\1", options).unwrap();
+        assert_eq!(matcher.match_string("Ab=aB").len(), 3);
+    }
+
+    #[test]
+    fn walk_with_a_visitor() {
+        /// Collects literal text, and how deeply groups are nested.
+        #[derive(Default)]
+        struct Summary {
+            literals: Vec<String>,
+            depth: usize,
+            max_depth: usize,
+            wildcards: usize,
+            alternatives: usize,
+        }
+
+        impl TokenVisitor<'_> for Summary {
+            fn visit_raw_text(&mut self, text: &str) {
+                self.literals.push(text.to_string());
+            }
+
+            fn visit_wildcard(&mut self) {
+                self.wildcards += 1;
+            }
+
+            fn enter_group(&mut self, alternatives: &[Vec<MatcherToken<'_>>]) {
+                self.depth += 1;
+                self.max_depth = self.max_depth.max(self.depth);
+                self.alternatives += alternatives.len();
+            }
+
+            fn exit_group(&mut self) {
+                self.depth -= 1;
+            }
+        }
+
+        let matcher = Matcher::new(r"ab.(c|d(e.|))\d|x").unwrap();
+        let mut summary = Summary::default();
+        matcher.walk(&mut summary);
+        assert_eq!(summary.literals, ["ab", "c", "d", "e", "x"]);
+        assert_eq!(summary.max_depth, 2);
+        assert_eq!(summary.depth, 0);
+        assert_eq!(summary.wildcards, 2);
+        assert_eq!(summary.alternatives, 4);
+    }
+
+    #[test]
+    fn literal_prefixes() {
+        let prefix = |pattern| {
+            Pattern::new(pattern)
+                .unwrap()
+                .literal_prefix()
+                .map(String::from)
+        };
+        assert_eq!(prefix(r"abc(d|e)"), Some("abc".to_string()));
+        assert_eq!(prefix(r"\Q(a)\E."), Some("(a)".to_string()));
+        assert_eq!(prefix(r"(a|b)c"), None);
+        assert_eq!(prefix(r".abc"), None);
+        assert_eq!(prefix(r"ab|ac"), None);
+        assert_eq!(prefix(r""), None);
+
+        let mut matcher = Matcher::new("ab(c|d)").unwrap();
+        let results = matcher.match_all(["abd", "xbd", "a", ""]);
+        let lengths: Vec<_> = results.iter().map(MatchResult::tokens_matched).collect();
+        assert_eq!(lengths, [2, 0, 0, 0]);
+        assert_eq!(matcher.stats().candidates_tried, 4);
+
+        // The prefix can't be used to reject candidates if case is ignored.
+        let options = MatcherOptions {
+            case_insensitive: true,
+            ..MatcherOptions::default()
+        };
+        let mut matcher = Matcher::with_options("ab", options).unwrap();
+        assert_eq!(matcher.match_all(["AB"])[0].tokens_matched(), 1);
+    }
+
+    #[test]
+    fn compose_matchers() -> Result<(), ComposeError> {
+        let word = Matcher::new(r"(\w)\w\1").unwrap();
+        let sep = Matcher::new("-|:").unwrap();
+        let mut matcher = word.clone().then(sep)?.then(word.clone())?;
+        assert_eq!(matcher.to_string(), r"(\w)\w\1(-|:)(\w)\w\3");
+        assert_eq!(matcher.text(), matcher.to_string());
+        assert_eq!(matcher.match_string("aba:xyx").len(), 7);
+        assert_eq!(matcher.match_string("aba-xyz").len(), 6);
+
+        let mut either = word.or(Matcher::new(r"(\d)\1").unwrap())?;
+        assert_eq!(either.to_string(), r"(\w)\w\1|(\d)\2");
+        assert_eq!(either.branches().len(), 2);
+        assert_eq!(either.match_string("77").len(), 2);
+
+        let mut wrapped = Matcher::new("x").unwrap().then(either)?;
+        assert_eq!(wrapped.to_string(), r"x((\w)\w\2|(\d)\3)");
+        assert_eq!(wrapped.match_string("x77").len(), 2);
+        assert_eq!(wrapped.stats().candidates_tried, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn backreferences_past_the_ninth_group_cant_be_composed() {
+        let nine = "(a)".repeat(9);
+        let nine = Pattern::parse(&nine).unwrap();
+        let backreference = Pattern::parse(r"(b)\1").unwrap();
+        assert_eq!(
+            nine.clone().then(backreference.clone()),
+            Err(ComposeError { group: 10 })
+        );
+        assert_eq!(
+            nine.clone().or(backreference),
+            Err(ComposeError { group: 10 })
+        );
+        // More groups are fine, as long as nothing refers to them.
+        let ten = nine.then(Pattern::parse("(b)").unwrap()).unwrap();
+        assert_eq!(Pattern::parse(ten.text()).unwrap(), ten);
+    }
+
+    #[test]
+    fn match_each_line() {
+        let text = "ab1\r\nxy\n\nab2 and more";
+        let mut matcher = Matcher::new(r"ab\d").unwrap();
+        let lines = matcher.match_lines(text);
+        assert_eq!(
+            lines,
+            vec![
+                (
+                    1,
+                    vec![
+                        (&raw("ab"), "ab"),
+                        (&MatcherToken::Class(CharClass::Digit), "1")
+                    ]
+                ),
+                (2, vec![]),
+                (3, vec![]),
+                (
+                    4,
+                    vec![
+                        (&raw("ab"), "ab"),
+                        (&MatcherToken::Class(CharClass::Digit), "2")
+                    ]
+                ),
+            ]
+        );
+        let (_, matches) = &lines[3];
+        assert_eq!(matches[0].1.as_ptr(), text[9..].as_ptr());
+        assert_eq!(matcher.stats().candidates_tried, 4);
+    }
+
+    #[test]
+    fn explain_why_matching_stopped() {
+        let mut matcher = Matcher::new(r"ab(c|d)\d").unwrap();
+        let trace = matcher.explain("abcx");
+        assert_eq!(trace.matches.len(), 2);
+        assert_eq!(
+            trace.failure,
+            Some(MatchFailure {
+                token: &MatcherToken::Class(CharClass::Digit),
+                offset: 3
+            })
+        );
+        assert_eq!(
+            trace.to_string(),
+            "`ab` matched \"ab\" at bytes 0..2\n\
+             `(c|d)` matched \"c\" at bytes 2..3\n\
+             `\\d` failed at byte 3: expected a digit, found \"x\""
+        );
+
+        let trace = matcher.explain("a");
+        assert_eq!(
+            trace.to_string(),
+            "`ab` failed at byte 0: expected \"ab\", found \"a\""
+        );
+        let trace = matcher.explain("ab");
+        assert!(trace.to_string().ends_with(
+            "expected one of the alternatives in `(c|d)`, found the end of the candidate"
+        ));
+        assert_eq!(
+            matcher.explain("abd7").to_string().lines().last(),
+            Some("every token matched")
+        );
+        assert_eq!(matcher.stats().candidates_tried, 4);
+    }
+
+    #[test]
+    fn parse_lossy_skips_errors() {
+        let (mut matcher, errors) = Matcher::parse_lossy(r"a)b(c|d\9");
+        assert_eq!(
+            errors,
+            [
+                MatcherParseError {
+                    kind: ParseErrorKind::UnmatchedClose,
+                    span: 1..2
+                },
+                MatcherParseError {
+                    kind: ParseErrorKind::UnknownGroup,
+                    span: 7..9
+                },
+                MatcherParseError {
+                    kind: ParseErrorKind::UnclosedGroup,
+                    span: 3..4
+                },
+            ]
+        );
+        assert_eq!(matcher.tokens(), [raw("a"), raw("b"), one_of(&["c", "d"])]);
+        assert_eq!(matcher.match_string("abd").len(), 3);
+
+        let (matcher, errors) = Matcher::parse_lossy(r"ab\");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(matcher.tokens(), [raw("ab")]);
+
+        let (matcher, errors) = Matcher::parse_lossy("a(b|c)");
+        assert_eq!(errors, []);
+        assert_eq!(matcher, Matcher::new("a(b|c)").unwrap());
+    }
+
+    #[test]
+    fn parse_limits() {
+        let limits = ParseLimits {
+            max_tokens: 5,
+            max_alternatives: 2,
+            max_depth: 1,
+        };
+        let parse = |pattern| Matcher::with_limits(pattern, MatcherOptions::default(), limits);
+        let error = |kind, span| MatcherParseError { kind, span };
+
+        assert!(parse("a(b|c).").is_ok());
+        assert_eq!(
+            parse("a(b|c)d.").unwrap_err(),
+            error(ParseErrorKind::TooManyTokens { limit: 5 }, 7..8)
+        );
+        assert_eq!(
+            parse("(a|b|c)").unwrap_err(),
+            error(ParseErrorKind::TooManyAlternatives { limit: 2 }, 4..5)
+        );
+        assert_eq!(
+            parse("a|b|c").unwrap_err(),
+            error(ParseErrorKind::TooManyAlternatives { limit: 2 }, 3..4)
+        );
+        assert_eq!(
+            parse("(a|(b))").unwrap_err(),
+            error(ParseErrorKind::TooDeep { limit: 1 }, 3..4)
+        );
+        assert_eq!(
+            parse("(a|(b))").unwrap_err().to_string(),
+            "this goes over the limit of 1 nested groups (at byte 3)"
+        );
+        assert!(Matcher::parse("((((((a))))))").is_ok());
+
+        // Only nesting is limited by default.
+        let nested = |depth| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(ParseLimits::default().max_depth, MAX_DECODE_DEPTH);
+        assert!(Matcher::parse(&nested(MAX_DECODE_DEPTH)).is_ok());
+        assert_eq!(
+            Matcher::parse(&nested(MAX_DECODE_DEPTH + 1)).unwrap_err(),
+            error(
+                ParseErrorKind::TooDeep {
+                    limit: MAX_DECODE_DEPTH
+                },
+                MAX_DECODE_DEPTH..MAX_DECODE_DEPTH + 1
+            )
+        );
+        assert!(validate_pattern(&nested(MAX_DECODE_DEPTH)));
+        assert!(!validate_pattern(&nested(MAX_DECODE_DEPTH + 1)));
+
+        let unlimited = ParseLimits {
+            max_depth: usize::MAX,
+            ..ParseLimits::default()
+        };
+        let deeper = nested(MAX_DECODE_DEPTH + 1);
+        assert!(Matcher::with_limits(&deeper, MatcherOptions::default(), unlimited).is_ok());
+    }
+
+    #[test]
+    fn very_deep_nesting_is_an_error() {
+        let too_deep = ParseErrorKind::TooDeep {
+            limit: MAX_DECODE_DEPTH,
+        };
+        let open = "(".repeat(200_000);
+        let balanced = format!("{open}{}", ")".repeat(200_000));
+        for pattern in [&open, &balanced] {
+            assert_eq!(
+                Matcher::parse(pattern).unwrap_err(),
+                MatcherParseError {
+                    kind: too_deep,
+                    span: MAX_DECODE_DEPTH..MAX_DECODE_DEPTH + 1
+                }
+            );
+            assert_eq!(Matcher::new(pattern), None);
+        }
+
+        // A lossy parse skips the group which is too deep, along with
+        // everything inside it, and carries on after its `)`.
+        let pattern = format!(r"{balanced}\Q)\E\1");
+        let (mut matcher, errors) = Matcher::parse_lossy(&pattern);
+        assert_eq!(
+            errors.iter().map(|error| error.kind).collect::<Vec<_>>(),
+            [too_deep]
+        );
+        assert_eq!(
+            matcher.tokens()[1..],
+            [raw(")"), MatcherToken::Backreference(1)]
+        );
+        assert_eq!(matcher.match_string(")").len(), 3);
+
+        let (_, errors) = Matcher::parse_lossy(&open);
+        assert_eq!(errors.len(), 1 + MAX_DECODE_DEPTH);
+    }
+
+    /// Patterns the `parse_and_match` fuzz target crashed on, checked the way
+    /// it checks them: `Matcher::new` and `validate_pattern` must agree.
+    #[test]
+    fn fuzzer_findings() {
+        let open = "(".repeat(200_000);
+        let balanced = format!("{open}{}", ")".repeat(200_000));
+        for pattern in [open, balanced] {
+            assert_eq!(Matcher::new(&pattern), None);
+            assert!(!validate_pattern(&pattern));
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let options = MatcherOptions {
+            case_insensitive: true,
+            dot_matches_newline: false,
+        };
+        let text = r"ab(c|\d(.|))\1|x\Q|\E";
+        let matcher = Matcher::with_options(text, options).unwrap();
+        let bytes = matcher.to_bytes();
+        assert_eq!(bytes[0], 1);
+
+        let mut decoded = Matcher::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, matcher);
+        assert_eq!(decoded.pattern.options, options);
+        assert_eq!(decoded.match_string("ABcC").len(), 3);
+
+        // The text is borrowed from the bytes, not copied out of them.
+        let bytes_range = bytes.as_ptr_range();
+        match &decoded.tokens()[0] {
+            MatcherToken::RawText(Cow::Borrowed(text)) => {
+                assert!(bytes_range.contains(&text.as_ptr()))
+            }
+            token => panic!("expected borrowed RawText, got {token:?}"),
+        }
+        assert!(bytes_range.contains(&decoded.text().as_ptr()));
+
+        // A long text needs a length of more than one byte.
+        let long = "x".repeat(300);
+        let matcher = Matcher::new(&long).unwrap();
+        assert_eq!(Matcher::from_bytes(&matcher.to_bytes()).unwrap(), matcher);
+    }
+
+    #[test]
+    fn bytes_which_dont_decode() {
+        let bytes = Matcher::new("a(b|c)").unwrap().to_bytes();
+        assert_eq!(Matcher::from_bytes(&[]), Err(DecodeError { offset: 0 }));
+        assert_eq!(Matcher::from_bytes(&[2]), Err(DecodeError { offset: 0 }));
+        for len in 1..bytes.len() {
+            assert!(Matcher::from_bytes(&bytes[..len]).is_err());
+        }
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            Matcher::from_bytes(&trailing),
+            Err(DecodeError {
+                offset: bytes.len()
+            })
+        );
+        // A backreference to group 0, which can't exist.
+        assert_eq!(
+            Matcher::from_bytes(&[1, 0, 2, 1, 1, 4, 0]),
+            Err(DecodeError { offset: 6 })
+        );
+        // A backreference to group 1, before there's a group at all.
+        assert_eq!(
+            Matcher::from_bytes(&[1, 0, 2, 1, 1, 4, 1]),
+            Err(DecodeError { offset: 6 })
+        );
+        let group_then_backreference = [1, 0, 2, 1, 2, 1, 1, 1, 2, 4, 1];
+        assert!(Matcher::from_bytes(&group_then_backreference).is_ok());
+        // A varint which doesn't fit in a usize.
+        let mut huge = vec![1];
+        huge.extend([0xff; 11]);
+        assert_eq!(Matcher::from_bytes(&huge), Err(DecodeError { offset: 1 }));
+    }
+
+    #[test]
+    fn deeply_nested_bytes_dont_decode() {
+        // Each level is a sequence of one token: a group with one
+        // alternative, which is the next level.
+        let mut bytes = vec![1, 0, 0, 1];
+        for _ in 0..100_000 {
+            bytes.extend([1, 1, 1]);
+        }
+        bytes.push(0);
+        let offset = 4 + 3 * MAX_DECODE_DEPTH + 1;
+        assert_eq!(Matcher::from_bytes(&bytes), Err(DecodeError { offset }));
+
+        let nested = format!("{}a{}", "(".repeat(3), ")".repeat(3));
+        let bytes = Matcher::new(&nested).unwrap().to_bytes();
+        let limits = ParseLimits {
+            max_depth: 2,
+            ..ParseLimits::default()
+        };
+        assert!(Pattern::from_bytes_with_limits(&bytes, limits).is_err());
+        assert!(Pattern::from_bytes(&bytes).is_ok());
+    }
+
+    #[test]
+    fn try_from_str() -> Result<(), MatcherParseError> {
+        let pattern = String::from("abc(d|e|f).");
+        let mut matcher = Matcher::try_from(pattern.as_str())?;
+        assert_eq!(matcher.match_string("abcdz").len(), 3);
+
+        let pattern: Pattern = "x(y|z)".try_into()?;
+        assert_eq!(pattern.match_str("xz").len(), 2);
+
+        assert_eq!(
+            Matcher::try_from("a)").unwrap_err().kind,
+            ParseErrorKind::UnmatchedClose
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn wildcards_stop_at_the_end_of_the_candidate() {
+        let mut matcher = Matcher::new("a..").unwrap();
+        assert_eq!(matcher.match_string(""), vec![]);
+        assert_eq!(
+            matcher.match_string("ab"),
+            vec![(&raw("a"), "a"), (&MatcherToken::WildCard, "b")]
+        );
+        assert_eq!(matcher.match_string("abc").len(), 3);
+
+        let mut matcher = Matcher::new("...").unwrap();
+        assert_eq!(matcher.match_string("").len(), 0);
+        assert_eq!(matcher.match_string("é").len(), 1);
+        assert_eq!(matcher.stats().candidates_tried, 2);
+    }
+
+    #[test]
+    fn chain_matchers_with_match_prefix() {
+        let mut method = Matcher::new("(GET|POST) ").unwrap();
+        let mut path = Matcher::new(r"/\w\w").unwrap();
+
+        let input = "POST /ab?q";
+        let (method_len, rest) = method.match_prefix(input);
+        assert_eq!((method_len, rest), (5, "/ab?q"));
+        assert_eq!(path.match_prefix(rest), (3, "?q"));
+
+        assert_eq!(path.match_prefix("nope"), (0, "nope"));
+        assert_eq!(path.stats().candidates_tried, 2);
+    }
+
+    #[test]
+    fn forks_keep_their_own_stats() {
+        let mut matcher = Matcher::new("ab(c|d)").unwrap();
+        matcher.match_string("abc");
+
+        let clone = matcher.clone();
+        assert_eq!(clone.stats(), matcher.stats());
+
+        let mut fork = matcher.fork();
+        assert_eq!(fork.stats().candidates_tried, 0);
+        fork.match_string("ab");
+        fork.match_string("xyz");
+        assert_eq!(fork.stats().candidates_tried, 2);
+        assert_eq!(fork.most_tokens_matched(), 1);
+        assert_eq!(matcher.stats().candidates_tried, 1);
+        assert_eq!(matcher.most_tokens_matched(), 2);
+    }
+
+    #[test]
+    fn equality_ignores_stats() {
+        let mut used = Matcher::new("ab(c|d)").unwrap();
+        used.match_string("abc");
+        let fresh = Matcher::new("ab(c|d)").unwrap();
+        assert_eq!(used, fresh);
+        assert_ne!(fresh, Matcher::new("ab(c|e)").unwrap());
+
+        let unique: HashSet<_> = [used, fresh, Matcher::new("ab").unwrap()]
+            .into_iter()
+            .collect();
+        assert_eq!(unique.len(), 2);
+    }
+
+    #[test]
+    fn inspect_a_parsed_matcher() {
+        let matcher = Matcher::new(r"ab(c|d)\d").unwrap();
+        assert_eq!(matcher.text(), r"ab(c|d)\d");
+        assert_eq!(
+            matcher.tokens(),
+            [
+                raw("ab"),
+                one_of(&["c", "d"]),
+                MatcherToken::Class(CharClass::Digit)
+            ]
+        );
+
+        let owned = matcher.into_owned();
+        assert_eq!(owned.text(), r"ab(c|d)\d");
+        assert_eq!(owned.tokens().len(), 3);
+    }
+
+    #[test]
+    fn validate_patterns_like_the_parser() {
+        const _: () = assert!(validate_pattern("abc(d|e)."));
+        for pattern in [
+            "",
+            "abc",
+            "a|b",
+            "(a|)",
+            "((a)(b|c))",
+            r"\(\)",
+            r"\d\é",
+            "(",
+            "a)",
+            r"a\",
+            r"(\)",
+            r"(a)\1",
+            r"(a\1)",
+            r"\1(a)",
+            r"(a)(b)\3",
+            r"\0",
+            r"(\Q)\E)",
+            r"\Q(",
+            r"(\Q)",
+            r"\Q\",
+            r"\Q\E\",
+        ] {
+            assert_eq!(
+                validate_pattern(pattern),
+                Pattern::parse(pattern).is_ok(),
+                "{pattern:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn render_parse_errors() {
+        let pattern = "abc(d|e|f.";
+        let error = Matcher::parse(pattern).unwrap_err();
+        assert_eq!(
+            error.render(pattern),
+            "error: this group is never closed\n  |\n  | abc(d|e|f.\n  |    ^\n"
+        );
+        assert_eq!(error.to_string(), "this group is never closed (at byte 3)");
+
+        let pattern = "💪.)";
+        let error = Matcher::parse(pattern).unwrap_err();
+        assert_eq!(error.render(pattern).lines().last(), Some("  |   ^"));
+    }
+
+    #[test]
+    fn match_spans() {
+        let mut matcher = Matcher::new("abc(d|e|f).").unwrap();
+        let candidate = "abcf💪!";
+        let spans = matcher.match_spans(candidate);
+
+        let group = one_of(&["d", "e", "f"]);
+        assert_eq!(
+            spans,
+            vec![
+                TokenMatch {
+                    token: &raw("abc"),
+                    text: "abc",
+                    span: 0..3
+                },
+                TokenMatch {
+                    token: &group,
+                    text: "f",
+                    span: 3..4
+                },
+                TokenMatch {
+                    token: &MatcherToken::WildCard,
+                    text: "💪",
+                    span: 4..8
+                },
+            ]
+        );
+        for token_match in &spans {
+            assert_eq!(&candidate[token_match.span.clone()], token_match.text);
+        }
+        assert_eq!(matcher.stats().longest_match, 8);
+    }
+
+    #[test]
+    fn match_all_scores_a_wordlist() {
+        let mut matcher = Matcher::new("(Black|Bridge)(rock|stone|water).company").unwrap();
+        let wordlist = String::from("BlackBridge\nBridgestone_Tyres\nBlackwater-company");
+
+        let results = matcher.match_all(wordlist.lines());
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| (result.candidate, result.tokens_matched()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("BlackBridge", 1),
+                ("Bridgestone_Tyres", 3),
+                ("Blackwater-company", 4)
+            ]
+        );
+        assert_eq!(results[2].match_len(), "Blackwater-company".len());
+
+        let stats = matcher.stats();
+        assert_eq!(stats.candidates_tried, 3);
+        assert_eq!(stats.token_hits, vec![3, 2, 2, 1]);
+        assert_eq!(stats.most_tokens_matched, 4);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_match_all_agrees_with_match_all() {
+        let candidates: Vec<String> = (0..1000).map(|i| format!("ab{i}")).collect();
+        let candidates: Vec<&str> = candidates.iter().map(String::as_str).collect();
+
+        let mut sequential = Matcher::new(r"ab\d(1|2)").unwrap();
+        let mut parallel = Matcher::new(r"ab\d(1|2)").unwrap();
+        assert_eq!(
+            parallel.par_match_all(&candidates),
+            sequential.match_all(candidates.iter().copied())
+        );
+        assert_eq!(parallel.stats(), sequential.stats());
+    }
+
+    #[test]
+    fn stats_track_every_candidate() {
+        let mut matcher = Matcher::new("abc(d|e|f).").unwrap();
+        matcher.match_string("abcge");
+        matcher.match_string("abcdz");
+        matcher.match_string("xyz");
+
+        let stats = matcher.stats();
+        assert_eq!(stats.candidates_tried, 3);
+        assert_eq!(stats.token_hits, vec![2, 1, 1]);
+        assert_eq!(stats.most_tokens_matched, 3);
+        assert_eq!(stats.longest_match, 5);
+
+        matcher.reset_stats();
+        assert_eq!(matcher.stats().candidates_tried, 0);
+        assert_eq!(matcher.most_tokens_matched(), 0);
+    }
+
+    #[test]
+    fn into_owned_outlives_pattern() {
+        let mut matcher = {
+            let match_string = "abc(d|e|f).".to_string();
+            Matcher::new(&match_string).unwrap().into_owned()
+        };
+        assert!(matches!(matcher.pattern.text, Cow::Owned(_)));
+
+        let result = matcher.match_string("abcfz");
+        assert_eq!(result.len(), 3);
+        assert_eq!(matcher.to_string(), "abc(d|e|f).");
+    }
+
+    #[test]
+    fn patterns_match_from_many_threads() {
+        let pattern = Pattern::new("abc(d|e|f).").unwrap();
+        let candidates = ["abcdz", "abcge", "xyz", "abcf"];
+
+        let lengths: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = candidates
+                .iter()
+                .map(|candidate| scope.spawn(|| pattern.match_str(candidate).len()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(lengths, vec![3, 1, 0, 2]);
+
+        let mut matcher = Matcher::new("abc(d|e|f).").unwrap();
+        let expected = matcher.match_str("abcdz").len();
+        assert_eq!(matcher.match_string("abcdz").len(), expected);
+        assert_eq!(matcher.stats().candidates_tried, 1);
+    }
+
+    #[test]
+    fn matcher_set_reports_every_pattern() {
+        let set = MatcherSet::new(["abc(d|e|f).", "ab.", "(x|y)z"]).unwrap();
+
+        assert_eq!(
+            set.matches("abcdz"),
+            vec![
+                SetMatch {
+                    index: 0,
+                    tokens_matched: 3,
+                    is_match: true
+                },
+                SetMatch {
+                    index: 1,
+                    tokens_matched: 2,
+                    is_match: false
+                },
+                SetMatch {
+                    index: 2,
+                    tokens_matched: 0,
+                    is_match: false
+                },
+            ]
+        );
+        assert_eq!(set.best_match("abcdz"), Some(0));
+        assert_eq!(set.best_match("abz"), Some(1));
+        assert_eq!(set.best_match("yz"), Some(2));
+        assert_eq!(set.best_match("nothing"), None);
+
+        assert_eq!(MatcherSet::new(["ok", "(broken"]), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_borrows_from_input() {
+        let match_string = "abc(d|e|f).".to_string();
+        let mut matcher = Matcher::new(&match_string).unwrap();
+        matcher.match_string("abcd");
+
+        let json = serde_json::to_string(&matcher).unwrap();
+        let reloaded: Matcher = serde_json::from_str(&json).unwrap();
+        assert_eq!(reloaded, matcher);
+
+        // The reloaded matcher points into `json`, not into `match_string`.
+        let json_range = json.as_bytes().as_ptr_range();
+        assert!(json_range.contains(&reloaded.pattern.text.as_ptr()));
+        match &reloaded.pattern.branches[0][1] {
+            MatcherToken::OneOfText(alternatives) => match &alternatives[0][0] {
+                MatcherToken::RawText(text) => assert!(json_range.contains(&text.as_ptr())),
+                token => panic!("expected a RawText, got {token:?}"),
+            },
+            token => panic!("expected a OneOfText, got {token:?}"),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_checks_tokens_against_the_text() {
+        use serde_json::{json, Value};
+
+        let reload = |value: &Value| {
+            let json = value.to_string();
+            serde_json::from_str::<Matcher>(&json)
+                .map(|_| ())
+                .map_err(|error| error.to_string())
+        };
+        let matcher = Matcher::new(r"(a)\1").unwrap();
+        let value = serde_json::to_value(&matcher).unwrap();
+        assert_eq!(reload(&value), Ok(()));
+
+        let mut no_group = value.clone();
+        no_group["pattern"]["branches"][0][1] = json!({ "Backreference": 0 });
+        let mut no_branches = value.clone();
+        no_branches["pattern"]["branches"] = json!([]);
+        let mut other_text = value.clone();
+        other_text["pattern"]["text"] = json!(r"(b)\1");
+        for value in [no_group, no_branches, other_text] {
+            let error = reload(&value).unwrap_err();
+            assert!(
+                error.contains("the pattern's tokens aren't the ones its text parses into"),
+                "{error}"
+            );
+        }
+
+        // Text from `parse_lossy` still parses into the same tokens.
+        let (lossy, _) = Matcher::parse_lossy("a)b(c");
+        assert_eq!(reload(&serde_json::to_value(&lossy).unwrap()), Ok(()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn reloaded_stats_with_too_few_token_hits_still_record() {
+        let mut matcher = Matcher::new("a.c").unwrap();
+        matcher.match_string("abc");
+
+        let json = serde_json::to_string(&matcher).unwrap();
+        assert!(json.contains("\"token_hits\":[1,1,1]"));
+        let json = json.replace("\"token_hits\":[1,1,1]", "\"token_hits\":[1]");
+        let mut reloaded: Matcher = serde_json::from_str(&json).unwrap();
+        reloaded.match_string("abc");
+
+        let stats = reloaded.stats();
+        assert_eq!(stats.candidates_tried, 2);
+        assert_eq!(stats.token_hits, vec![2]);
+        assert_eq!(stats.most_tokens_matched, 3);
+    }
+
+    // Candidates and patterns are drawn from a small alphabet, so that
+    // patterns actually match some of the candidates.
+    const PATTERN: &str = r"([ab.|()\\]|\\[dwQE.()|\\])*";
+    const CANDIDATE: &str = "[ab1 ().|]*";
+
+    proptest! {
+        #[test]
+        fn matched_slices_are_a_prefix(
+            pattern in PATTERN.prop_filter("valid", |p| validate_pattern(p)),
+            candidate in CANDIDATE,
+        ) {
+            let pattern = Pattern::parse(&pattern).unwrap();
+            let matched: String = pattern
+                .match_spans(&candidate)
+                .iter()
+                .map(|token_match| token_match.text)
+                .collect();
+            prop_assert!(candidate.starts_with(&matched));
+        }
+
+        #[test]
+        fn display_round_trips(pattern in PATTERN.prop_filter("valid", |p| validate_pattern(p))) {
+            let matcher = Matcher::new(&pattern).unwrap();
+            let shown = matcher.to_string();
+            prop_assert_eq!(Matcher::new(&shown), Some(matcher));
+        }
+
+        #[test]
+        fn most_tokens_matched_never_decreases(
+            pattern in PATTERN.prop_filter("valid", |p| validate_pattern(p)),
+            candidates in prop::collection::vec(CANDIDATE, 0..10),
+        ) {
+            let mut matcher = Matcher::parse(&pattern).unwrap();
+            let mut most = 0;
+            for candidate in &candidates {
+                matcher.match_string(candidate);
+                prop_assert!(matcher.most_tokens_matched() >= most);
+                most = matcher.most_tokens_matched();
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+// Loads lifetimekata_playground.wasm, built by `cargo playground`, and
+// wraps the functions it exports so that they take and return JavaScript
+// values:
+//
+//     import { load } from "./lifetimekata.js";
+//
+//     const kata = await load("lifetimekata_playground.wasm");
+//     kata.match("a(b|c)d", "acd");  // { matches: [{ token, text, start, end }, ...] }
+//     kata.explain("a.c", "ab");     // { trace: "..." }
+//     kata.words("the hound and the fox");
+//     kata.split("a, b, c", ", ");
+//
+// `load` takes the module's URL, or its bytes.
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+export async function load(source) {
+  const { instance } =
+    typeof source === "string" || source instanceof URL
+      ? await WebAssembly.instantiateStreaming(fetch(source))
+      : await WebAssembly.instantiate(source);
+  const wasm = instance.exports;
+
+  // Copies each string into the module's memory, calls `exported` with a
+  // pointer and length for each, and reads back the JSON it leaves.
+  function call(exported, ...strings) {
+    const buffers = strings.map((string) => {
+      const bytes = encoder.encode(string);
+      const ptr = wasm.lk_alloc(bytes.length);
+      new Uint8Array(wasm.memory.buffer, ptr, bytes.length).set(bytes);
+      return [ptr, bytes.length];
+    });
+    try {
+      const len = exported(...buffers.flat());
+      const json = new Uint8Array(wasm.memory.buffer, wasm.lk_result(), len);
+      return JSON.parse(decoder.decode(json));
+    } finally {
+      for (const [ptr, len] of buffers) {
+        wasm.lk_free(ptr, len);
+      }
+    }
+  }
+
+  return {
+    match: (pattern, text) => call(wasm.lk_match, pattern, text),
+    explain: (pattern, text) => call(wasm.lk_explain, pattern, text),
+    words: (text) => call(wasm.lk_words, text),
+    split: (text, delimiter) => call(wasm.lk_split, text, delimiter),
+  };
+}
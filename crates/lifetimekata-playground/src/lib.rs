@@ -0,0 +1,251 @@
+//! The pieces of LifetimeKata worth experimenting with in a browser, built
+//! for `wasm32-unknown-unknown` with `cargo playground`.
+//!
+//! There's no `wasm-bindgen` here, so that the module builds with nothing
+//! but rustup's target. Instead, every function takes strings as a pointer
+//! and a length into the module's memory, written there by JavaScript after
+//! [`lk_alloc`], and leaves its result as JSON, read back with [`lk_result`].
+//! `js/lifetimekata.js` wraps all of that up.
+//!
+//! The same functions are available to Rust as ordinary ones returning
+//! JSON, which is what the tests use.
+
+use std::cell::RefCell;
+
+use lifetimekata_common::{Tokenizer, Words};
+use lifetimekata_matcher::{Matcher, MatcherOptions, MatcherParseError, ParseLimits};
+use serde_json::{json, Value};
+
+/// How big a pattern typed into the playground may be. Anyone can type
+/// anything, and the module has a small stack, so these are well inside
+/// what the matcher allows by default.
+const PLAYGROUND_LIMITS: ParseLimits = ParseLimits {
+    max_tokens: 1_000,
+    max_alternatives: 100,
+    max_depth: 32,
+};
+
+/// Parses `pattern`, as long as it stays within [`PLAYGROUND_LIMITS`].
+fn parse(pattern: &str) -> Result<Matcher<'_>, MatcherParseError> {
+    Matcher::with_limits(pattern, MatcherOptions::default(), PLAYGROUND_LIMITS)
+}
+
+/// The tokens of `pattern`, each with the part of `text` which it matched
+/// and where, as
+/// `{"matches": [{"token": "...", "text": "...", "start": 0, "end": 3}]}`;
+/// or `{"error": "..."}`, if the pattern doesn't parse or is too big.
+pub fn match_pattern(pattern: &str, text: &str) -> Value {
+    let mut matcher = match parse(pattern) {
+        Ok(matcher) => matcher,
+        Err(error) => return json!({ "error": error.to_string() }),
+    };
+    let matches: Vec<Value> = matcher
+        .match_spans(text)
+        .into_iter()
+        .map(|token_match| {
+            json!({
+                "token": token_match.token.to_string(),
+                "text": token_match.text,
+                "start": token_match.span.start,
+                "end": token_match.span.end,
+            })
+        })
+        .collect();
+    json!({ "matches": matches })
+}
+
+/// A readable trace of matching `pattern` against `text`, token by token,
+/// as `{"trace": "..."}`; or `{"error": "..."}`.
+pub fn explain(pattern: &str, text: &str) -> Value {
+    match parse(pattern) {
+        Ok(mut matcher) => json!({ "trace": matcher.explain(text).to_string() }),
+        Err(error) => json!({ "error": error.to_string() }),
+    }
+}
+
+/// `text` split into words, the way the exercises do.
+pub fn words(text: &str) -> Value {
+    json!(Words.tokenize(text))
+}
+
+/// `text` split at each `delimiter`, by the solution to chapter 2.
+pub fn split(text: &str, delimiter: &str) -> Value {
+    json!(soln02::split(text, delimiter))
+}
+
+thread_local! {
+    /// The JSON returned by the last call, for [`lk_result`] to hand over.
+    static RESULT: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Keeps `value` for [`lk_result`], returning its length in bytes.
+fn keep(value: Value) -> usize {
+    RESULT.with(|result| {
+        *result.borrow_mut() = value.to_string();
+        result.borrow().len()
+    })
+}
+
+/// The string at `ptr`, as JavaScript wrote it.
+///
+/// # Safety
+///
+/// `ptr` must point to `len` bytes of UTF-8 which stay put while the
+/// returned string is used.
+unsafe fn text<'memory>(ptr: *const u8, len: usize) -> &'memory str {
+    if len == 0 {
+        return "";
+    }
+    // SAFETY: the caller promises that these are `len` bytes of UTF-8.
+    unsafe { std::str::from_utf8_unchecked(std::slice::from_raw_parts(ptr, len)) }
+}
+
+/// Makes room for `len` bytes, for JavaScript to write a string into.
+#[no_mangle]
+pub extern "C" fn lk_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let ptr = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    ptr
+}
+
+/// Gives back room made by [`lk_alloc`].
+///
+/// # Safety
+///
+/// `ptr` and `len` must be exactly as given to and returned by one call of
+/// [`lk_alloc`], and not given back already.
+#[no_mangle]
+pub unsafe extern "C" fn lk_free(ptr: *mut u8, len: usize) {
+    // SAFETY: the caller promises that this is a buffer from `lk_alloc`.
+    drop(unsafe { Vec::from_raw_parts(ptr, 0, len) });
+}
+
+/// Where the last call left its JSON; it's as long as that call said.
+#[no_mangle]
+pub extern "C" fn lk_result() -> *const u8 {
+    RESULT.with(|result| result.borrow().as_ptr())
+}
+
+/// [`match_pattern`], returning the length of its JSON.
+///
+/// # Safety
+///
+/// Each pointer must point to as many bytes of UTF-8 as its length says.
+#[no_mangle]
+pub unsafe extern "C" fn lk_match(
+    pattern: *const u8,
+    pattern_len: usize,
+    text_ptr: *const u8,
+    text_len: usize,
+) -> usize {
+    // SAFETY: passed on from the caller.
+    let (pattern, text_) = unsafe { (text(pattern, pattern_len), text(text_ptr, text_len)) };
+    keep(match_pattern(pattern, text_))
+}
+
+/// [`explain`], returning the length of its JSON.
+///
+/// # Safety
+///
+/// Each pointer must point to as many bytes of UTF-8 as its length says.
+#[no_mangle]
+pub unsafe extern "C" fn lk_explain(
+    pattern: *const u8,
+    pattern_len: usize,
+    text_ptr: *const u8,
+    text_len: usize,
+) -> usize {
+    // SAFETY: passed on from the caller.
+    let (pattern, text_) = unsafe { (text(pattern, pattern_len), text(text_ptr, text_len)) };
+    keep(explain(pattern, text_))
+}
+
+/// [`words`], returning the length of its JSON.
+///
+/// # Safety
+///
+/// `text_ptr` must point to `text_len` bytes of UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn lk_words(text_ptr: *const u8, text_len: usize) -> usize {
+    // SAFETY: passed on from the caller.
+    keep(words(unsafe { text(text_ptr, text_len) }))
+}
+
+/// [`split`], returning the length of its JSON.
+///
+/// # Safety
+///
+/// Each pointer must point to as many bytes of UTF-8 as its length says.
+#[no_mangle]
+pub unsafe extern "C" fn lk_split(
+    text_ptr: *const u8,
+    text_len: usize,
+    delimiter: *const u8,
+    delimiter_len: usize,
+) -> usize {
+    // SAFETY: passed on from the caller.
+    let (text_, delimiter) = unsafe { (text(text_ptr, text_len), text(delimiter, delimiter_len)) };
+    keep(split(text_, delimiter))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matches_say_where_they_are() {
+        assert_eq!(
+            match_pattern("a.c", "abcd"),
+            json!({ "matches": [
+                { "token": "a", "text": "a", "start": 0, "end": 1 },
+                { "token": ".", "text": "b", "start": 1, "end": 2 },
+                { "token": "c", "text": "c", "start": 2, "end": 3 },
+            ]})
+        );
+        assert!(match_pattern("(a", "a")["error"].is_string());
+    }
+
+    #[test]
+    fn huge_patterns_are_refused() {
+        let deep = "(".repeat(200_000);
+        assert_eq!(
+            match_pattern(&deep, "a")["error"],
+            "this goes over the limit of 32 nested groups (at byte 32)"
+        );
+        assert_eq!(
+            explain(&"a.".repeat(1_000), "a")["error"],
+            "this goes over the limit of 1000 tokens (at byte 1000)"
+        );
+        let alternatives = format!("({})", "a|".repeat(100));
+        assert!(match_pattern(&alternatives, "a")["error"].is_string());
+    }
+
+    #[test]
+    fn results_are_handed_over_through_memory() {
+        let (text_, delimiter) = ("one, two, ", ", ");
+        let len = unsafe {
+            lk_split(
+                text_.as_ptr(),
+                text_.len(),
+                delimiter.as_ptr(),
+                delimiter.len(),
+            )
+        };
+        let result = unsafe { text(lk_result(), len) };
+        assert_eq!(result, r#"["one","two"]"#);
+
+        let len = unsafe { lk_words(text_.as_ptr(), 3) };
+        assert_eq!(unsafe { text(lk_result(), len) }, r#"["one"]"#);
+    }
+
+    #[test]
+    fn memory_can_be_given_back() {
+        let ptr = lk_alloc(16);
+        unsafe {
+            ptr.copy_from(b"sixteen bytes!!!".as_ptr(), 16);
+            assert_eq!(text(ptr, 16), "sixteen bytes!!!");
+            lk_free(ptr, 16);
+        }
+    }
+}
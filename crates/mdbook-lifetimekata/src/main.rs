@@ -0,0 +1,257 @@
+//! An mdBook preprocessor which fills in `{{#kata path:anchor}}` with code
+//! from the LifetimeKata crates, so that the book can't drift from them.
+//!
+//! `path` is relative to the repository, and `anchor` names the lines
+//! between `// ANCHOR: anchor` and `// ANCHOR_END: anchor` in that file,
+//! as with mdBook's own `{{#include}}`; without an anchor, the whole file is
+//! used. Every crate a snippet comes from is then checked with
+//! `cargo check`, and if one doesn't compile, neither does the book.
+//!
+//! Exercise crates don't compile until they're done, so they're left to
+//! `cargo xtask check-consistency` instead.
+//!
+//! mdBook talks to preprocessors over stdin and stdout: `supports
+//! <renderer>` asks whether a renderer is supported, and otherwise the
+//! preprocessor reads `[context, book]` as JSON, and writes the book back.
+
+use std::collections::BTreeSet;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+use serde_json::Value;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "supports") {
+        return ExitCode::SUCCESS;
+    }
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("mdbook-lifetimekata: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|e| e.to_string())?;
+    let [context, mut book]: [Value; 2] = serde_json::from_str(&input)
+        .map_err(|error| format!("mdBook didn't send [context, book]: {error}"))?;
+    // The book lives in `book/`, at the top of the repository.
+    let book_root = PathBuf::from(context["root"].as_str().unwrap_or("."));
+    let root = book_root.join("..");
+
+    let mut sources = BTreeSet::new();
+    // Older mdBooks call the chapters `sections`, and newer ones `items`.
+    for key in ["sections", "items"] {
+        if let Some(items) = book.get_mut(key) {
+            expand_items(items, &root, &mut sources)?;
+        }
+    }
+    check_crates(&root, &sources)?;
+    serde_json::to_writer(io::stdout(), &book).map_err(|e| e.to_string())
+}
+
+/// Expands the directives in every chapter among `items`, and their
+/// sub-chapters, noting the files used in `sources`.
+fn expand_items(
+    items: &mut Value,
+    root: &Path,
+    sources: &mut BTreeSet<PathBuf>,
+) -> Result<(), String> {
+    for item in items.as_array_mut().into_iter().flatten() {
+        let Some(chapter) = item.get_mut("Chapter") else {
+            continue;
+        };
+        if let Some(content) = chapter["content"].as_str() {
+            let name = chapter["name"].as_str().unwrap_or("a chapter").to_string();
+            let expanded = expand(content, root, sources).map_err(|e| format!("{name}: {e}"))?;
+            chapter["content"] = Value::String(expanded);
+        }
+        if let Some(sub_items) = chapter.get_mut("sub_items") {
+            expand_items(sub_items, root, sources)?;
+        }
+    }
+    Ok(())
+}
+
+const OPEN: &str = "{{#kata ";
+const CLOSE: &str = "}}";
+
+/// `content` with each `{{#kata path:anchor}}` replaced by the code it
+/// names, noting the files used in `sources`.
+fn expand(content: &str, root: &Path, sources: &mut BTreeSet<PathBuf>) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = content;
+    while let Some(start) = rest.find(OPEN) {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + OPEN.len()..];
+        let end = after
+            .find(CLOSE)
+            .ok_or_else(|| format!("`{OPEN}` isn't closed with `{CLOSE}`"))?;
+        let target = after[..end].trim();
+        let (path, anchor) = match target.rsplit_once(':') {
+            Some((path, anchor)) => (path, Some(anchor)),
+            None => (target, None),
+        };
+        let file = root.join(path);
+        let source = std::fs::read_to_string(&file).map_err(|error| format!("{path}: {error}"))?;
+        let code = match anchor {
+            Some(anchor) => anchored(&source, anchor)
+                .ok_or_else(|| format!("{path} has no `// ANCHOR: {anchor}`"))?,
+            None => without_anchors(&source),
+        };
+        expanded.push_str(code.trim_end_matches('\n'));
+        sources.insert(file);
+        rest = &after[end + CLOSE.len()..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Whether `line` is an anchor comment, like `// ANCHOR: name`.
+fn anchor_of(line: &str) -> Option<(bool, &str)> {
+    let comment = line.trim().strip_prefix("//")?.trim();
+    if let Some(name) = comment.strip_prefix("ANCHOR:") {
+        Some((true, name.trim()))
+    } else {
+        comment
+            .strip_prefix("ANCHOR_END:")
+            .map(|name| (false, name.trim()))
+    }
+}
+
+/// The lines of `source` between the start and end of `anchor`, without
+/// any other anchors, and with the indentation they share taken off.
+fn anchored(source: &str, anchor: &str) -> Option<String> {
+    let mut lines = source.lines();
+    lines.find(|line| anchor_of(line) == Some((true, anchor)))?;
+    let inside: Vec<&str> = lines
+        .take_while(|line| anchor_of(line) != Some((false, anchor)))
+        .filter(|line| anchor_of(line).is_none())
+        .collect();
+    let indent = inside
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut code = String::new();
+    for line in inside {
+        code.push_str(line.get(indent..).unwrap_or("").trim_end());
+        code.push('\n');
+    }
+    Some(code)
+}
+
+fn without_anchors(source: &str) -> String {
+    source
+        .lines()
+        .filter(|line| anchor_of(line).is_none())
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+/// Checks the crate of every file in `sources`, other than the exercises.
+fn check_crates(root: &Path, sources: &BTreeSet<PathBuf>) -> Result<(), String> {
+    let mut packages = BTreeSet::new();
+    for source in sources {
+        let Some(manifest) = source
+            .ancestors()
+            .map(|dir| dir.join("Cargo.toml"))
+            .find(|manifest| manifest.is_file())
+        else {
+            continue;
+        };
+        let is_exercise = manifest
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|dir| dir == "exercise");
+        if is_exercise {
+            continue;
+        }
+        let text = std::fs::read_to_string(&manifest).map_err(|e| e.to_string())?;
+        if let Some(package) = package_name(&text) {
+            packages.insert(package.to_string());
+        }
+    }
+
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    for package in packages {
+        let output = Command::new(&cargo)
+            .current_dir(root)
+            .args(["check", "--quiet", "--all-targets", "--package", &package])
+            .output()
+            .map_err(|e| e.to_string())?;
+        if !output.status.success() {
+            return Err(format!(
+                "the book shows code from {package}, which doesn't compile:\n{}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The `name` in a manifest's `[package]` table.
+fn package_name(manifest: &str) -> Option<&str> {
+    manifest
+        .lines()
+        .skip_while(|line| line.trim() != "[package]")
+        .skip(1)
+        .take_while(|line| !line.trim_start().starts_with('['))
+        .find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "name").then(|| value.trim().trim_matches('"'))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = "\
+use std::fmt;
+
+// ANCHOR: split
+pub fn split<'a>(text: &'a str) -> Vec<&'a str> {
+    // ANCHOR: body
+    text.split(' ').collect()
+    // ANCHOR_END: body
+}
+// ANCHOR_END: split
+";
+
+    #[test]
+    fn anchors_pick_out_lines() {
+        assert_eq!(
+            anchored(SOURCE, "split").unwrap(),
+            "pub fn split<'a>(text: &'a str) -> Vec<&'a str> {\n    \
+             text.split(' ').collect()\n}\n"
+        );
+        assert_eq!(
+            anchored(SOURCE, "body").unwrap(),
+            "text.split(' ').collect()\n"
+        );
+        assert_eq!(anchored(SOURCE, "missing"), None);
+    }
+
+    #[test]
+    fn directives_are_replaced() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let mut sources = BTreeSet::new();
+        let content = "Before\n```rust\n{{#kata Cargo.toml}}\n```\nAfter";
+        let expanded = expand(content, root, &mut sources).unwrap();
+        assert!(expanded.starts_with("Before\n```rust\n[package]\n"));
+        assert!(expanded.ends_with("serde_json = \"1\"\n```\nAfter"));
+        assert_eq!(sources.len(), 1);
+
+        let error = expand("{{#kata Cargo.toml:nowhere}}", root, &mut sources).unwrap_err();
+        assert_eq!(error, "Cargo.toml has no `// ANCHOR: nowhere`");
+    }
+}
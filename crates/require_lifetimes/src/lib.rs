@@ -0,0 +1,160 @@
+//! The `#[require_lifetimes]` attribute used by the LifetimeKata exercises.
+//!
+//! Putting the attribute on a function makes it a compile error to leave out
+//! a lifetime that the compiler would otherwise have filled in by elision.
+//! Each error points at the reference that needs a name, and suggests one.
+//!
+//! On an `impl` block or a `trait`, every method inside is checked, whether
+//! or not it has a body; a method can opt out with
+//! `#[require_lifetimes(skip)]`, or give its own arguments by having its own
+//! attribute.
+//!
+//! Trait impls are checked like any other `impl` block; writing out a
+//! lifetime the trait elided still matches the trait. On an `extern` block,
+//! every function declared inside is checked, or the attribute can go on
+//! just one of them.
+//!
+//! On an inline `mod`, every function, method and nested module inside is
+//! checked in the same way. Rust doesn't yet allow `#![require_lifetimes]`
+//! as an inner attribute, so the attribute goes on the `mod` itself:
+//!
+//! ```ignore
+//! #[require_lifetimes]
+//! mod exercise {
+//!     // ...
+//! }
+//! ```
+//!
+//! Other attributes, like `#[inline]`, `#[track_caller]` and doc comments,
+//! are kept as they are and can come in any order. An attribute from this
+//! crate behind `cfg_attr` on something inside the item is taken as that
+//! item's own, whether or not its condition holds.
+//!
+//! A returned `impl Trait` has to say which lifetimes it captures, with
+//! `+ 'a` or `use<'a>`, if any argument has a lifetime it could borrow.
+//!
+//! `'_` counts as naming a lifetime, unless `deny_anonymous` is given; then
+//! it needs a real name too. `allow_anonymous` asks for the default
+//! explicitly.
+//!
+//! `'static` is allowed in types unless `deny_static` is given, for exercises
+//! which shouldn't be solvable by making everything `'static`; bounds like
+//! `T: 'static` are still fine. `allow_static` asks for the default.
+//!
+//! `return_only` only asks for lifetimes in the return type, leaving the
+//! arguments free to elide theirs. `except(self, out, x)` is finer-grained:
+//! it lets the receiver, the return type and the argument `x` elide theirs,
+//! so an exercise can allow the usual `&self` while asking for the rest.
+//!
+//! `strict` also rejects what a solution doesn't need: lifetime parameters
+//! which aren't used, and `'a: 'b` bounds which always hold or follow from
+//! the other bounds.
+//!
+//! `deny_implied_bounds` rejects `'a: 'b` bounds which the types already
+//! imply, like the one in `fn f<'a: 'b, 'b>(x: &'b &'a str)`, since a
+//! `&'b &'a str` can't exist unless `'a` outlives `'b` anyway.
+//! `allow_implied_bounds` asks for the default.
+//!
+//! `descriptive_names` rejects lifetimes named with a single letter, and
+//! `names("'input", "'output")` rejects any lifetime not in the list; either
+//! way, suggestions use names which would pass.
+//!
+//! Alongside the errors, a note shows the signature as the elision rules
+//! would have filled it in, naming the rule that did it. `no_notes` leaves
+//! that out, for exercises where working it out is the point.
+//!
+//! Every message starts with a code like `[LK0001]`, which stays the same
+//! when the wording changes, so tools can tell the diagnostics apart. With
+//! `json`, or with `LIFETIMEKATA_DIAGNOSTICS=json` set for every macro in
+//! the crate, each message is instead a JSON object like
+//! `{"code":"LK0001","message":"..."}`. Cargo doesn't know the macros read
+//! that variable, so changing it doesn't rebuild anything by itself.
+//!
+//! The codes are:
+//!
+//! | Code     | Meaning                                                    |
+//! |----------|------------------------------------------------------------|
+//! | `LK0001` | a lifetime in an argument, or in `&self`, was elided       |
+//! | `LK0002` | a lifetime in the return type was elided                   |
+//! | `LK0003` | a lifetime in `Fn(..)` or `fn(..)` was elided              |
+//! | `LK0004` | `'_` was used with `deny_anonymous`                        |
+//! | `LK0005` | `'static` was used with `deny_static`                      |
+//! | `LK0006` | a lifetime's name is one letter, with `descriptive_names`  |
+//! | `LK0007` | a lifetime's name isn't one of those given to `names(...)` |
+//! | `LK0008` | a note on what elision would have made of the signature    |
+//! | `LK0009` | a lifetime was written which elision would have filled in  |
+//! | `LK0010` | the attribute was misused                                  |
+//! | `LK0011` | two fields share a lifetime, with `separate`               |
+//! | `LK0012` | a warning showing the signature with elision               |
+//! | `LK0013` | a returned `impl Trait` doesn't say what it captures       |
+//! | `LK0014` | a lifetime parameter isn't used, with `strict`             |
+//! | `LK0015` | an `'a: 'b` bound isn't needed, with `strict`              |
+//! | `LK0016` | an `'a: 'b` bound is implied by the types                  |
+//!
+//! `#[elide_lifetimes]` checks the opposite: it's an error to write a
+//! lifetime which elision would have filled in. Between the two, an exercise
+//! can ask for exactly the annotations that are needed.
+//!
+//! `#[derive(RequireFieldLifetimes)]` does the same for the fields of a
+//! struct or enum: `'_` isn't allowed in them, and with
+//! `#[field_lifetimes(separate)]` on the type, no two fields of a struct (or
+//! of one enum variant) may share a lifetime.
+//!
+//! Closures can't have attributes in expression position, so they are
+//! checked with `require_closure_lifetimes!(|x: &'a str| -> &'a str { x })`
+//! instead. Any lifetime a closure names has to come from the function
+//! around it, and arguments without a type are left to inference.
+//!
+//! `#[show_elided]` goes the other way for learners comparing the two
+//! forms: on a function whose lifetimes are all written, it warns with the
+//! signature as elision would let it be written.
+//!
+//! `#[require_lifetimes(!)]` means the same as `#[require_lifetimes]`; the
+//! exercises were written with that spelling, so it is still accepted.
+//!
+//! The checks themselves are in the `require_lifetimes_core` crate, which
+//! can run them on a parsed signature without going through rustc.
+
+use proc_macro::TokenStream;
+use require_lifetimes_core::expand;
+
+#[proc_macro_attribute]
+pub fn require_lifetimes(args: TokenStream, item: TokenStream) -> TokenStream {
+    expand::require_lifetimes(args.into(), item.into()).into()
+}
+
+/// The opposite of `#[require_lifetimes]`: a compile error for every
+/// lifetime in the signature which the elision rules would have filled in.
+///
+/// Like `#[require_lifetimes]`, it can go on an `impl` block, trait or
+/// inline module, and anything inside with an attribute of its own is left
+/// alone.
+#[proc_macro_attribute]
+pub fn elide_lifetimes(args: TokenStream, item: TokenStream) -> TokenStream {
+    expand::elide_lifetimes(args.into(), item.into()).into()
+}
+
+/// Checks the lifetimes on the fields of a struct or enum: a compile error
+/// for every `'_`, and with `#[field_lifetimes(separate)]`, for every
+/// lifetime shared between fields. Nothing else is generated.
+#[proc_macro_derive(RequireFieldLifetimes, attributes(field_lifetimes))]
+pub fn require_field_lifetimes(input: TokenStream) -> TokenStream {
+    expand::require_field_lifetimes(input.into()).into()
+}
+
+/// `#[require_lifetimes]` for a closure: a compile error for every reference
+/// in its argument or return types which doesn't name its lifetime.
+#[proc_macro]
+pub fn require_closure_lifetimes(input: TokenStream) -> TokenStream {
+    expand::require_closure_lifetimes(input.into()).into()
+}
+
+/// Shows, as a warning, what an explicit signature would look like with
+/// every lifetime that elision could fill in left out.
+///
+/// Like the other attributes, it can go on an `impl` block, trait or inline
+/// module.
+#[proc_macro_attribute]
+pub fn show_elided(args: TokenStream, item: TokenStream) -> TokenStream {
+    expand::show_elided(args.into(), item.into()).into()
+}
@@ -0,0 +1,6 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+fn argument(_x: &str, _y: &mut u8) {}
+
+fn main() {}
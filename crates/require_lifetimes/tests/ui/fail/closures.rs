@@ -0,0 +1,6 @@
+use require_lifetimes::require_closure_lifetimes;
+
+fn main() {
+    let first = require_closure_lifetimes!(|x: &str| -> &str { x });
+    let _ = first("word");
+}
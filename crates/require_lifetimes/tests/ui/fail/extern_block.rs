@@ -0,0 +1,8 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+extern "C" {
+    fn first(bytes: &[u8; 4]) -> &u8;
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use require_lifetimes::RequireFieldLifetimes;
+
+#[derive(RequireFieldLifetimes)]
+#[field_lifetimes(separate)]
+struct Split<'a> {
+    first: &'a str,
+    rest: &'a str,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+fn words<'a>(text: &'a str) -> impl Iterator<Item = &'a str> {
+    text.split(' ')
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes(deny_implied_bounds)]
+fn inner<'a, 'b>(x: &'b &'a str) -> &'b str
+where
+    'a: 'b,
+{
+    x
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+trait LendingIterator {
+    type Item<'a>
+    where
+        Self: 'a;
+
+    fn next(&mut self) -> Option<Self::Item<'_>>;
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+fn nested_generics(x: Vec<&str>) -> Option<(&str, [&str; 1])> {
+    x.first().map(|first| (*first, [*first]))
+}
+
+#[require_lifetimes]
+fn nested_arguments(_x: Option<&mut u8>, _y: (&u8, [&u8; 2])) {}
+
+fn main() {}
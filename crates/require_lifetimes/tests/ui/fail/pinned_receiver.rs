@@ -0,0 +1,16 @@
+use std::pin::Pin;
+
+use require_lifetimes::require_lifetimes;
+
+struct Buffer {
+    bytes: Vec<u8>,
+}
+
+#[require_lifetimes]
+impl Buffer {
+    fn first(self: Pin<&mut Self>) -> Option<&u8> {
+        self.get_mut().bytes.first()
+    }
+}
+
+fn main() {}
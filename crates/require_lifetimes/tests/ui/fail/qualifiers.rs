@@ -0,0 +1,8 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+const unsafe extern "C" fn everything(x: &u8) -> &u8 {
+    x
+}
+
+fn main() {}
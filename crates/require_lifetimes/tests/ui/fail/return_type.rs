@@ -0,0 +1,8 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+fn return_type<'a>(x: &'a str) -> &str {
+    x
+}
+
+fn main() {}
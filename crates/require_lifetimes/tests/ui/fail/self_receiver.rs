@@ -0,0 +1,16 @@
+use require_lifetimes::require_lifetimes;
+
+struct Thing(String);
+
+#[require_lifetimes]
+impl Thing {
+    fn shared(&self) -> &str {
+        &self.0
+    }
+
+    fn exclusive(&mut self) {}
+
+    fn typed(self: &Self) {}
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+#![deny(deprecated)]
+
+use require_lifetimes::show_elided;
+
+#[show_elided]
+fn first<'a, 'b>(words: &'a [String], _count: &'b usize) -> &'a str {
+    words[0]
+}
+
+#[show_elided]
+fn longest<'a>(x: &'a str, y: &'a str) -> &'a str {
+    if x.len() > y.len() {
+        x
+    } else {
+        y
+    }
+}
+
+fn main() {}
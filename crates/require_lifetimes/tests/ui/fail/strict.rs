@@ -0,0 +1,15 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes(strict)]
+fn first<'a, 'b: 'a, 'unused>(x: &'a str, y: &'b str) -> &'a str
+where
+    'b: 'a,
+{
+    if x.is_empty() {
+        y
+    } else {
+        x
+    }
+}
+
+fn main() {}
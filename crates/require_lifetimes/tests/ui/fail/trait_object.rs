@@ -0,0 +1,11 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+fn trait_object<'a>(
+    _f: Box<dyn Fn(&str) -> &str>,
+    items: &'a [u8],
+) -> Box<dyn Iterator<Item = &u8> + 'a> {
+    Box::new(items.iter())
+}
+
+fn main() {}
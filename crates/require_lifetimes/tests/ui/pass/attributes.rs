@@ -0,0 +1,35 @@
+use require_lifetimes::require_lifetimes;
+
+struct Counter {
+    count: u32,
+}
+
+#[require_lifetimes]
+impl Counter {
+    #[cfg_attr(all(), require_lifetimes(skip))]
+    fn enabled(&self) -> &u32 {
+        &self.count
+    }
+
+    /// Documented.
+    #[inline]
+    #[cfg_attr(all(), track_caller)]
+    fn checked<'a>(&'a self) -> &'a u32 {
+        &self.count
+    }
+}
+
+#[inline(always)]
+#[require_lifetimes]
+#[track_caller]
+/// Attributes on either side of the macro are kept.
+#[cfg_attr(any(), deprecated)]
+fn first<'a>(words: &'a [String]) -> &'a str {
+    &words[0]
+}
+
+fn main() {
+    let counter = Counter { count: 1 };
+    assert_eq!(*counter.enabled(), *counter.checked());
+    assert_eq!(first(&["a".to_string()]), "a");
+}
@@ -0,0 +1,18 @@
+use require_lifetimes::require_closure_lifetimes;
+
+fn longest<'a>(words: &[&'a str]) -> Option<&'a str> {
+    let longer = require_closure_lifetimes!(|x: &'a str, y: &'a str| -> &'a str {
+        if y.len() > x.len() {
+            y
+        } else {
+            x
+        }
+    });
+    words.iter().copied().reduce(longer)
+}
+
+fn main() {
+    assert_eq!(longest(&["a", "abc", "ab"]), Some("abc"));
+    let double = require_closure_lifetimes!(|x| x * 2);
+    assert_eq!(double(2), 4);
+}
@@ -0,0 +1,23 @@
+use require_lifetimes::RequireFieldLifetimes;
+
+#[derive(RequireFieldLifetimes)]
+#[field_lifetimes(separate)]
+struct Split<'first, 'rest> {
+    first: &'first str,
+    rest: &'rest str,
+}
+
+#[derive(RequireFieldLifetimes)]
+enum Token<'a> {
+    Word(&'a str),
+    Pair(&'a str, &'a str),
+}
+
+fn main() {
+    let split = Split {
+        first: "a",
+        rest: "b c",
+    };
+    let _ = (split.first, split.rest);
+    let _ = [Token::Word("a"), Token::Pair("b", "c")];
+}
@@ -0,0 +1,53 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+fn arguments<'a, 'b>(x: &'a str, y: &'b mut Vec<u8>) -> usize {
+    y.push(0);
+    x.len()
+}
+
+#[require_lifetimes]
+fn return_type<'a>(x: &'a str) -> &'a str {
+    x
+}
+
+#[require_lifetimes]
+fn nested_generics<'a, 'b>(x: Vec<&'a str>, y: Option<&'b mut u8>) -> (&'a str, [&'a str; 1]) {
+    let _ = y;
+    (x[0], [x[0]])
+}
+
+#[require_lifetimes]
+fn trait_objects<'a>(
+    f: Box<dyn Fn(&'a str) -> &'a str + 'a>,
+) -> Box<dyn Fn(&'a str) -> &'a str + 'a> {
+    f
+}
+
+#[require_lifetimes]
+fn captures<'a>(text: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    text.split(' ')
+}
+
+#[require_lifetimes]
+fn precise_captures<'a>(text: &'a str) -> impl Iterator<Item = char> + use<'a> {
+    text.chars()
+}
+
+#[require_lifetimes]
+fn higher_ranked<F>(f: F) -> usize
+where
+    F: for<'a> Fn(&'a str) -> &'a str,
+{
+    f("").len()
+}
+
+fn main() {
+    arguments("", &mut Vec::new());
+    return_type("");
+    nested_generics(vec![""], None);
+    let _ = trait_objects(Box::new(|x| x));
+    higher_ranked(|x| x);
+    assert_eq!(captures("a b").count(), 2);
+    assert_eq!(precise_captures("ab").count(), 2);
+}
@@ -0,0 +1,41 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+trait LendingIterator {
+    type Item<'a>
+    where
+        Self: 'a;
+
+    fn next<'a>(&'a mut self) -> Option<Self::Item<'a>>;
+}
+
+struct WindowsMut<'t> {
+    slice: &'t mut [u8],
+    start: usize,
+}
+
+#[require_lifetimes]
+impl<'t> LendingIterator for WindowsMut<'t> {
+    type Item<'a>
+        = &'a mut [u8]
+    where
+        Self: 'a;
+
+    fn next<'a>(&'a mut self) -> Option<Self::Item<'a>> {
+        let window = self.slice.get_mut(self.start..self.start + 2)?;
+        self.start += 1;
+        Some(window)
+    }
+}
+
+fn main() {
+    let mut data = [1, 2, 3];
+    let mut windows = WindowsMut {
+        slice: &mut data,
+        start: 0,
+    };
+    while let Some(window) = windows.next() {
+        window[1] += window[0];
+    }
+    assert_eq!(data, [1, 3, 6]);
+}
@@ -0,0 +1,67 @@
+use std::pin::Pin;
+use std::rc::Rc;
+
+use require_lifetimes::require_lifetimes;
+
+struct Counter {
+    count: u32,
+}
+
+#[require_lifetimes]
+impl Counter {
+    fn get<'a>(&'a self) -> &'a u32 {
+        &self.count
+    }
+
+    fn bump<'a>(self: &'a mut Self) {
+        self.count += 1;
+    }
+
+    #[require_lifetimes(skip)]
+    fn skipped(&self) -> &u32 {
+        &self.count
+    }
+
+    #[require_lifetimes(except(self))]
+    fn larger<'a>(&self, other: &'a u32) -> &'a u32 {
+        if self.count > *other {
+            &0
+        } else {
+            other
+        }
+    }
+}
+
+#[require_lifetimes]
+impl Counter {
+    fn pinned<'a>(self: Pin<&'a mut Self>) -> &'a mut u32 {
+        &mut self.get_mut().count
+    }
+
+    fn boxed(self: Box<Self>) -> u32 {
+        self.count
+    }
+
+    fn shared(self: Rc<Self>) -> u32 {
+        self.count
+    }
+}
+
+#[require_lifetimes]
+trait Named {
+    fn name<'a>(&'a self) -> &'a str;
+
+    fn greeting<'a>(&'a self) -> String {
+        format!("hello, {}", self.name())
+    }
+}
+
+fn main() {
+    let mut counter = Counter { count: 0 };
+    counter.bump();
+    assert_eq!(*counter.get(), *counter.skipped());
+    assert_eq!(*counter.larger(&2), 2);
+    *Pin::new(&mut counter).pinned() += 1;
+    assert_eq!(Rc::new(Counter { count: 3 }).shared(), 3);
+    assert_eq!(Box::new(counter).boxed(), 2);
+}
@@ -0,0 +1,49 @@
+use require_lifetimes::require_lifetimes;
+
+#[require_lifetimes]
+const fn constant<'a>(x: &'a [u8]) -> &'a [u8] {
+    x
+}
+
+#[require_lifetimes]
+unsafe fn not_safe<'a>(x: *const u8, _marker: &'a u8) -> &'a u8 {
+    &*x
+}
+
+#[require_lifetimes]
+extern "C" fn foreign<'a>(x: &'a u8) -> &'a u8 {
+    x
+}
+
+#[require_lifetimes]
+pub(crate) const unsafe extern "C" fn everything<'a>(x: &'a u8) -> &'a u8 {
+    x
+}
+
+struct Holder(u8);
+
+#[require_lifetimes]
+impl Holder {
+    const fn get<'a>(&'a self) -> &'a u8 {
+        &self.0
+    }
+
+    unsafe extern "system" fn raw<'a>(&'a self) -> *const u8 {
+        &self.0
+    }
+}
+
+const CONSTANT: &[u8] = constant(b"const");
+
+fn main() {
+    let x = 1;
+    let holder = Holder(2);
+    unsafe {
+        assert_eq!(*not_safe(&x, &x), 1);
+        assert_eq!(*everything(&x), 1);
+        assert_eq!(*holder.raw(), 2);
+    }
+    assert_eq!(*foreign(&x), 1);
+    assert_eq!(*holder.get(), 2);
+    assert_eq!(CONSTANT, b"const");
+}
@@ -0,0 +1,35 @@
+#![allow(deprecated)]
+
+use require_lifetimes::show_elided;
+
+struct Counter {
+    count: u32,
+}
+
+#[show_elided]
+impl Counter {
+    fn get<'a>(&'a self) -> &'a u32 {
+        &self.count
+    }
+
+    #[show_elided]
+    fn larger<'a>(&self, other: &'a u32) -> &'a u32 {
+        if self.count > *other {
+            &0
+        } else {
+            other
+        }
+    }
+}
+
+#[show_elided]
+fn first<'a>(words: &'a [&'a str]) -> &'a str {
+    words[0]
+}
+
+fn main() {
+    let counter = Counter { count: 1 };
+    assert_eq!(*counter.get(), 1);
+    assert_eq!(*counter.larger(&5), 5);
+    assert_eq!(first(&["a", "b"]), "a");
+}
@@ -0,0 +1,28 @@
+use std::fmt;
+
+use require_lifetimes::require_lifetimes;
+
+struct Word<'text> {
+    text: &'text str,
+}
+
+#[require_lifetimes]
+impl<'text> fmt::Display for Word<'text> {
+    fn fmt<'a, 'b, 'c>(&'a self, f: &'b mut fmt::Formatter<'c>) -> fmt::Result {
+        f.write_str(self.text)
+    }
+}
+
+#[require_lifetimes]
+extern "C" {
+    fn first<'a>(bytes: &'a [u8; 4]) -> &'a u8;
+}
+
+extern "C" {
+    #[require_lifetimes]
+    fn last<'a>(bytes: &'a [u8; 4]) -> &'a u8;
+}
+
+fn main() {
+    assert_eq!(Word { text: "word" }.to_string(), "word");
+}
@@ -0,0 +1,166 @@
+//! The arguments that can be given to `#[require_lifetimes(...)]`.
+
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, Ident, LitStr, Token};
+
+/// What the attribute was asked to do, parsed from its arguments.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Options {
+    /// Don't check this function; used to opt a method out of a checked
+    /// `impl` block.
+    pub skip: bool,
+    /// Whether `'_` counts as naming a lifetime. It does unless
+    /// `deny_anonymous` is given, since it at least shows that there is one.
+    pub allow_anonymous: bool,
+    /// Whether `'static` may be used in the signature's types. It can unless
+    /// `deny_static` is given, which catches `'static` sprinkled around to
+    /// make an exercise compile.
+    pub allow_static: bool,
+    /// Whether to explain what elision would have made of the signature.
+    /// `no_notes` turns this off, for exercises where that's the answer.
+    pub notes: bool,
+    /// Whether the lifetimes the function introduces need names longer than
+    /// one letter, so that they say what they're for.
+    pub descriptive_names: bool,
+    /// The only names the function may give its lifetimes, without the `'`.
+    pub names: Option<Vec<String>>,
+    /// Whether only the return type needs its lifetimes written, for the
+    /// exercises that come before input lifetimes are introduced.
+    pub return_only: bool,
+    /// Whether to write each diagnostic as JSON, for the lifetimekata CLI.
+    pub json: bool,
+    /// Parameters which may elide their lifetimes anyway: `self` for the
+    /// receiver, `out` for the return type, or an argument's name.
+    pub except: Vec<String>,
+    /// Whether lifetimes and bounds which aren't needed are errors too, so
+    /// that an exercise can't be passed by annotating everything.
+    pub strict: bool,
+    /// Whether an `'a: 'b` bound may say what the types already imply, as
+    /// in `fn f<'a: 'b, 'b>(x: &'b &'a str)`. It can unless
+    /// `deny_implied_bounds` is given.
+    pub allow_implied_bounds: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            skip: false,
+            allow_anonymous: true,
+            allow_static: true,
+            notes: true,
+            descriptive_names: false,
+            names: None,
+            return_only: false,
+            json: false,
+            except: Vec::new(),
+            strict: false,
+            allow_implied_bounds: true,
+        }
+    }
+}
+
+impl Options {
+    /// The names to suggest for elided lifetimes, before falling back on
+    /// `'a`, `'b` and so on.
+    pub(crate) fn suggested_names(&self) -> Vec<String> {
+        match &self.names {
+            Some(names) => names.clone(),
+            None if self.descriptive_names => ["first", "second", "third", "fourth", "fifth"]
+                .map(String::from)
+                .to_vec(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Whether `except(...)` named the parameter called `name`.
+    pub(crate) fn exempts(&self, name: &str) -> bool {
+        self.except.iter().any(|exempt| exempt == name)
+    }
+}
+
+impl Parse for Options {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut options = Options::default();
+        let mut anonymous: Option<Ident> = None;
+        let mut static_: Option<Ident> = None;
+        let mut implied: Option<Ident> = None;
+        while !input.is_empty() {
+            if input.peek(Token![!]) {
+                // The spelling the exercises use; it asks for the default.
+                input.parse::<Token![!]>()?;
+            } else {
+                let name: Ident = input.parse()?;
+                match name.to_string().as_str() {
+                    "skip" => options.skip = true,
+                    "no_notes" => options.notes = false,
+                    "descriptive_names" => options.descriptive_names = true,
+                    "return_only" => options.return_only = true,
+                    "json" => options.json = true,
+                    "strict" => options.strict = true,
+                    "except" => {
+                        let content;
+                        parenthesized!(content in input);
+                        let names = content.parse_terminated(Ident::parse_any, Token![,])?;
+                        options.except = names.iter().map(Ident::to_string).collect();
+                    }
+                    "names" => {
+                        let content;
+                        parenthesized!(content in input);
+                        let names = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+                        options.names = Some(
+                            names
+                                .iter()
+                                .map(|name| {
+                                    let value = name.value();
+                                    let value = value.strip_prefix('\'').unwrap_or(&value);
+                                    syn::parse_str::<Ident>(value)
+                                        .map(|ident| ident.to_string())
+                                        .map_err(|_| {
+                                            syn::Error::new(name.span(), "not a lifetime name")
+                                        })
+                                })
+                                .collect::<syn::Result<_>>()?,
+                        );
+                    }
+                    "allow_anonymous" | "deny_anonymous" => {
+                        conflicts(&mut anonymous, &name)?;
+                        options.allow_anonymous = name == "allow_anonymous";
+                    }
+                    "allow_static" | "deny_static" => {
+                        conflicts(&mut static_, &name)?;
+                        options.allow_static = name == "allow_static";
+                    }
+                    "allow_implied_bounds" | "deny_implied_bounds" => {
+                        conflicts(&mut implied, &name)?;
+                        options.allow_implied_bounds = name == "allow_implied_bounds";
+                    }
+                    _ => {
+                        return Err(syn::Error::new(
+                            name.span(),
+                            format!("unknown argument `{name}` to #[require_lifetimes]"),
+                        ))
+                    }
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Records `name` as the choice between a pair of opposite arguments, failing
+/// if the choice was already made.
+fn conflicts(choice: &mut Option<Ident>, name: &Ident) -> syn::Result<()> {
+    match choice.replace(name.clone()) {
+        Some(previous) => Err(syn::Error::new(
+            name.span(),
+            format!("`{name}` can't be used along with `{previous}`"),
+        )),
+        None => Ok(()),
+    }
+}
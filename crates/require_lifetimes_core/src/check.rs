@@ -0,0 +1,869 @@
+//! Finding the references in a signature whose lifetimes have been elided.
+
+use std::collections::BTreeSet;
+
+use proc_macro2::Span;
+use quote::ToTokens;
+use syn::visit::{self, Visit};
+use syn::{
+    FnArg, GenericArgument, GenericParam, Lifetime, LifetimeParam, Pat, Path, PathArguments,
+    PathSegment, Receiver, ReturnType, Signature, Type, TypeImplTrait, TypeParamBound,
+    TypeReference, WherePredicate,
+};
+
+use crate::args::Options;
+use crate::diagnostic::{Code, Diagnostic};
+use crate::elision;
+
+/// Returns an error for every reference in `signature` that doesn't name its
+/// lifetime, each pointing at that reference.
+///
+/// Unless `options` allows them, every use of `'_`, and of `'static` in a
+/// type, is an error as well, as are lifetime names it doesn't like. With
+/// `return_only`, only the return type is looked at for any of those, and
+/// parameters named by `except(...)` aren't looked at at all.
+pub(crate) fn elided_references(signature: &Signature, options: &Options) -> Vec<Diagnostic> {
+    let names = LifetimeNames::new(signature).preferring(options.suggested_names());
+    let elaboration = elision::elaborate_with(signature, names.clone());
+    let mut check = Check {
+        options,
+        names,
+        output: elaboration.rule_3.or(elaboration.rule_2),
+        errors: Vec::new(),
+        in_bound: false,
+        captured: signature.asyncness.is_some(),
+        in_output: false,
+        higher_ranked: None,
+        borrows: elision::input_positions(signature) > 0,
+    };
+    let inputs: Vec<&FnArg> = signature
+        .inputs
+        .iter()
+        .filter(|input| !options.return_only && !options.exempts(&parameter_name(input)))
+        .collect();
+    let output_checked = !options.exempts("out");
+    for input in &inputs {
+        match input {
+            FnArg::Receiver(receiver) => check.receiver(receiver),
+            FnArg::Typed(argument) => check.ty(&argument.ty),
+        }
+    }
+    check.captured = false;
+    check.in_output = true;
+    if let ReturnType::Type(_, ty) = &signature.output {
+        if output_checked {
+            check.ty(ty);
+        }
+    }
+    check.in_output = false;
+    if !options.return_only {
+        for param in &signature.generics.params {
+            if let GenericParam::Type(param) = param {
+                check.bounds(&param.bounds);
+            }
+        }
+        if let Some(where_clause) = &signature.generics.where_clause {
+            for predicate in &where_clause.predicates {
+                if let WherePredicate::Type(predicate) = predicate {
+                    check.bounds(&predicate.bounds);
+                }
+            }
+        }
+    }
+    if !options.allow_anonymous || !options.allow_static {
+        if !options.return_only {
+            check.visit_generics(&signature.generics);
+        }
+        for input in inputs {
+            check.visit_fn_arg(input);
+        }
+        if output_checked {
+            check.in_output = true;
+            check.visit_return_type(&signature.output);
+            check.in_output = false;
+        }
+    }
+    if options.descriptive_names || options.names.is_some() {
+        check.naming(signature);
+    }
+    check.errors
+}
+
+struct Check<'o> {
+    options: &'o Options,
+    names: LifetimeNames,
+    /// What rule 3, or else rule 2, gives the return type's elided
+    /// lifetimes, named as they're suggested for the arguments.
+    output: Option<Lifetime>,
+    errors: Vec<Diagnostic>,
+    /// Whether the lifetimes being visited are bounds, like `T: 'static`.
+    in_bound: bool,
+    /// Whether the references being checked are held by the future an
+    /// `async fn` returns, which is true of all its arguments.
+    captured: bool,
+    /// Whether the references being checked are in the return type.
+    in_output: bool,
+    /// When the references being checked are in the arguments of a bound
+    /// like `Fn(&str)` or a type like `fn(&str)`, a name has to come from a
+    /// `for<...>` binder in front of this.
+    higher_ranked: Option<&'static str>,
+    /// Whether any argument has a lifetime, so that an `impl Trait` in the
+    /// return type could be borrowing from it.
+    borrows: bool,
+}
+
+impl Check<'_> {
+    fn receiver(&mut self, receiver: &Receiver) {
+        if receiver.colon_token.is_some() {
+            // `self: &Self` is written out like any other argument.
+            return self.ty(&receiver.ty);
+        }
+        if let Some((_, None)) = receiver.reference {
+            let mut suggested = receiver.clone();
+            if let Some((_, lifetime)) = &mut suggested.reference {
+                *lifetime = Some(self.names.fresh());
+            }
+            self.error(Code::ElidedArgument, receiver, &suggested);
+        }
+    }
+
+    fn ty(&mut self, ty: &Type) {
+        match ty {
+            Type::Reference(reference) => self.reference(reference),
+            Type::Paren(paren) => self.ty(&paren.elem),
+            Type::Group(group) => self.ty(&group.elem),
+            Type::Slice(slice) => self.ty(&slice.elem),
+            Type::Array(array) => self.ty(&array.elem),
+            Type::Ptr(pointer) => self.ty(&pointer.elem),
+            Type::Tuple(tuple) => {
+                for elem in &tuple.elems {
+                    self.ty(elem);
+                }
+            }
+            Type::Path(path) => {
+                if let Some(qself) = &path.qself {
+                    self.ty(&qself.ty);
+                }
+                let segments = &path.path.segments;
+                if path.qself.is_some() || segments.len() > 1 && segments[0].ident == "Self" {
+                    self.associated_type(segments.last().expect("a path has a segment"));
+                }
+                self.path(&path.path);
+            }
+            Type::TraitObject(object) => self.bounds(&object.bounds),
+            Type::ImplTrait(opaque) => {
+                if self.in_output && self.higher_ranked.is_none() && self.borrows {
+                    self.capture(opaque);
+                }
+                self.bounds(&opaque.bounds);
+            }
+            Type::BareFn(function) => {
+                let outer = self.higher_ranked.replace("the `fn` type");
+                for input in &function.inputs {
+                    self.ty(&input.ty);
+                }
+                if let ReturnType::Type(_, ty) = &function.output {
+                    self.ty(ty);
+                }
+                self.higher_ranked = outer;
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks the generic arguments in `path`, like the `&str` in
+    /// `Vec<&str>`. The elided lifetimes in `Fn(..) -> ..` arguments are
+    /// implicitly higher-ranked.
+    fn path(&mut self, path: &Path) {
+        for segment in &path.segments {
+            match &segment.arguments {
+                PathArguments::AngleBracketed(arguments) => {
+                    for argument in &arguments.args {
+                        match argument {
+                            GenericArgument::Type(ty) => self.ty(ty),
+                            GenericArgument::AssocType(assoc) => self.ty(&assoc.ty),
+                            _ => {}
+                        }
+                    }
+                }
+                PathArguments::Parenthesized(arguments) => {
+                    let outer = self.higher_ranked.replace("the trait");
+                    for input in &arguments.inputs {
+                        self.ty(input);
+                    }
+                    if let ReturnType::Type(_, ty) = &arguments.output {
+                        self.ty(ty);
+                    }
+                    self.higher_ranked = outer;
+                }
+                PathArguments::None => {}
+            }
+        }
+    }
+
+    /// Checks the lifetimes given to an associated type like `Self::Item<'_>`.
+    /// Those are generic associated types, where writing the lifetime out
+    /// is the whole point, so `'_` doesn't count even when it's allowed.
+    fn associated_type(&mut self, segment: &PathSegment) {
+        // With `deny_anonymous`, the `'_` gets reported anyway.
+        if !self.options.allow_anonymous {
+            return;
+        }
+        let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+            return;
+        };
+        for (index, argument) in arguments.args.iter().enumerate() {
+            let GenericArgument::Lifetime(lifetime) = argument else {
+                continue;
+            };
+            if lifetime.ident != "_" {
+                continue;
+            }
+            let mut suggested = segment.clone();
+            let lifetime = self.suggestion();
+            if let PathArguments::AngleBracketed(arguments) = &mut suggested.arguments {
+                arguments.args[index] = GenericArgument::Lifetime(lifetime);
+            }
+            self.error(self.elided_code(), segment, &suggested);
+        }
+    }
+
+    fn bounds<'b>(&mut self, bounds: impl IntoIterator<Item = &'b TypeParamBound>) {
+        for bound in bounds {
+            if let TypeParamBound::Trait(bound) = bound {
+                self.path(&bound.path);
+            }
+        }
+    }
+
+    /// Requires an `impl Trait` in the return type to say which lifetimes it
+    /// captures, with `+ 'a` or `use<'a>`, rather than leaving it to the
+    /// edition's capture rules.
+    fn capture(&mut self, opaque: &TypeImplTrait) {
+        let explicit = opaque.bounds.iter().any(|bound| {
+            matches!(
+                bound,
+                TypeParamBound::Lifetime(_) | TypeParamBound::PreciseCapture(_)
+            )
+        });
+        if explicit {
+            return;
+        }
+        let mut used = Vec::new();
+        for bound in &opaque.bounds {
+            used.extend(lifetimes_in(bound));
+        }
+        let suggested = used
+            .into_iter()
+            .find(|lifetime| lifetime.ident != "_" && lifetime.ident != "static")
+            .or_else(|| self.output.clone())
+            .unwrap_or_else(|| self.names.fresh());
+        let message = format!(
+            "`{}` doesn't say which lifetimes it captures; add `+ {suggested}` or `use<{suggested}>`",
+            render(opaque)
+        );
+        self.errors
+            .push(Code::ImplTraitCapture.error(opaque, message));
+    }
+
+    /// Checks the names of the lifetimes `signature` introduces, including in
+    /// `for<...>`, against the naming rules in the options.
+    fn naming(&mut self, signature: &Signature) {
+        struct Introduced<'s>(Vec<&'s Lifetime>);
+        impl<'ast> Visit<'ast> for Introduced<'ast> {
+            fn visit_lifetime_param(&mut self, param: &'ast LifetimeParam) {
+                self.0.push(&param.lifetime);
+            }
+        }
+
+        let mut introduced = Introduced(Vec::new());
+        introduced.visit_signature(signature);
+        for lifetime in introduced.0 {
+            let name = lifetime.ident.to_string();
+            let (code, message) = match &self.options.names {
+                Some(names) if !names.contains(&name) => {
+                    let names: Vec<_> = names.iter().map(|name| format!("`'{name}`")).collect();
+                    let message = format!(
+                        "`{lifetime}` isn't one of the lifetime names asked for: {}",
+                        names.join(", ")
+                    );
+                    (Code::UnlistedName, message)
+                }
+                _ if self.options.descriptive_names && name.chars().count() == 1 => {
+                    let message = format!(
+                        "`{lifetime}` doesn't say what it's for; \
+                         give it a longer name such as `{}`",
+                        self.names.fresh()
+                    );
+                    (Code::ShortName, message)
+                }
+                _ => continue,
+            };
+            self.errors.push(code.error(lifetime, message));
+        }
+    }
+
+    fn reference(&mut self, reference: &TypeReference) {
+        if reference.lifetime.is_none() {
+            let mut suggested = reference.clone();
+            suggested.lifetime = Some(self.suggestion());
+            self.error(self.elided_code(), reference, &suggested);
+        }
+        self.ty(&reference.elem);
+    }
+
+    /// The lifetime to suggest for one which was elided: what elision
+    /// gives it in the return type, if a rule says, or else a new one.
+    fn suggestion(&mut self) -> Lifetime {
+        match &self.output {
+            Some(lifetime) if self.in_output && self.higher_ranked.is_none() => lifetime.clone(),
+            _ => self.names.fresh(),
+        }
+    }
+
+    /// The code for a lifetime elided where the check is looking.
+    fn elided_code(&self) -> Code {
+        if self.higher_ranked.is_some() {
+            Code::ElidedHigherRanked
+        } else if self.in_output {
+            Code::ElidedReturn
+        } else {
+            Code::ElidedArgument
+        }
+    }
+
+    fn error(&mut self, code: Code, written: &impl ToTokens, suggested: &impl ToTokens) {
+        let mut message = format!(
+            "`{}` here needs a name such as `{}`",
+            render(written),
+            render(suggested)
+        );
+        if self.captured {
+            message.push_str(", since the future this `async fn` returns holds on to it");
+        }
+        if let Some(binder) = self.higher_ranked {
+            message.push_str(&format!(", introduced by a `for<...>` before {binder}"));
+        }
+        self.errors.push(code.error(written, message));
+    }
+}
+
+impl<'ast> Visit<'ast> for Check<'_> {
+    fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+        if lifetime.ident == "_" && !self.options.allow_anonymous {
+            let suggested = self.suggestion();
+            self.error(Code::Anonymous, lifetime, &suggested);
+        }
+        if lifetime.ident == "static" && !self.options.allow_static && !self.in_bound {
+            let message = format!(
+                "`'static` isn't allowed here; name a lifetime such as `{}` instead",
+                self.names.fresh()
+            );
+            self.errors.push(Code::Static.error(lifetime, message));
+        }
+    }
+
+    fn visit_type_param_bound(&mut self, bound: &'ast TypeParamBound) {
+        // `T: 'static` says something about a type rather than borrowing
+        // for `'static`, so `deny_static` has nothing to say about it.
+        let outer = self.in_bound;
+        self.in_bound = matches!(bound, TypeParamBound::Lifetime(_));
+        visit::visit_type_param_bound(self, bound);
+        self.in_bound = outer;
+    }
+}
+
+/// Every lifetime written in `bound`, in order.
+fn lifetimes_in(bound: &TypeParamBound) -> Vec<Lifetime> {
+    struct Collect(Vec<Lifetime>);
+    impl<'ast> Visit<'ast> for Collect {
+        fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+            self.0.push(lifetime.clone());
+        }
+    }
+
+    let mut collect = Collect(Vec::new());
+    collect.visit_type_param_bound(bound);
+    collect.0
+}
+
+/// The name `except(...)` knows a parameter by: `self` for the receiver, or
+/// the name it binds. Parameters which are patterns can't be exempted.
+fn parameter_name(input: &FnArg) -> String {
+    match input {
+        FnArg::Receiver(_) => "self".to_string(),
+        FnArg::Typed(argument) => match &*argument.pat {
+            Pat::Ident(pat) => pat.ident.to_string(),
+            _ => String::new(),
+        },
+    }
+}
+
+/// Hands out lifetime names which the signature doesn't already use.
+#[derive(Clone)]
+pub(crate) struct LifetimeNames {
+    used: BTreeSet<String>,
+    /// Names to try before `'a`, `'b` and so on.
+    preferred: Vec<String>,
+}
+
+impl LifetimeNames {
+    pub(crate) fn new(signature: &Signature) -> Self {
+        struct Collect<'s>(&'s mut BTreeSet<String>);
+        impl<'ast> Visit<'ast> for Collect<'_> {
+            fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+                self.0.insert(lifetime.ident.to_string());
+            }
+        }
+
+        let mut used = BTreeSet::new();
+        Collect(&mut used).visit_signature(signature);
+        LifetimeNames {
+            used,
+            preferred: Vec::new(),
+        }
+    }
+
+    pub(crate) fn preferring(self, preferred: Vec<String>) -> Self {
+        LifetimeNames { preferred, ..self }
+    }
+
+    pub(crate) fn fresh(&mut self) -> Lifetime {
+        let name = self
+            .preferred
+            .iter()
+            .cloned()
+            .chain(('a'..='z').map(String::from))
+            .chain((1..).map(|n| format!("a{n}")))
+            .find(|name| !self.used.contains(name))
+            .expect("there are infinitely many names");
+        self.used.insert(name.clone());
+        Lifetime::new(&format!("'{name}"), Span::call_site())
+    }
+}
+
+/// Formats a type or signature roughly how it would be written by hand;
+/// `quote` puts a space between every token, which makes `& 'a str` hard to
+/// read.
+pub(crate) fn render(tokens: &impl ToTokens) -> String {
+    let text = tokens.to_token_stream().to_string();
+    let mut rendered = String::new();
+    let mut previous = "";
+    for piece in text.split_whitespace() {
+        let glued = previous.is_empty()
+            || previous.ends_with(['&', '(', '[', '<', '*'])
+            || previous == "::"
+            || piece.starts_with([')', ']', '>', ',', ';', ':', '<'])
+            || (piece.starts_with('(') && is_path_segment(previous));
+        if !glued {
+            rendered.push(' ');
+        }
+        rendered.push_str(piece);
+        previous = piece;
+    }
+    rendered
+}
+
+/// Whether `(` after `piece` starts the arguments of `Fn(..)` or
+/// `fn f<'a>(..)`, rather than a tuple.
+fn is_path_segment(piece: &str) -> bool {
+    if piece.ends_with('>') {
+        return piece != "->";
+    }
+    !matches!(piece, "mut" | "dyn" | "impl" | "const")
+        && piece.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+        && !piece.starts_with('\'')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syn::parse_quote;
+
+    fn messages(signature: Signature) -> Vec<String> {
+        messages_with(signature, Options::default())
+    }
+
+    /// The messages for `signature`, without their codes.
+    fn messages_with(signature: Signature, options: Options) -> Vec<String> {
+        elided_references(&signature, &options)
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect()
+    }
+
+    fn codes(signature: Signature, options: Options) -> Vec<String> {
+        elided_references(&signature, &options)
+            .iter()
+            .map(|diagnostic| diagnostic.code.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn each_kind_of_error_has_its_own_code() {
+        assert_eq!(
+            codes(
+                parse_quote!(fn f<F: Fn(&u8)>(&self, f: F) -> &str),
+                Options::default()
+            ),
+            vec!["LK0001", "LK0002", "LK0003"]
+        );
+        let options = Options {
+            allow_anonymous: false,
+            allow_static: false,
+            names: Some(vec!["input".into()]),
+            ..Options::default()
+        };
+        assert_eq!(
+            codes(
+                parse_quote!(fn f<'a>(x: &'_ str, y: &'static str, z: &'a str)),
+                options
+            ),
+            vec!["LK0004", "LK0005", "LK0007"]
+        );
+        let options = Options {
+            descriptive_names: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            codes(parse_quote!(fn f<'a>(x: &'a str)), options),
+            vec!["LK0006"]
+        );
+    }
+
+    #[test]
+    fn explicit_lifetimes_pass() {
+        assert!(messages(parse_quote!(fn f<'a>(x: &'a str) -> &'a str)).is_empty());
+        assert!(messages(parse_quote!(fn f<'a>(&'a mut self, y: u32) -> u32)).is_empty());
+        assert!(messages(parse_quote!(fn f(x: u32, y: String))).is_empty());
+    }
+
+    #[test]
+    fn each_reference_gets_its_own_error() {
+        // No rule says where the return type borrows from, so it gets a
+        // lifetime of its own.
+        assert_eq!(
+            messages(parse_quote!(fn f(x: &str, y: &mut Vec<u8>) -> &str)),
+            vec![
+                "`&str` here needs a name such as `&'a str`",
+                "`&mut Vec<u8>` here needs a name such as `&'b mut Vec<u8>`",
+                "`&str` here needs a name such as `&'c str`",
+            ]
+        );
+    }
+
+    #[test]
+    fn receivers_are_checked() {
+        assert_eq!(
+            messages(parse_quote!(fn f(&self))),
+            vec!["`&self` here needs a name such as `&'a self`"]
+        );
+        assert_eq!(
+            messages(parse_quote!(fn f(&mut self))),
+            vec!["`&mut self` here needs a name such as `&'a mut self`"]
+        );
+        assert_eq!(
+            messages(parse_quote!(fn f(self: &Self))),
+            vec!["`&Self` here needs a name such as `&'a Self`"]
+        );
+        assert_eq!(
+            messages(parse_quote!(fn f(self: Pin<&mut Self>) -> Pin<&u8>)),
+            vec![
+                "`&mut Self` here needs a name such as `&'a mut Self`",
+                "`&u8` here needs a name such as `&'a u8`",
+            ]
+        );
+        assert!(messages(parse_quote!(fn f(self: Box<Self>, other: Rc<Self>))).is_empty());
+        assert!(messages(parse_quote!(fn f<'a>(self: Pin<&'a mut Self>) -> &'a u8)).is_empty());
+    }
+
+    #[test]
+    fn returned_references_are_given_what_elision_would_give_them() {
+        assert_eq!(
+            messages(parse_quote!(fn f(x: &str) -> &str)),
+            vec![
+                "`&str` here needs a name such as `&'a str`",
+                "`&str` here needs a name such as `&'a str`",
+            ]
+        );
+        assert_eq!(
+            messages(parse_quote!(fn f(&self, x: &str) -> &str)),
+            vec![
+                "`&self` here needs a name such as `&'a self`",
+                "`&str` here needs a name such as `&'b str`",
+                "`&str` here needs a name such as `&'a str`",
+            ]
+        );
+        assert_eq!(
+            messages(parse_quote!(fn f<'x>(x: &'x str, y: u8) -> Vec<&str>)),
+            vec!["`&str` here needs a name such as `&'x str`"]
+        );
+    }
+
+    #[test]
+    fn suggestions_avoid_names_in_use() {
+        assert_eq!(
+            messages(parse_quote!(fn f<'a>(x: &'a str, y: &(&'b u8)))),
+            vec!["`&(&'b u8)` here needs a name such as `&'c (&'b u8)`"]
+        );
+    }
+
+    #[test]
+    fn anonymous_lifetimes_can_be_denied() {
+        let signature: Signature = parse_quote!(fn f(x: &'_ str, y: Thing<'_>));
+        assert!(messages(signature.clone()).is_empty());
+
+        let options = Options {
+            allow_anonymous: false,
+            ..Options::default()
+        };
+        assert_eq!(
+            messages_with(signature, options),
+            vec![
+                "`'_` here needs a name such as `'a`",
+                "`'_` here needs a name such as `'b`",
+            ]
+        );
+    }
+
+    #[test]
+    fn static_lifetimes_can_be_denied() {
+        let signature: Signature =
+            parse_quote!(fn f<T: 'static>(x: &'static str, y: Box<dyn Fn() + 'static>) -> T);
+        assert!(messages(signature.clone()).is_empty());
+
+        let options = Options {
+            allow_static: false,
+            ..Options::default()
+        };
+        assert_eq!(
+            messages_with(signature, options),
+            vec!["`'static` isn't allowed here; name a lifetime such as `'a` instead"]
+        );
+    }
+
+    #[test]
+    fn async_functions_mention_the_future() {
+        assert_eq!(
+            messages(parse_quote!(async fn f(&self, x: &str) -> &str)),
+            vec![
+                "`&self` here needs a name such as `&'a self`, \
+                 since the future this `async fn` returns holds on to it",
+                "`&str` here needs a name such as `&'b str`, \
+                 since the future this `async fn` returns holds on to it",
+                "`&str` here needs a name such as `&'a str`",
+            ]
+        );
+        assert!(messages(parse_quote!(async fn f<'a>(x: &'a str) -> &'a str)).is_empty());
+    }
+
+    #[test]
+    fn fn_bounds_are_checked() {
+        assert_eq!(
+            messages(parse_quote!(fn f<F: Fn(&str) -> &str>(f: F))),
+            vec![
+                "`&str` here needs a name such as `&'a str`, \
+                 introduced by a `for<...>` before the trait",
+                "`&str` here needs a name such as `&'b str`, \
+                 introduced by a `for<...>` before the trait",
+            ]
+        );
+        assert_eq!(
+            messages(parse_quote!(fn f<G>(g: G) where G: FnMut(&mut u8))),
+            vec![
+                "`&mut u8` here needs a name such as `&'a mut u8`, \
+                 introduced by a `for<...>` before the trait"
+            ]
+        );
+    }
+
+    #[test]
+    fn higher_ranked_lifetimes_count_as_named() {
+        assert!(messages(parse_quote!(
+            fn f<F: for<'a> Fn(&'a str) -> &'a str>(f: F)
+        ))
+        .is_empty());
+        assert!(messages(parse_quote!(
+            fn f<F>(f: F) where for<'a> F: Fn(&'a str) -> &'a str, F: Clone
+        ))
+        .is_empty());
+        assert_eq!(
+            messages(parse_quote!(
+                fn f<'a, F>(x: &str, f: F) where for<'b> F: Fn(&'b str)
+            )),
+            vec!["`&str` here needs a name such as `&'c str`"]
+        );
+    }
+
+    #[test]
+    fn references_inside_other_types_are_checked() {
+        assert_eq!(
+            messages(parse_quote!(
+                fn f<T>(x: Vec<&str>, y: (&u8, [&u8; 2])) -> Option<&mut T>
+            )),
+            vec![
+                "`&str` here needs a name such as `&'a str`",
+                "`&u8` here needs a name such as `&'b u8`",
+                "`&u8` here needs a name such as `&'c u8`",
+                "`&mut T` here needs a name such as `&'d mut T`",
+            ]
+        );
+        assert_eq!(
+            messages(parse_quote!(
+                fn f<'a>(x: Box<dyn Iterator<Item = &str> + 'a>, y: &'a [&'a str])
+            )),
+            vec!["`&str` here needs a name such as `&'b str`"]
+        );
+    }
+
+    #[test]
+    fn function_types_are_checked() {
+        assert_eq!(
+            messages(parse_quote!(fn f(x: fn(&str) -> usize, y: Box<dyn Fn(&u8)>))),
+            vec![
+                "`&str` here needs a name such as `&'a str`, \
+                 introduced by a `for<...>` before the `fn` type",
+                "`&u8` here needs a name such as `&'b u8`, \
+                 introduced by a `for<...>` before the trait",
+            ]
+        );
+        assert!(messages(parse_quote!(
+            fn f(x: for<'a> fn(&'a str) -> &'a str)
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn descriptive_names_can_be_required() {
+        let options = Options {
+            descriptive_names: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            messages_with(
+                parse_quote!(fn f<'a, 'text, F: for<'b> Fn(&'b str)>(x: &'a str, y: &str)),
+                options
+            ),
+            vec![
+                "`&str` here needs a name such as `&'first str`",
+                "`'a` doesn't say what it's for; give it a longer name such as `'second`",
+                "`'b` doesn't say what it's for; give it a longer name such as `'third`",
+            ]
+        );
+    }
+
+    #[test]
+    fn names_can_be_required() {
+        let options = Options {
+            names: Some(vec!["input".into(), "output".into()]),
+            ..Options::default()
+        };
+        assert_eq!(
+            messages_with(
+                parse_quote!(fn f<'input, 'a>(x: &'input str, y: &'a str) -> &str),
+                options
+            ),
+            vec![
+                "`&str` here needs a name such as `&'output str`",
+                "`'a` isn't one of the lifetime names asked for: `'input`, `'output`",
+            ]
+        );
+    }
+
+    #[test]
+    fn generic_associated_types_need_their_lifetimes_written() {
+        assert_eq!(
+            messages(parse_quote!(
+                fn next(&'a mut self) -> Option<Self::Item<'_>>
+            )),
+            vec!["`Item<'_>` here needs a name such as `Item<'a>`"]
+        );
+        assert_eq!(
+            messages(parse_quote!(
+                fn first<I: LendingIterator>(i: &'a mut I) -> Option<<I as LendingIterator>::Item<'_>>
+            )),
+            vec!["`Item<'_>` here needs a name such as `Item<'a>`"]
+        );
+        assert!(
+            messages(parse_quote!(fn next<'a>(&'a mut self) -> Option<Self::Item<'a>>)).is_empty()
+        );
+        // Other paths keep following `allow_anonymous`.
+        assert!(messages(parse_quote!(fn f(x: Thing<'_>))).is_empty());
+    }
+
+    #[test]
+    fn return_only_ignores_the_arguments() {
+        let options = Options {
+            return_only: true,
+            allow_anonymous: false,
+            ..Options::default()
+        };
+        assert_eq!(
+            messages_with(
+                parse_quote!(fn f<F: Fn(&u8)>(&self, x: &'_ str, f: F) -> (&str, Thing<'_>)),
+                options
+            ),
+            vec![
+                "`&str` here needs a name such as `&'a str`",
+                "`'_` here needs a name such as `'a`",
+            ]
+        );
+    }
+
+    #[test]
+    fn exempted_parameters_are_not_checked() {
+        let options = Options {
+            except: vec!["self".to_string(), "out".to_string(), "y".to_string()],
+            allow_anonymous: false,
+            ..Options::default()
+        };
+        assert_eq!(
+            messages_with(
+                parse_quote!(fn f(&self, x: &str, y: &'_ str, (z, _): (&u8, u8)) -> Thing<'_>),
+                options
+            ),
+            vec![
+                "`&str` here needs a name such as `&'a str`",
+                "`&u8` here needs a name such as `&'b u8`",
+            ]
+        );
+    }
+
+    #[test]
+    fn returned_impl_traits_say_what_they_capture() {
+        assert_eq!(
+            messages(parse_quote!(fn f<'a>(x: &'a str) -> impl Iterator<Item = &str>)),
+            vec![
+                "`impl Iterator<Item = &str>` doesn't say which lifetimes it captures; \
+                 add `+ 'a` or `use<'a>`",
+                "`&str` here needs a name such as `&'a str`",
+            ]
+        );
+        assert_eq!(
+            messages(parse_quote!(fn f<'a>(x: &'a str) -> impl Iterator<Item = &'a str>)),
+            vec![
+                "`impl Iterator<Item = &'a str>` doesn't say which lifetimes it captures; \
+                 add `+ 'a` or `use<'a>`"
+            ]
+        );
+        assert!(messages(parse_quote!(
+            fn f<'a>(x: &'a str) -> impl Iterator<Item = &'a str> + 'a
+        ))
+        .is_empty());
+        assert!(messages(parse_quote!(fn f<'a>(x: &'a str) -> impl Sized + use<'a>)).is_empty());
+        // Nothing to borrow from, or nowhere that captures matter.
+        assert!(messages(parse_quote!(fn f(x: u32) -> impl Iterator<Item = u32>)).is_empty());
+        assert!(messages(parse_quote!(fn f<'a>(x: &'a str, y: impl Display))).is_empty());
+    }
+
+    #[test]
+    fn nested_references_are_all_checked() {
+        assert_eq!(
+            messages(parse_quote!(fn f(x: &&str))),
+            vec![
+                "`&&str` here needs a name such as `&'a &str`",
+                "`&str` here needs a name such as `&'b str`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! The codes which every diagnostic starts with, so that tools like the
+//! lifetimekata CLI can tell them apart without reading the English.
+
+use std::fmt::{self, Display};
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{quote, ToTokens};
+
+/// A stable code for each kind of diagnostic. Codes are never reused or
+/// renumbered; a diagnostic which goes away keeps its number retired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum Code {
+    /// `LK0001`: a lifetime in an argument, or in `&self`, was elided.
+    ElidedArgument,
+    /// `LK0002`: a lifetime in the return type was elided.
+    ElidedReturn,
+    /// `LK0003`: a lifetime in `Fn(..)` or `fn(..)` was elided.
+    ElidedHigherRanked,
+    /// `LK0004`: `'_` was used with `deny_anonymous`.
+    Anonymous,
+    /// `LK0005`: `'static` was used with `deny_static`.
+    Static,
+    /// `LK0006`: a lifetime's name is one letter, with `descriptive_names`.
+    ShortName,
+    /// `LK0007`: a lifetime's name isn't one of those given to `names(...)`.
+    UnlistedName,
+    /// `LK0008`: a note on what elision would have made of the signature.
+    ElisionNote,
+    /// `LK0009`: a lifetime was written which elision would have filled in.
+    Needless,
+    /// `LK0010`: the attribute was given bad arguments, or put on something
+    /// it can't check.
+    Usage,
+    /// `LK0011`: two fields share a lifetime, with `separate`.
+    SharedLifetime,
+    /// `LK0012`: a warning showing the signature with elision, from
+    /// `#[show_elided]`.
+    ElidedForm,
+    /// `LK0013`: an `impl Trait` in the return type doesn't say which
+    /// lifetimes it captures.
+    ImplTraitCapture,
+    /// `LK0014`: a lifetime parameter isn't used, with `strict`.
+    UnusedLifetime,
+    /// `LK0015`: an `'a: 'b` bound isn't needed, with `strict`.
+    NeedlessBound,
+    /// `LK0016`: an `'a: 'b` bound is implied by the types, with
+    /// `deny_implied_bounds`.
+    ImpliedBound,
+}
+
+impl Code {
+    /// Every code, in order.
+    pub const ALL: [Code; 16] = [
+        Code::ElidedArgument,
+        Code::ElidedReturn,
+        Code::ElidedHigherRanked,
+        Code::Anonymous,
+        Code::Static,
+        Code::ShortName,
+        Code::UnlistedName,
+        Code::ElisionNote,
+        Code::Needless,
+        Code::Usage,
+        Code::SharedLifetime,
+        Code::ElidedForm,
+        Code::ImplTraitCapture,
+        Code::UnusedLifetime,
+        Code::NeedlessBound,
+        Code::ImpliedBound,
+    ];
+
+    /// The code as it's written in messages, like `LK0001`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Code::ElidedArgument => "LK0001",
+            Code::ElidedReturn => "LK0002",
+            Code::ElidedHigherRanked => "LK0003",
+            Code::Anonymous => "LK0004",
+            Code::Static => "LK0005",
+            Code::ShortName => "LK0006",
+            Code::UnlistedName => "LK0007",
+            Code::ElisionNote => "LK0008",
+            Code::Needless => "LK0009",
+            Code::Usage => "LK0010",
+            Code::SharedLifetime => "LK0011",
+            Code::ElidedForm => "LK0012",
+            Code::ImplTraitCapture => "LK0013",
+            Code::UnusedLifetime => "LK0014",
+            Code::NeedlessBound => "LK0015",
+            Code::ImpliedBound => "LK0016",
+        }
+    }
+
+    /// A diagnostic with this code spanning `tokens`.
+    pub(crate) fn error(self, tokens: impl ToTokens, message: impl Display) -> Diagnostic {
+        // This is how `syn::Error::new_spanned` finds where an error goes.
+        let mut tokens = tokens.into_token_stream().into_iter();
+        let start = tokens
+            .next()
+            .map_or_else(Span::call_site, |token| token.span());
+        let end = tokens.last().map_or(start, |token| token.span());
+        Diagnostic {
+            code: self,
+            message: message.to_string(),
+            span: start.join(end).unwrap_or(start),
+            range: (start, end),
+        }
+    }
+
+    /// A diagnostic with this code at `span`.
+    pub(crate) fn error_at(self, span: Span, message: impl Display) -> Diagnostic {
+        Diagnostic {
+            code: self,
+            message: message.to_string(),
+            span,
+            range: (span, span),
+        }
+    }
+
+    /// A warning at `span`, with this code in front of `message`.
+    ///
+    /// Proc macros can't emit warnings on stable Rust, so this is a
+    /// deprecated item which is used straight away; the warning rustc gives
+    /// for that carries the message. `#[allow(deprecated)]` silences it.
+    pub(crate) fn warning(self, span: Span, message: impl Display) -> TokenStream {
+        let note = format!("[{}] {message}", self.as_str());
+        let name = Ident::new("lifetimekata", span);
+        quote! {
+            const _: () = {
+                #[deprecated(note = #note)]
+                #[allow(non_camel_case_types)]
+                struct #name;
+                let _ = #name;
+            };
+        }
+    }
+
+    /// Gives this code to every message in `error`, which came from
+    /// somewhere that doesn't know about codes, like `syn`.
+    pub(crate) fn wrap(self, error: syn::Error) -> impl Iterator<Item = Diagnostic> {
+        error
+            .into_iter()
+            .map(move |error| self.error_at(error.span(), error))
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Something `check_signature` found, as the attribute would report it.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// Which kind of diagnostic it is.
+    pub code: Code,
+    /// The message, without the code in front.
+    pub message: String,
+    /// Where in the signature it was found.
+    pub span: Span,
+    /// The spans of the first and last tokens it covers. Spans can't be
+    /// joined in a proc macro on stable Rust, so these are what rustc is
+    /// given to underline everything in between.
+    range: (Span, Span),
+}
+
+impl Diagnostic {
+    /// This diagnostic as an error for rustc, which says `message`.
+    fn to_error(&self, message: impl Display) -> syn::Error {
+        let (start, end) = self.range;
+        let (start, end) = (Ident::new("start", start), Ident::new("end", end));
+        syn::Error::new_spanned(quote!(#start #end), message)
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
+
+/// The variable which asks for every diagnostic as JSON, for tools which
+/// read rustc's output rather than people.
+const JSON_VARIABLE: &str = "LIFETIMEKATA_DIAGNOSTICS";
+
+/// Turns `diagnostics` into `compile_error!`s, each reading like
+/// `[LK0001] message`. If `json` is set, or asked for with
+/// `LIFETIMEKATA_DIAGNOSTICS=json`, each message is a JSON object such as
+/// `{"code":"LK0001","message":"..."}` instead; rustc still says where it is.
+pub(crate) fn compile_errors(diagnostics: Vec<Diagnostic>, json: bool) -> TokenStream {
+    let json = json || std::env::var(JSON_VARIABLE).is_ok_and(|value| value == "json");
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            if json {
+                diagnostic.to_error(to_json(diagnostic))
+            } else {
+                diagnostic.to_error(diagnostic)
+            }
+            .to_compile_error()
+        })
+        .collect()
+}
+
+/// `diagnostic` as a JSON object with the code and message apart.
+fn to_json(diagnostic: &Diagnostic) -> String {
+    format!(
+        r#"{{"code":"{}","message":"{}"}}"#,
+        diagnostic.code,
+        escape(&diagnostic.message)
+    )
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json_keeps_the_code_apart() {
+        let message = "`&str` here needs a name such as `&'a str`";
+        assert_eq!(
+            to_json(&Code::ElidedArgument.error_at(Span::call_site(), message)),
+            r#"{"code":"LK0001","message":"`&str` here needs a name such as `&'a str`"}"#
+        );
+        let message = "not a \"lifetime\"\\name";
+        assert_eq!(
+            to_json(&Code::Usage.error_at(Span::call_site(), message)),
+            r#"{"code":"LK0010","message":"not a \"lifetime\"\\name"}"#
+        );
+    }
+}
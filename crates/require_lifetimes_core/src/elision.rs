@@ -0,0 +1,683 @@
+//! Working out which of a signature's lifetimes the elision rules would have
+//! filled in anyway.
+//!
+//! The rules, as the book describes them, are:
+//!
+//! 1. each elided lifetime in the arguments becomes its own parameter;
+//! 2. if there is exactly one lifetime in the arguments, elided or not, it
+//!    is used for every elided lifetime in the return type;
+//! 3. if there is a `&self` or `&mut self`, its lifetime is used for every
+//!    elided lifetime in the return type.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use proc_macro2::TokenStream;
+
+use syn::visit::{self, Visit};
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    FnArg, GenericParam, Lifetime, LifetimeParam, ReturnType, Signature, Type, TypeBareFn,
+    TypeParamBound, TypeReference,
+};
+
+use crate::check::{render, LifetimeNames};
+use crate::diagnostic::{Code, Diagnostic};
+
+/// Returns an error for every lifetime parameter of `signature` which could
+/// be removed, with its uses, without changing what the signature means.
+///
+/// Only the signature is looked at, so a lifetime which is also named in the
+/// function's body will be reported too.
+pub(crate) fn needless_lifetimes(signature: &Signature) -> Vec<Diagnostic> {
+    elidable_lifetimes(signature)
+        .into_iter()
+        .map(|param| {
+            let message = format!(
+                "`{}` doesn't need to be written; lifetime elision would give the same signature",
+                param.lifetime
+            );
+            Code::Needless.error(&param.lifetime, message)
+        })
+        .collect()
+}
+
+/// What the elision rules make of a signature, one rule at a time; see
+/// [`elaborate`].
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct Elaboration {
+    /// The lifetimes rule 1 gave the arguments, one for each which was
+    /// elided, in order.
+    pub named: Vec<Lifetime>,
+    /// The signature once rule 1 has been applied, with the return type as
+    /// it was written.
+    pub arguments: String,
+    /// How many places in the arguments have a lifetime, whether it's
+    /// written or not; rule 2 only applies when this is one.
+    pub input_lifetimes: usize,
+    /// The lifetime rule 2 gives the return type, if there's exactly one in
+    /// the arguments.
+    pub rule_2: Option<Lifetime>,
+    /// The lifetime rule 3 gives the return type, `self`'s, if it's
+    /// borrowed. When rules 2 and 3 both apply, they agree.
+    pub rule_3: Option<Lifetime>,
+    /// How many lifetimes were elided in the return type.
+    pub elided_outputs: usize,
+    /// The signature with every elided lifetime filled in, or `None` if the
+    /// return type elides one which no rule says anything about.
+    pub elaborated: Option<String>,
+}
+
+/// Applies the elision rules to `signature`, the way the compiler would.
+/// Lifetimes inside `Fn(..)`, `fn(..)` and `+ '_` bounds are left alone,
+/// since elision means something else there.
+pub fn elaborate(signature: &Signature) -> Elaboration {
+    elaborate_with(signature, LifetimeNames::new(signature))
+}
+
+/// [`elaborate`], naming the lifetimes rule 1 fills in with `names`.
+pub(crate) fn elaborate_with(signature: &Signature, names: LifetimeNames) -> Elaboration {
+    let mut expanded = signature.clone();
+    expanded.inputs.pop_punct();
+    let mut fill = Fill {
+        names,
+        in_output: false,
+        output: None,
+        added: Vec::new(),
+        elided_outputs: 0,
+        depth: 0,
+    };
+    for input in &mut expanded.inputs {
+        match input {
+            FnArg::Receiver(receiver) => fill.visit_receiver_mut(receiver),
+            FnArg::Typed(argument) => fill.visit_type_mut(&mut argument.ty),
+        }
+    }
+
+    let input_lifetimes = input_positions(signature);
+    let rule_2 = if input_lifetimes == 1 {
+        single_input_lifetime(&expanded)
+    } else {
+        None
+    };
+    let rule_3 = receiver_lifetime(&expanded);
+    let arguments = render(&declaring(expanded.clone(), &fill.added));
+
+    // Rule 3 comes first: a method's return type borrows from `self`.
+    fill.output = rule_3.clone().or_else(|| rule_2.clone());
+    fill.in_output = true;
+    if let ReturnType::Type(_, ty) = &mut expanded.output {
+        fill.visit_type_mut(ty);
+    }
+    let elaborated = (fill.elided_outputs == 0 || fill.output.is_some())
+        .then(|| render(&declaring(expanded, &fill.added)));
+    Elaboration {
+        named: fill.added,
+        arguments,
+        input_lifetimes,
+        rule_2,
+        rule_3,
+        elided_outputs: fill.elided_outputs,
+        elaborated,
+    }
+}
+
+/// `signature` with `lifetimes` declared after the lifetimes it already
+/// has.
+fn declaring(mut signature: Signature, lifetimes: &[Lifetime]) -> Signature {
+    let first_non_lifetime = signature
+        .generics
+        .params
+        .iter()
+        .take_while(|param| matches!(param, GenericParam::Lifetime(_)))
+        .count();
+    for (offset, lifetime) in lifetimes.iter().enumerate() {
+        let param = GenericParam::Lifetime(LifetimeParam::new(lifetime.clone()));
+        signature
+            .generics
+            .params
+            .insert(first_non_lifetime + offset, param);
+    }
+    signature
+}
+
+/// An error explaining what the elision rules would make of `signature`, so
+/// that each complaint about an elided lifetime comes with how the compiler
+/// would have read it. There's nothing to say if nothing was elided.
+pub(crate) fn expansion_note(signature: &Signature) -> Option<Diagnostic> {
+    let elaboration = elaborate(signature);
+    if elaboration.named.is_empty() && elaboration.elided_outputs == 0 {
+        return None;
+    }
+    let message = match &elaboration.elaborated {
+        None => format!(
+            "rule 1 would make this `{}`, but neither rule 2 nor rule 3 says which \
+             argument the return type borrows from, so it has to be written",
+            elaboration.arguments
+        ),
+        Some(elaborated) if elaboration.elided_outputs == 0 => {
+            format!("rule 1 would make this `{elaborated}`")
+        }
+        Some(elaborated) => {
+            let rule = if elaboration.rule_3.is_some() { 3 } else { 2 };
+            format!("rule {rule} would make this `{elaborated}`")
+        }
+    };
+    Some(Code::ElisionNote.error(&signature.ident, message))
+}
+
+/// `signature` with every lifetime which elision would fill in left out:
+/// references lose theirs, and lifetimes in paths become `'_`.
+pub(crate) fn elided_form(signature: &Signature) -> Signature {
+    struct Remove(BTreeSet<String>);
+    impl VisitMut for Remove {
+        fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+            if self.0.contains(&lifetime.ident.to_string()) {
+                *lifetime = Lifetime::new("'_", lifetime.span());
+            }
+        }
+
+        fn visit_type_reference_mut(&mut self, reference: &mut TypeReference) {
+            if let Some(lifetime) = &reference.lifetime {
+                if self.0.contains(&lifetime.ident.to_string()) {
+                    reference.lifetime = None;
+                }
+            }
+            visit_mut::visit_type_reference_mut(self, reference);
+        }
+
+        fn visit_receiver_mut(&mut self, receiver: &mut syn::Receiver) {
+            if let Some((_, lifetime @ Some(_))) = &mut receiver.reference {
+                if self
+                    .0
+                    .contains(&lifetime.as_ref().unwrap().ident.to_string())
+                {
+                    *lifetime = None;
+                }
+            }
+            visit_mut::visit_receiver_mut(self, receiver);
+        }
+    }
+
+    let elidable: BTreeSet<String> = elidable_lifetimes(signature)
+        .iter()
+        .map(|param| param.lifetime.ident.to_string())
+        .collect();
+    let mut elided = signature.clone();
+    elided.inputs.pop_punct();
+    elided.generics.params = elided
+        .generics
+        .params
+        .into_iter()
+        .filter(|param| match param {
+            GenericParam::Lifetime(param) => !elidable.contains(&param.lifetime.ident.to_string()),
+            _ => true,
+        })
+        .collect();
+    if elided.generics.params.is_empty() {
+        elided.generics.lt_token = None;
+        elided.generics.gt_token = None;
+    }
+    let mut remove = Remove(elidable);
+    for input in &mut elided.inputs {
+        remove.visit_fn_arg_mut(input);
+    }
+    remove.visit_return_type_mut(&mut elided.output);
+    elided
+}
+
+/// A warning showing `signature` as it could have been written with
+/// elision, for `#[show_elided]`.
+pub(crate) fn elided_form_note(signature: &Signature) -> TokenStream {
+    let message = if elidable_lifetimes(signature).is_empty() {
+        "elision couldn't fill in any of these lifetimes, so they all have to be written"
+            .to_string()
+    } else {
+        format!(
+            "with elision, this could be written `{}`",
+            render(&elided_form(signature))
+        )
+    };
+    Code::ElidedForm.warning(signature.ident.span(), message)
+}
+
+/// The lifetime parameters of `signature` which elision would fill in.
+pub(crate) fn elidable_lifetimes(signature: &Signature) -> Vec<&LifetimeParam> {
+    let inputs = input_uses(signature);
+    let mut outputs = Uses::default();
+    if let ReturnType::Type(_, ty) = &signature.output {
+        outputs.visit_type(ty);
+    }
+    // Lifetimes in the bounds can't be elided, so anything mentioned there
+    // is treated as pinned.
+    let mut bounds = Uses::default();
+    for param in &signature.generics.params {
+        match param {
+            GenericParam::Lifetime(param) => {
+                for bound in &param.bounds {
+                    bounds.pin(bound);
+                }
+            }
+            GenericParam::Type(param) => {
+                for bound in &param.bounds {
+                    bounds.visit_type_param_bound(bound);
+                }
+            }
+            GenericParam::Const(_) => {}
+        }
+    }
+    if let Some(where_clause) = &signature.generics.where_clause {
+        bounds.visit_where_clause(where_clause);
+    }
+
+    let input_lifetimes = input_positions(signature);
+    let receiver = receiver_lifetime(signature);
+
+    signature
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Lifetime(param) => Some(param),
+            _ => None,
+        })
+        .filter(|param| {
+            let name = param.lifetime.ident.to_string();
+            let in_outputs = outputs.counts.contains_key(&name);
+            param.bounds.is_empty()
+                && !inputs.pinned.contains_key(&name)
+                && !outputs.pinned.contains_key(&name)
+                && !bounds.counts.contains_key(&name)
+                && !bounds.pinned.contains_key(&name)
+                // Elided arguments each get their own lifetime, so one that
+                // is shared between arguments has to be written.
+                && inputs.counts.get(&name) == Some(&1)
+                && (!in_outputs
+                    || input_lifetimes == 1
+                    || receiver.as_ref() == Some(&param.lifetime))
+        })
+        .collect()
+}
+
+fn input_uses(signature: &Signature) -> Uses {
+    let mut inputs = Uses::default();
+    for input in &signature.inputs {
+        match input {
+            FnArg::Receiver(receiver) => inputs.visit_receiver(receiver),
+            FnArg::Typed(argument) => inputs.visit_type(&argument.ty),
+        }
+    }
+    inputs
+}
+
+/// How many places in the arguments have a lifetime, whether it's written
+/// or not; rule 2 only applies when this is one.
+pub(crate) fn input_positions(signature: &Signature) -> usize {
+    let inputs = input_uses(signature);
+    inputs.counts.values().sum::<usize>() + inputs.elided
+}
+
+/// The one lifetime in the arguments, once they've all been filled in.
+fn single_input_lifetime(signature: &Signature) -> Option<Lifetime> {
+    let inputs = input_uses(signature);
+    let name = inputs.counts.keys().next()?;
+    Some(Lifetime::new(
+        &format!("'{name}"),
+        proc_macro2::Span::call_site(),
+    ))
+}
+
+/// The lifetime of `&'a self`, or of the `&'a Self` in a receiver like
+/// `self: Pin<&'a mut Self>`, if the method has one. A receiver like
+/// `self: Box<Self>` doesn't borrow, so rule 3 has nothing to go on.
+fn receiver_lifetime(signature: &Signature) -> Option<Lifetime> {
+    struct FindSelf(Option<Option<Lifetime>>);
+    impl<'ast> Visit<'ast> for FindSelf {
+        fn visit_type_reference(&mut self, reference: &'ast TypeReference) {
+            let to_self = matches!(&*reference.elem, Type::Path(path) if path.qself.is_none() && path.path.is_ident("Self"));
+            if to_self && self.0.is_none() {
+                self.0 = Some(reference.lifetime.clone());
+            }
+            visit::visit_type_reference(self, reference);
+        }
+    }
+
+    let Some(FnArg::Receiver(receiver)) = signature.inputs.first() else {
+        return None;
+    };
+    if receiver.colon_token.is_none() {
+        return receiver.reference.as_ref()?.1.clone();
+    }
+    let mut find = FindSelf(None);
+    find.visit_type(&receiver.ty);
+    find.0.flatten()
+}
+
+/// How often each named lifetime appears in part of a signature.
+#[derive(Default)]
+struct Uses {
+    /// Lifetimes used somewhere elision could have put them. `'static` is
+    /// counted too, since it's still a lifetime in the arguments.
+    counts: BTreeMap<String, usize>,
+    /// Lifetimes used somewhere elision couldn't have put them, like a
+    /// `for<...>` type or a `+ 'a` bound.
+    pinned: BTreeMap<String, usize>,
+    /// References with no lifetime at all, or with `'_`.
+    elided: usize,
+    /// How many `Fn(..)`, `fn(..)` and lifetime bounds we're inside.
+    depth: usize,
+}
+
+impl Uses {
+    fn pin(&mut self, lifetime: &Lifetime) {
+        *self.pinned.entry(lifetime.ident.to_string()).or_default() += 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for Uses {
+    fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+        if lifetime.ident == "_" {
+            if self.depth == 0 {
+                self.elided += 1;
+            }
+        } else if self.depth > 0 {
+            self.pin(lifetime);
+        } else {
+            *self.counts.entry(lifetime.ident.to_string()).or_default() += 1;
+        }
+    }
+
+    fn visit_type_reference(&mut self, reference: &'ast TypeReference) {
+        if reference.lifetime.is_none() && self.depth == 0 {
+            self.elided += 1;
+        }
+        visit::visit_type_reference(self, reference);
+    }
+
+    fn visit_receiver(&mut self, receiver: &'ast syn::Receiver) {
+        if receiver.colon_token.is_some() {
+            return self.visit_type(&receiver.ty);
+        }
+        match &receiver.reference {
+            Some((_, Some(lifetime))) => self.visit_lifetime(lifetime),
+            Some((_, None)) => self.elided += 1,
+            None => {}
+        }
+    }
+
+    fn visit_type_bare_fn(&mut self, function: &'ast TypeBareFn) {
+        self.depth += 1;
+        visit::visit_type_bare_fn(self, function);
+        self.depth -= 1;
+    }
+
+    fn visit_parenthesized_generic_arguments(
+        &mut self,
+        arguments: &'ast syn::ParenthesizedGenericArguments,
+    ) {
+        self.depth += 1;
+        visit::visit_parenthesized_generic_arguments(self, arguments);
+        self.depth -= 1;
+    }
+
+    fn visit_type_param_bound(&mut self, bound: &'ast TypeParamBound) {
+        self.depth += 1;
+        visit::visit_type_param_bound(self, bound);
+        self.depth -= 1;
+    }
+}
+
+/// Fills in elided lifetimes: with a new name each in the arguments, or with
+/// `output` in the return type.
+struct Fill {
+    names: LifetimeNames,
+    in_output: bool,
+    /// What the rules give the return type, if any of them apply.
+    output: Option<Lifetime>,
+    added: Vec<Lifetime>,
+    /// How many elided lifetimes the return type had.
+    elided_outputs: usize,
+    /// How many `Fn(..)`, `fn(..)` and lifetime bounds we're inside, where
+    /// elision means something else.
+    depth: usize,
+}
+
+impl Fill {
+    fn lifetime(&mut self) -> Option<Lifetime> {
+        if self.in_output {
+            self.elided_outputs += 1;
+            return self.output.clone();
+        }
+        let lifetime = self.names.fresh();
+        self.added.push(lifetime.clone());
+        Some(lifetime)
+    }
+}
+
+impl VisitMut for Fill {
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" && self.depth == 0 {
+            if let Some(filled) = self.lifetime() {
+                *lifetime = filled;
+            }
+        }
+    }
+
+    fn visit_type_reference_mut(&mut self, reference: &mut TypeReference) {
+        if reference.lifetime.is_none() && self.depth == 0 {
+            reference.lifetime = self.lifetime();
+        }
+        visit_mut::visit_type_reference_mut(self, reference);
+    }
+
+    fn visit_receiver_mut(&mut self, receiver: &mut syn::Receiver) {
+        if receiver.colon_token.is_some() {
+            return self.visit_type_mut(&mut receiver.ty);
+        }
+        if let Some((_, lifetime @ None)) = &mut receiver.reference {
+            *lifetime = self.lifetime();
+        }
+    }
+
+    fn visit_type_bare_fn_mut(&mut self, function: &mut TypeBareFn) {
+        self.depth += 1;
+        visit_mut::visit_type_bare_fn_mut(self, function);
+        self.depth -= 1;
+    }
+
+    fn visit_parenthesized_generic_arguments_mut(
+        &mut self,
+        arguments: &mut syn::ParenthesizedGenericArguments,
+    ) {
+        self.depth += 1;
+        visit_mut::visit_parenthesized_generic_arguments_mut(self, arguments);
+        self.depth -= 1;
+    }
+
+    fn visit_type_param_bound_mut(&mut self, bound: &mut TypeParamBound) {
+        // `dyn Trait + '_` follows the rules for trait objects instead, but
+        // the `&str` in `dyn Iterator<Item = &str>` is elided as usual.
+        let lifetime = matches!(bound, TypeParamBound::Lifetime(_));
+        self.depth += usize::from(lifetime);
+        visit_mut::visit_type_param_bound_mut(self, bound);
+        self.depth -= usize::from(lifetime);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syn::parse_quote;
+
+    fn elided(signature: Signature) -> String {
+        render(&elided_form(&signature))
+    }
+
+    #[test]
+    fn the_elided_form_leaves_out_what_elision_fills_in() {
+        assert_eq!(
+            elided(parse_quote!(fn f<'a, T>(x: &'a str, y: Thing<'a>) -> Vec<T>)),
+            "fn f<'a, T>(x: &'a str, y: Thing<'a>) -> Vec<T>"
+        );
+        assert_eq!(
+            elided(parse_quote!(fn f<'a, 'b>(&'a self, y: &'b mut [&'static u8]) -> &'a str)),
+            "fn f(&self, y: &mut [&'static u8]) -> &str"
+        );
+        assert_eq!(
+            elided(parse_quote!(fn f<'a, T>(x: Thing<'a>,) -> &'a T)),
+            "fn f<T>(x: Thing<'_>) -> &T"
+        );
+    }
+
+    fn elidable(signature: Signature) -> Vec<String> {
+        elidable_lifetimes(&signature)
+            .iter()
+            .map(|param| param.lifetime.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn lifetimes_only_in_arguments_can_be_elided() {
+        assert_eq!(
+            elidable(parse_quote!(fn f<'a, 'b>(x: &'a str, y: &'b mut Vec<u8>))),
+            vec!["'a", "'b"]
+        );
+        // Elision would give these two different lifetimes.
+        assert!(elidable(parse_quote!(fn f<'a>(x: &'a str, y: &'a str))).is_empty());
+    }
+
+    #[test]
+    fn a_single_argument_lifetime_can_be_elided_in_the_return_type() {
+        assert_eq!(
+            elidable(parse_quote!(fn f<'a>(x: &'a str) -> &'a str)),
+            vec!["'a"]
+        );
+        assert_eq!(
+            elidable(parse_quote!(fn f<'a>(x: &'a str, y: u32) -> Vec<&'a str>)),
+            vec!["'a"]
+        );
+        assert!(elidable(parse_quote!(fn f<'a>(x: &'a str, y: &str) -> &'a str)).is_empty());
+        assert!(
+            elidable(parse_quote!(fn f<'a>(x: &'a str, y: &'static str) -> &'a str)).is_empty()
+        );
+    }
+
+    #[test]
+    fn self_lifetimes_can_be_elided_in_the_return_type() {
+        assert_eq!(
+            elidable(parse_quote!(fn f<'a, 'b>(&'a self, x: &'b str) -> &'a str)),
+            vec!["'a", "'b"]
+        );
+        assert_eq!(
+            elidable(parse_quote!(fn f<'a, 'b>(self: &'a Self, x: &'b str) -> &'a str)),
+            vec!["'a", "'b"]
+        );
+        assert_eq!(
+            elidable(parse_quote!(fn f<'a, 'b>(&'a self, x: &'b str) -> &'b str)),
+            vec!["'a"]
+        );
+    }
+
+    #[test]
+    fn bounded_and_higher_ranked_lifetimes_are_needed() {
+        assert!(elidable(parse_quote!(fn f<'a, T: 'a>(x: &'a T))).is_empty());
+        assert!(elidable(parse_quote!(fn f<'a, 'b: 'a>(x: &'a u8, y: &'b u8))).is_empty());
+        assert!(elidable(parse_quote!(fn f<'a>(x: Box<dyn Fn(&'a str) + 'a>))).is_empty());
+        assert!(elidable(parse_quote!(fn f<'a>(x: fn(&'a str)))).is_empty());
+        assert!(elidable(parse_quote!(fn f<'a, T>(x: &'a T) where T: Trait<'a>)).is_empty());
+    }
+
+    fn note(signature: Signature) -> Option<String> {
+        expansion_note(&signature).map(|diagnostic| diagnostic.message)
+    }
+
+    #[test]
+    fn notes_show_what_the_rules_would_do() {
+        assert_eq!(note(parse_quote!(fn f<'a>(x: &'a str) -> u32)), None);
+        assert_eq!(
+            note(parse_quote!(fn f<T>(x: &T, y: &mut u8))).unwrap(),
+            "rule 1 would make this `fn f<'a, 'b, T>(x: &'a T, y: &'b mut u8)`"
+        );
+        assert_eq!(
+            note(parse_quote!(fn f(x: &str) -> Option<&str>)).unwrap(),
+            "rule 2 would make this `fn f<'a>(x: &'a str) -> Option<&'a str>`"
+        );
+        assert_eq!(
+            note(parse_quote!(fn f<'a>(&self, x: &'a str) -> &str)).unwrap(),
+            "rule 3 would make this `fn f<'a, 'b>(&'b self, x: &'a str) -> &'b str`"
+        );
+        assert_eq!(
+            note(parse_quote!(fn f(x: &str, y: &str) -> &str)).unwrap(),
+            "rule 1 would make this `fn f<'a, 'b>(x: &'a str, y: &'b str) -> &str`, but \
+             neither rule 2 nor rule 3 says which argument the return type borrows \
+             from, so it has to be written"
+        );
+    }
+
+    #[test]
+    fn rule_three_looks_inside_arbitrary_self_types() {
+        assert_eq!(
+            note(parse_quote!(fn f(self: Pin<&mut Self>, x: &u8) -> &u8)).unwrap(),
+            "rule 3 would make this `fn f<'a, 'b>(self: Pin<&'a mut Self>, x: &'b u8) -> &'a u8`"
+        );
+        assert_eq!(
+            elidable(parse_quote!(fn f<'a, 'b>(self: Pin<&'a mut Self>, x: &'b u8) -> &'a u8)),
+            vec!["'a", "'b"]
+        );
+        assert_eq!(
+            note(parse_quote!(fn f(self: Box<Self>, x: &u8, y: &u8) -> &u8)).unwrap(),
+            "rule 1 would make this `fn f<'a, 'b>(self: Box<Self>, x: &'a u8, y: &'b u8) -> &u8`, \
+             but neither rule 2 nor rule 3 says which argument the return type borrows from, \
+             so it has to be written"
+        );
+        assert_eq!(
+            note(parse_quote!(fn f(self: Rc<Self>, x: &u8) -> &u8)).unwrap(),
+            "rule 2 would make this `fn f<'a>(self: Rc<Self>, x: &'a u8) -> &'a u8`"
+        );
+    }
+
+    #[test]
+    fn notes_fill_in_trait_object_arguments() {
+        assert_eq!(
+            note(parse_quote!(
+                fn f(x: &[u8], f: Box<dyn Fn(&u8)>) -> Box<dyn Iterator<Item = &u8> + '_>
+            ))
+            .unwrap(),
+            "rule 2 would make this `fn f<'a>(x: &'a [u8], f: Box<dyn Fn(&u8)>) -> \
+             Box<dyn Iterator<Item = &'a u8> + '_>`"
+        );
+    }
+
+    #[test]
+    fn elaboration_keeps_each_rule_apart() {
+        let elaboration = elaborate(&parse_quote!(fn f(&self, x: &str) -> &str));
+        assert_eq!(
+            elaboration.arguments,
+            "fn f<'a, 'b>(&'a self, x: &'b str) -> &str"
+        );
+        assert_eq!(elaboration.input_lifetimes, 2);
+        assert!(elaboration.rule_2.is_none());
+        assert_eq!(elaboration.rule_3.unwrap().to_string(), "'a");
+        assert_eq!(
+            elaboration.elaborated.unwrap(),
+            "fn f<'a, 'b>(&'a self, x: &'b str) -> &'a str"
+        );
+
+        let elaboration = elaborate(&parse_quote!(fn f(x: &str, y: &str) -> &str));
+        assert_eq!(elaboration.named.len(), 2);
+        assert_eq!(elaboration.elided_outputs, 1);
+        assert!(elaboration.elaborated.is_none());
+
+        let elaboration = elaborate(&parse_quote!(fn f<'a>(x: &'a str) -> u32));
+        assert!(elaboration.named.is_empty());
+        assert_eq!(
+            elaboration.elaborated.unwrap(),
+            "fn f<'a>(x: &'a str) -> u32"
+        );
+    }
+
+    #[test]
+    fn lifetimes_outside_the_function_are_left_alone() {
+        assert!(elidable(parse_quote!(fn f(&'a self) -> &'a str)).is_empty());
+    }
+}
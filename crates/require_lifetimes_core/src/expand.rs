@@ -0,0 +1,510 @@
+//! What each of the `require_lifetimes` macros expands to. The macros
+//! themselves have to live in a `proc-macro` crate, which can't export
+//! anything else, so they only forward to these.
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::Nothing;
+use syn::punctuated::Punctuated;
+use syn::{
+    Attribute, DeriveInput, ExprClosure, FnArg, ForeignItem, Ident, ImplItem, Item, Meta, Pat,
+    Signature, Token, TraitItem, TraitItemFn,
+};
+
+use crate::args::Options;
+use crate::diagnostic::{self, Code, Diagnostic};
+use crate::fields::{self, FieldOptions};
+use crate::{check, elision};
+
+/// `#[require_lifetimes(args)]` on `item`.
+pub fn require_lifetimes(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut errors = Vec::new();
+    let options = syn::parse2::<Options>(args).unwrap_or_else(|error| {
+        errors.extend(Code::Usage.wrap(error));
+        Options::default()
+    });
+
+    if !options.skip {
+        match signatures(item.clone(), "require_lifetimes") {
+            Ok(signatures) => {
+                for signature in &signatures {
+                    errors.extend(crate::check_signature(signature, &options));
+                }
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+
+    // The item is always handed back, so that a missing lifetime doesn't
+    // also cause a flood of errors about the function not existing. Clippy
+    // would rather the lifetimes were elided, which is the opposite of what
+    // the attribute asks for.
+    let mut output = quote! {
+        #[allow(clippy::needless_lifetimes)]
+        #item
+    };
+    output.extend(diagnostic::compile_errors(errors, options.json));
+    output
+}
+
+/// `#[elide_lifetimes(args)]` on `item`.
+pub fn elide_lifetimes(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut errors = Vec::new();
+    if let Err(error) = syn::parse2::<Nothing>(args) {
+        errors.extend(Code::Usage.wrap(error));
+    }
+    match signatures(item.clone(), "elide_lifetimes") {
+        Ok(signatures) => {
+            for signature in &signatures {
+                errors.extend(elision::needless_lifetimes(signature));
+            }
+        }
+        Err(error) => errors.push(error),
+    }
+
+    let mut output = item;
+    output.extend(diagnostic::compile_errors(errors, false));
+    output
+}
+
+/// `#[derive(RequireFieldLifetimes)]` on `input`.
+pub fn require_field_lifetimes(input: TokenStream) -> TokenStream {
+    let input = match syn::parse2::<DeriveInput>(input) {
+        Ok(input) => input,
+        Err(error) => return error.to_compile_error(),
+    };
+    let errors = match FieldOptions::from_attributes(&input.attrs) {
+        Ok(options) => fields::field_lifetimes(&input, &options),
+        Err(error) => Code::Usage.wrap(error).collect(),
+    };
+    diagnostic::compile_errors(errors, false)
+}
+
+/// `require_closure_lifetimes!(input)`.
+pub fn require_closure_lifetimes(input: TokenStream) -> TokenStream {
+    let closure = match syn::parse2::<ExprClosure>(input) {
+        Ok(closure) => closure,
+        Err(error) => return error.to_compile_error(),
+    };
+    let errors = diagnostic::compile_errors(
+        check::elided_references(&closure_signature(&closure), &Options::default()),
+        false,
+    );
+    quote!({
+        #errors
+        #closure
+    })
+}
+
+/// A signature with the closure's typed arguments and return type, for the
+/// checks written for functions. There is no elision note for closures,
+/// since the rules which fill in their lifetimes aren't the ones for `fn`.
+fn closure_signature(closure: &ExprClosure) -> Signature {
+    let inputs = closure.inputs.iter().filter_map(|input| match input {
+        Pat::Type(argument) => Some(FnArg::Typed(argument.clone())),
+        _ => None,
+    });
+    Signature {
+        constness: None,
+        asyncness: None,
+        unsafety: None,
+        abi: None,
+        fn_token: Default::default(),
+        ident: Ident::new("closure", Span::call_site()),
+        generics: Default::default(),
+        paren_token: Default::default(),
+        inputs: inputs.collect(),
+        variadic: None,
+        output: closure.output.clone(),
+    }
+}
+
+/// `#[show_elided(args)]` on `item`.
+pub fn show_elided(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut errors = Vec::new();
+    if let Err(error) = syn::parse2::<Nothing>(args) {
+        errors.extend(Code::Usage.wrap(error));
+    }
+    let mut output = match syn::parse2::<Item>(item.clone()) {
+        // A function might be a method, where the warning can't go next to
+        // it, so it goes inside instead.
+        Ok(Item::Fn(mut function)) => {
+            let warning = elision::elided_form_note(&function.sig);
+            function.block.stmts.insert(0, syn::parse_quote!(#warning));
+            quote!(#function)
+        }
+        Ok(parsed @ (Item::Impl(_) | Item::Trait(_) | Item::Mod(_) | Item::ForeignMod(_))) => {
+            let mut signatures = Vec::new();
+            collect_signatures(parsed, &mut signatures);
+            let mut output = item;
+            output.extend(signatures.iter().map(elision::elided_form_note));
+            output
+        }
+        _ => {
+            errors.push(Code::Usage.error_at(
+                Span::call_site(),
+                "#[show_elided] can only be used on functions with bodies, impl blocks, \
+                 traits, modules and extern blocks",
+            ));
+            item
+        }
+    };
+    output.extend(diagnostic::compile_errors(errors, false));
+    output
+}
+
+/// The signatures `#[attribute]` should check in `item`.
+fn signatures(item: TokenStream, attribute: &str) -> Result<Vec<Signature>, Diagnostic> {
+    if let Ok(item) = syn::parse2::<Item>(item.clone()) {
+        if matches!(
+            item,
+            Item::Fn(_) | Item::Impl(_) | Item::Trait(_) | Item::Mod(_) | Item::ForeignMod(_)
+        ) {
+            let mut signatures = Vec::new();
+            collect_signatures(item, &mut signatures);
+            return Ok(signatures);
+        }
+    }
+    // A method in a trait, or a function in an `extern` block, doesn't have
+    // a body, so it isn't an `Item`.
+    match syn::parse2::<TraitItemFn>(item) {
+        Ok(function) => Ok(vec![function.sig]),
+        Err(_) => Err(Code::Usage.error_at(
+            Span::call_site(),
+            format!(
+                "#[{attribute}] can only be used on functions, impl blocks, traits, modules \
+                 and extern blocks"
+            ),
+        )),
+    }
+}
+
+/// Adds the signatures of every function in `item` to `signatures`.
+///
+/// Anything inside `item` which has an attribute of its own is left for that
+/// attribute to check (or skip), so it isn't reported twice.
+fn collect_signatures(item: Item, signatures: &mut Vec<Signature>) {
+    match item {
+        Item::Fn(function) => signatures.push(function.sig),
+        Item::Impl(block) => {
+            for item in block.items {
+                match item {
+                    ImplItem::Fn(method) if !has_own_attribute(&method.attrs) => {
+                        signatures.push(method.sig)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Item::Trait(definition) => {
+            for item in definition.items {
+                match item {
+                    TraitItem::Fn(method) if !has_own_attribute(&method.attrs) => {
+                        signatures.push(method.sig)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Item::ForeignMod(block) => {
+            for item in block.items {
+                match item {
+                    ForeignItem::Fn(function) if !has_own_attribute(&function.attrs) => {
+                        signatures.push(function.sig)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Item::Mod(module) => {
+            for item in module.content.map(|(_, items)| items).unwrap_or_default() {
+                let attrs = match &item {
+                    Item::Fn(function) => &function.attrs,
+                    Item::Impl(block) => &block.attrs,
+                    Item::Trait(definition) => &definition.attrs,
+                    Item::Mod(module) => &module.attrs,
+                    Item::ForeignMod(block) => &block.attrs,
+                    _ => continue,
+                };
+                if !has_own_attribute(attrs) {
+                    collect_signatures(item, signatures);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_own_attribute(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| is_own_attribute(&attr.meta))
+}
+
+/// Whether `meta` is one of this crate's attributes, perhaps behind a
+/// `cfg_attr`. Those reach us unexpanded when they're on something inside
+/// the item, and the condition can't be checked from here, so the item is
+/// left to its own attribute either way.
+fn is_own_attribute(meta: &Meta) -> bool {
+    if let Meta::List(list) = meta {
+        if list.path.is_ident("cfg_attr") {
+            return list
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .is_ok_and(|metas| metas.iter().skip(1).any(is_own_attribute));
+        }
+    }
+    meta.path().segments.last().is_some_and(|segment| {
+        segment.ident == "require_lifetimes" || segment.ident == "elide_lifetimes"
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(item: TokenStream) -> Vec<String> {
+        signatures(item, "require_lifetimes")
+            .unwrap()
+            .iter()
+            .map(|signature| signature.ident.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn impl_blocks_check_every_method() {
+        let item = quote! {
+            impl Thing {
+                const X: u32 = 1;
+                fn first(&self) -> &str { "" }
+                fn second(x: &u32) {}
+            }
+        };
+        assert_eq!(names(item), vec!["first", "second"]);
+    }
+
+    #[test]
+    fn methods_with_their_own_attribute_are_left_alone() {
+        let item = quote! {
+            impl Thing {
+                #[require_lifetimes(skip)]
+                fn skipped(&self) -> &str { "" }
+                #[require_lifetimes::require_lifetimes]
+                fn checked_separately(&self) -> &str { "" }
+                fn checked(&self) -> &str { "" }
+            }
+        };
+        assert_eq!(names(item), vec!["checked"]);
+    }
+
+    #[test]
+    fn attributes_behind_cfg_attr_are_found() {
+        let item = quote! {
+            impl Thing {
+                #[inline]
+                #[cfg_attr(test, require_lifetimes(skip))]
+                fn skipped_in_tests(&self) -> &str { "" }
+                #[cfg_attr(all(), track_caller, require_lifetimes::elide_lifetimes)]
+                fn checked_separately<'a>(&'a self) -> &'a str { "" }
+                #[cfg_attr(test, inline)]
+                #[doc = "Checked."]
+                #[track_caller]
+                fn checked(&self) -> &str { "" }
+            }
+        };
+        assert_eq!(names(item), vec!["checked"]);
+    }
+
+    #[test]
+    fn other_attributes_are_kept_in_order() {
+        let output = require_lifetimes(
+            quote!(),
+            quote! {
+                #[cfg_attr(test, inline)]
+                /// Documented.
+                #[track_caller]
+                fn f<'a>(x: &'a str) -> &'a str { x }
+            },
+        );
+        let expected = quote! {
+            #[allow(clippy::needless_lifetimes)]
+            #[cfg_attr(test, inline)]
+            /// Documented.
+            #[track_caller]
+            fn f<'a>(x: &'a str) -> &'a str { x }
+        };
+        assert_eq!(output.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn trait_impls_and_extern_blocks_are_checked() {
+        let item = quote! {
+            impl Display for Thing {
+                fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result { Ok(()) }
+            }
+        };
+        assert_eq!(names(item), vec!["fmt"]);
+
+        let item = quote! {
+            extern "C" {
+                static COUNT: u32;
+                fn first(bytes: *const u8, len: &usize) -> &u8;
+                #[require_lifetimes(skip)]
+                fn skipped(x: &u8) -> &u8;
+                fn variadic(format: &u8, ...) -> i32;
+            }
+        };
+        assert_eq!(names(item), vec!["first", "variadic"]);
+
+        let item = quote! {
+            mod ffi {
+                extern "C" {
+                    fn nested(x: &u8) -> &u8;
+                }
+            }
+        };
+        assert_eq!(names(item), vec!["nested"]);
+    }
+
+    #[test]
+    fn traits_check_methods_with_and_without_bodies() {
+        let item = quote! {
+            trait Thing {
+                type Output;
+                fn required(&self, x: &str) -> &str;
+                #[require_lifetimes(skip)]
+                fn skipped(&self) -> &str;
+                fn provided(&self) -> &str { "" }
+            }
+        };
+        assert_eq!(names(item), vec!["required", "provided"]);
+    }
+
+    #[test]
+    fn modules_are_checked_recursively() {
+        let item = quote! {
+            mod outer {
+                fn free(x: &u32) {}
+                impl Thing {
+                    fn method(&self) {}
+                }
+                mod inner {
+                    fn nested(x: &u32) {}
+                }
+                #[require_lifetimes(skip)]
+                mod skipped {
+                    fn ignored(x: &u32) {}
+                }
+                #[require_lifetimes(skip)]
+                fn also_ignored(x: &u32) {}
+            }
+        };
+        assert_eq!(names(item), vec!["free", "method", "nested"]);
+    }
+
+    #[test]
+    fn skip_turns_off_checking() {
+        let output = require_lifetimes(
+            quote!(skip),
+            quote!(
+                fn f(x: &str) -> &str {
+                    x
+                }
+            ),
+        );
+        assert!(!output.to_string().contains("compile_error"));
+
+        let output = require_lifetimes(
+            quote!(!),
+            quote!(
+                fn f(x: &str) -> &str {
+                    x
+                }
+            ),
+        );
+        assert!(output.to_string().contains("compile_error"));
+    }
+
+    #[test]
+    fn anonymous_lifetime_arguments_conflict() {
+        let options = syn::parse2::<Options>(quote!(!, deny_anonymous)).unwrap();
+        assert!(!options.allow_anonymous);
+
+        let error = syn::parse2::<Options>(quote!(allow_anonymous, deny_anonymous)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`deny_anonymous` can't be used along with `allow_anonymous`"
+        );
+    }
+
+    #[test]
+    fn exemptions_are_parsed() {
+        let options = syn::parse2::<Options>(quote!(except(self, out, input))).unwrap();
+        assert_eq!(options.except, vec!["self", "out", "input"]);
+        assert!(options.exempts("self") && !options.exempts("other"));
+    }
+
+    #[test]
+    fn names_are_parsed_with_or_without_quotes() {
+        let options = syn::parse2::<Options>(quote!(names("'input", "output"))).unwrap();
+        assert_eq!(options.names.unwrap(), vec!["input", "output"]);
+
+        let error = syn::parse2::<Options>(quote!(names("two words"))).unwrap_err();
+        assert_eq!(error.to_string(), "not a lifetime name");
+    }
+
+    #[test]
+    fn misuse_has_a_code_too() {
+        let output = require_lifetimes(
+            quote!(skp),
+            quote!(
+                fn f() {}
+            ),
+        );
+        assert!(output.to_string().contains("[LK0010] unknown argument"));
+
+        let output = require_lifetimes(
+            quote!(),
+            quote!(
+                struct S;
+            ),
+        );
+        assert!(output
+            .to_string()
+            .contains("[LK0010] #[require_lifetimes] can only"));
+    }
+
+    #[test]
+    fn closures_are_checked_like_functions() {
+        let signature =
+            closure_signature(&syn::parse_quote!(|x: &str, y, z: &'a u8| -> &str { x }));
+        let errors = check::elided_references(&signature, &Options::default());
+        let messages: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "[LK0001] `&str` here needs a name such as `&'b str`",
+                "[LK0002] `&str` here needs a name such as `&'c str`",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_can_be_asked_for() {
+        let output = require_lifetimes(
+            quote!(json),
+            quote!(
+                fn f(x: &u8) {}
+            ),
+        );
+        assert!(output.to_string().contains(
+            r#"{\"code\":\"LK0001\",\"message\":\"`&u8` here needs a name such as `&'a u8`\"}"#
+        ));
+    }
+
+    #[test]
+    fn unknown_arguments_are_errors() {
+        let error = syn::parse2::<Options>(quote!(skp)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown argument `skp` to #[require_lifetimes]"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+//! Checking the lifetimes on the fields of a struct or enum, for
+//! `#[derive(RequireFieldLifetimes)]`.
+
+use std::collections::BTreeMap;
+
+use syn::parse::{Parse, ParseStream};
+use syn::visit::Visit;
+use syn::{Attribute, Data, DeriveInput, Field, Fields, Ident, Lifetime, Token};
+
+use crate::diagnostic::{Code, Diagnostic};
+
+/// What `#[field_lifetimes(...)]` on the type asked for.
+#[derive(Debug, Default)]
+pub(crate) struct FieldOptions {
+    /// Whether every field needs lifetimes of its own, rather than sharing
+    /// one lifetime between all the references in the type.
+    pub(crate) separate: bool,
+}
+
+impl Parse for FieldOptions {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut options = FieldOptions::default();
+        while !input.is_empty() {
+            let name: Ident = input.parse()?;
+            match name.to_string().as_str() {
+                "separate" => options.separate = true,
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        format!("unknown argument `{name}` to #[field_lifetimes]"),
+                    ))
+                }
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(options)
+    }
+}
+
+impl FieldOptions {
+    /// The options given by the `#[field_lifetimes(...)]` attributes in
+    /// `attrs`, if there are any.
+    pub(crate) fn from_attributes(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = FieldOptions::default();
+        for attr in attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("field_lifetimes"))
+        {
+            let parsed: FieldOptions = attr.parse_args()?;
+            options.separate |= parsed.separate;
+        }
+        Ok(options)
+    }
+}
+
+/// Returns an error for every `'_` in the fields of `input`, and, if
+/// `options` asks for separate lifetimes, for every lifetime which more than
+/// one field of the same struct or variant uses.
+pub(crate) fn field_lifetimes(input: &DeriveInput, options: &FieldOptions) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    match &input.data {
+        Data::Struct(data) => check_fields(&data.fields, options, &mut errors),
+        Data::Enum(data) => {
+            // Only one variant exists at a time, so they can share lifetimes.
+            for variant in &data.variants {
+                check_fields(&variant.fields, options, &mut errors);
+            }
+        }
+        Data::Union(data) => {
+            check_fields(&Fields::Named(data.fields.clone()), options, &mut errors)
+        }
+    }
+    errors
+}
+
+fn check_fields(fields: &Fields, options: &FieldOptions, errors: &mut Vec<Diagnostic>) {
+    // Each lifetime name, and the field that used it first.
+    let mut owners = BTreeMap::new();
+    for (index, field) in fields.iter().enumerate() {
+        let name = field_name(field, index);
+        let mut lifetimes = Lifetimes::default();
+        lifetimes.visit_type(&field.ty);
+        for lifetime in lifetimes.anonymous {
+            let message = format!(
+                "`'_` can't be used in `{name}`; \
+                 declare a lifetime such as `'a` on the type and use that"
+            );
+            errors.push(Code::Anonymous.error(lifetime, message));
+        }
+        if !options.separate {
+            continue;
+        }
+        for (lifetime_name, lifetime) in lifetimes.named {
+            match owners.get(&lifetime_name) {
+                Some(owner) => {
+                    let message = format!(
+                        "`{lifetime}` is already used by `{owner}`; \
+                         give `{name}` a lifetime of its own"
+                    );
+                    errors.push(Code::SharedLifetime.error(&lifetime, message));
+                }
+                None => {
+                    owners.insert(lifetime_name, name.clone());
+                }
+            }
+        }
+    }
+}
+
+/// How a field is referred to: its name, or its position in a tuple struct.
+fn field_name(field: &Field, index: usize) -> String {
+    match &field.ident {
+        Some(ident) => ident.to_string(),
+        None => index.to_string(),
+    }
+}
+
+/// The lifetimes used in a field's type.
+#[derive(Default)]
+struct Lifetimes {
+    anonymous: Vec<Lifetime>,
+    /// The first use of each name other than `'static`, in order.
+    named: Vec<(String, Lifetime)>,
+}
+
+impl<'ast> Visit<'ast> for Lifetimes {
+    fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+        let name = lifetime.ident.to_string();
+        if name == "_" {
+            self.anonymous.push(lifetime.clone());
+        } else if name != "static" && !self.named.iter().any(|(used, _)| *used == name) {
+            self.named.push((name, lifetime.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syn::parse_quote;
+
+    /// The messages for `input`, without their codes.
+    fn messages(input: DeriveInput) -> Vec<String> {
+        let options = FieldOptions::from_attributes(&input.attrs).unwrap();
+        field_lifetimes(&input, &options)
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn named_lifetimes_pass() {
+        assert!(messages(parse_quote! {
+            struct Split<'a> { first: &'a str, rest: &'a str, len: usize }
+        })
+        .is_empty());
+        assert!(messages(parse_quote! {
+            #[field_lifetimes(separate)]
+            struct Split<'a, 'b> { first: &'a str, rest: &'b str, all: &'static str }
+        })
+        .is_empty());
+    }
+
+    #[test]
+    fn anonymous_lifetimes_are_errors() {
+        assert_eq!(
+            messages(parse_quote! {
+                struct Wrapper(&'_ str, Vec<Thing<'_>>);
+            }),
+            vec![
+                "`'_` can't be used in `0`; declare a lifetime such as `'a` on the type and use that",
+                "`'_` can't be used in `1`; declare a lifetime such as `'a` on the type and use that",
+            ]
+        );
+    }
+
+    #[test]
+    fn separate_lifetimes_can_be_required() {
+        assert_eq!(
+            messages(parse_quote! {
+                #[field_lifetimes(separate)]
+                struct Split<'a, 'b> {
+                    first: &'a str,
+                    rest: &'a [&'a str],
+                    last: (&'b str, &'a str),
+                }
+            }),
+            vec![
+                "`'a` is already used by `first`; give `rest` a lifetime of its own",
+                "`'a` is already used by `first`; give `last` a lifetime of its own",
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_variants_are_checked_separately() {
+        assert_eq!(
+            messages(parse_quote! {
+                #[field_lifetimes(separate)]
+                enum Token<'a, 'b> {
+                    Word(&'a str),
+                    Pair { left: &'a str, right: &'a str },
+                    Other(&'b str),
+                }
+            }),
+            vec!["`'a` is already used by `left`; give `right` a lifetime of its own"]
+        );
+    }
+
+    #[test]
+    fn unknown_arguments_are_errors() {
+        let input: DeriveInput = parse_quote! {
+            #[field_lifetimes(seperate)]
+            struct S;
+        };
+        let error = FieldOptions::from_attributes(&input.attrs).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "unknown argument `seperate` to #[field_lifetimes]"
+        );
+    }
+}
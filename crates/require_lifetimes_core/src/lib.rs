@@ -0,0 +1,92 @@
+//! The checks behind the `#[require_lifetimes]` attribute, as a library.
+//!
+//! [`check_signature`] runs the attribute's checks on a signature that has
+//! already been parsed, so the lifetimekata CLI, tests and other tools can
+//! check a snippet without a full rustc invocation:
+//!
+//! ```
+//! use require_lifetimes_core::{check_signature, Code, Options};
+//!
+//! let signature = syn::parse_str("fn first(words: &[String]) -> &str").unwrap();
+//! let codes: Vec<Code> = check_signature(&signature, &Options::default())
+//!     .iter()
+//!     .map(|diagnostic| diagnostic.code)
+//!     .collect();
+//! assert_eq!(
+//!     codes,
+//!     [Code::ElisionNote, Code::ElidedArgument, Code::ElidedReturn]
+//! );
+//! ```
+//!
+//! [`elaborate`] shows what the elision rules make of a signature, rule by
+//! rule, which is what `lifetimekata explain 'fn ...'` prints.
+//!
+//! [`Options`] parses from the attribute's arguments, like
+//! `syn::parse_str::<Options>("deny_anonymous, strict")`; the
+//! `require_lifetimes` crate documents what each of them does.
+
+use syn::Signature;
+
+mod args;
+mod check;
+mod diagnostic;
+mod elision;
+#[doc(hidden)]
+pub mod expand;
+mod fields;
+mod strict;
+
+pub use args::Options;
+pub use diagnostic::{Code, Diagnostic};
+pub use elision::{elaborate, Elaboration};
+
+/// Everything `#[require_lifetimes]` with `options` would report about
+/// `signature`, in the order the attribute reports it.
+pub fn check_signature(signature: &Signature, options: &Options) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let found = check::elided_references(signature, options);
+    if !found.is_empty() && options.notes {
+        diagnostics.extend(elision::expansion_note(signature));
+    }
+    diagnostics.extend(found);
+    if options.strict {
+        diagnostics.extend(strict::superfluous_lifetimes(signature));
+    }
+    if !options.allow_implied_bounds {
+        diagnostics.extend(strict::implied_bounds(signature));
+    }
+    diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn diagnostics_keep_the_code_apart() {
+        let options = syn::parse_str::<Options>("no_notes, strict").unwrap();
+        let diagnostics = check_signature(&parse_quote!(fn f<'a>(x: &str)), &options);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    Code::ElidedArgument,
+                    "`&str` here needs a name such as `&'b str`"
+                ),
+                (
+                    Code::UnusedLifetime,
+                    "`'a` is declared but never used; remove it"
+                ),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "[LK0001] `&str` here needs a name such as `&'b str`"
+        );
+    }
+}
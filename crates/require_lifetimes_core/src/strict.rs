@@ -0,0 +1,308 @@
+//! Finding lifetimes a signature declares but doesn't need, for
+//! `#[require_lifetimes(strict)]`, so that an exercise can't be passed by
+//! annotating more than it asks for.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use syn::visit::{self, Visit};
+use syn::{
+    FnArg, GenericParam, Lifetime, ReturnType, Signature, TypeBareFn, TypeImplTrait, TypeReference,
+    TypeTraitObject, WherePredicate,
+};
+
+use crate::check::render;
+use crate::diagnostic::{Code, Diagnostic};
+
+/// Returns an error for every lifetime parameter of `signature` which isn't
+/// used, and for every `'a: 'b` bound which always holds or which follows
+/// from the other bounds.
+///
+/// Only the signature is looked at, so a lifetime which is only named in the
+/// function's body counts as unused.
+pub(crate) fn superfluous_lifetimes(signature: &Signature) -> Vec<Diagnostic> {
+    let used = used_lifetimes(signature);
+    let mut errors = Vec::new();
+    for param in signature.generics.lifetimes() {
+        if !used.contains(&param.lifetime.ident.to_string()) {
+            let message = format!("`{}` is declared but never used; remove it", param.lifetime);
+            errors.push(Code::UnusedLifetime.error(&param.lifetime, message));
+        }
+    }
+
+    // Bounds naming an unused lifetime go with it, so they aren't reported
+    // twice.
+    let bounds: Vec<(&Lifetime, &Lifetime)> = outlives_bounds(signature)
+        .into_iter()
+        .filter(|(longer, shorter)| {
+            [longer, shorter].iter().all(|lifetime| {
+                lifetime.ident == "static" || used.contains(&lifetime.ident.to_string())
+            })
+        })
+        .collect();
+    // Going from the last bound back, so that of two bounds which say the
+    // same thing, the one written later is the one reported.
+    let mut needless = BTreeMap::new();
+    for (index, (longer, shorter)) in bounds.iter().enumerate().rev() {
+        let reason = if longer.ident == shorter.ident || longer.ident == "static" {
+            "always holds"
+        } else {
+            let others: Vec<_> = bounds
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index && !needless.contains_key(other))
+                .map(|(_, bound)| *bound)
+                .collect();
+            if !outlives(&others, longer, shorter) {
+                continue;
+            }
+            "follows from the other bounds"
+        };
+        needless.insert(index, reason);
+    }
+    for (index, reason) in needless {
+        let (longer, shorter) = bounds[index];
+        let message = format!("`{longer}: {shorter}` {reason}; remove it");
+        errors.push(Code::NeedlessBound.error(shorter, message));
+    }
+    errors
+}
+
+/// Returns an error for every `'a: 'b` bound in `signature` which a type in
+/// it already implies: `&'b T` can only exist if everything in `T`
+/// outlives `'b`, so a bound saying so adds nothing.
+pub(crate) fn implied_bounds(signature: &Signature) -> Vec<Diagnostic> {
+    let mut implied = Implied::default();
+    for input in &signature.inputs {
+        match input {
+            FnArg::Receiver(receiver) => implied.visit_receiver(receiver),
+            FnArg::Typed(argument) => implied.visit_type(&argument.ty),
+        }
+    }
+    implied.visit_return_type(&signature.output);
+
+    let mut errors = Vec::new();
+    for (longer, shorter) in outlives_bounds(signature) {
+        let key = (longer.ident.to_string(), shorter.ident.to_string());
+        if let Some(reference) = implied.0.get(&key) {
+            let message =
+                format!("`{longer}: {shorter}` is already implied by `{reference}`; remove it");
+            errors.push(Code::ImpliedBound.error(shorter, message));
+        }
+    }
+    errors
+}
+
+/// The `('a, 'b)` pairs which references like `&'b &'a T` imply, each with
+/// the first reference that implies it.
+#[derive(Default)]
+struct Implied(BTreeMap<(String, String), String>);
+
+impl<'ast> Visit<'ast> for Implied {
+    fn visit_type_reference(&mut self, reference: &'ast TypeReference) {
+        if let Some(outer) = &reference.lifetime {
+            let mut inner = Inner(Vec::new());
+            inner.visit_type(&reference.elem);
+            for lifetime in inner.0 {
+                if lifetime.ident != outer.ident {
+                    let key = (lifetime.ident.to_string(), outer.ident.to_string());
+                    self.0.entry(key).or_insert_with(|| render(reference));
+                }
+            }
+        }
+        visit::visit_type_reference(self, reference);
+    }
+}
+
+/// The lifetimes in a type which it has to outlive. Those in trait objects,
+/// `impl Trait` and function types don't count: `dyn Trait<'a> + 'b` only
+/// has to outlive `'b`, and `fn(&'a str)` doesn't have to outlive anything.
+struct Inner(Vec<Lifetime>);
+
+impl<'ast> Visit<'ast> for Inner {
+    fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+        if lifetime.ident != "_" && lifetime.ident != "static" {
+            self.0.push(lifetime.clone());
+        }
+    }
+
+    fn visit_type_trait_object(&mut self, _: &'ast TypeTraitObject) {}
+
+    fn visit_type_impl_trait(&mut self, _: &'ast TypeImplTrait) {}
+
+    fn visit_type_bare_fn(&mut self, _: &'ast TypeBareFn) {}
+}
+
+/// The names of the lifetimes used anywhere in `signature` except in
+/// `'a: 'b` bounds and their own declarations.
+fn used_lifetimes(signature: &Signature) -> BTreeSet<String> {
+    struct Collect(BTreeSet<String>);
+    impl<'ast> Visit<'ast> for Collect {
+        fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+            self.0.insert(lifetime.ident.to_string());
+        }
+    }
+
+    let mut collect = Collect(BTreeSet::new());
+    for input in &signature.inputs {
+        match input {
+            FnArg::Receiver(receiver) => collect.visit_receiver(receiver),
+            FnArg::Typed(argument) => collect.visit_type(&argument.ty),
+        }
+    }
+    if let ReturnType::Type(_, ty) = &signature.output {
+        collect.visit_type(ty);
+    }
+    for param in signature.generics.type_params() {
+        for bound in &param.bounds {
+            collect.visit_type_param_bound(bound);
+        }
+    }
+    if let Some(where_clause) = &signature.generics.where_clause {
+        for predicate in &where_clause.predicates {
+            if let WherePredicate::Type(predicate) = predicate {
+                collect.visit_predicate_type(predicate);
+            }
+        }
+    }
+    collect.0
+}
+
+/// Every `'a: 'b` bound in `signature`, as `('a, 'b)`, whether it was
+/// written on the parameter or in the `where` clause.
+fn outlives_bounds(signature: &Signature) -> Vec<(&Lifetime, &Lifetime)> {
+    let mut bounds = Vec::new();
+    for param in &signature.generics.params {
+        if let GenericParam::Lifetime(param) = param {
+            bounds.extend(param.bounds.iter().map(|bound| (&param.lifetime, bound)));
+        }
+    }
+    if let Some(where_clause) = &signature.generics.where_clause {
+        for predicate in &where_clause.predicates {
+            if let WherePredicate::Lifetime(predicate) = predicate {
+                bounds.extend(
+                    predicate
+                        .bounds
+                        .iter()
+                        .map(|bound| (&predicate.lifetime, bound)),
+                );
+            }
+        }
+    }
+    bounds
+}
+
+/// Whether `bounds` say that `longer` outlives `shorter`, following them
+/// from one lifetime to the next.
+fn outlives(bounds: &[(&Lifetime, &Lifetime)], longer: &Lifetime, shorter: &Lifetime) -> bool {
+    let mut reached = BTreeSet::from([longer.ident.to_string()]);
+    let mut pending = vec![longer.ident.to_string()];
+    while let Some(current) = pending.pop() {
+        for (from, to) in bounds {
+            if from.ident == current && reached.insert(to.ident.to_string()) {
+                pending.push(to.ident.to_string());
+            }
+        }
+    }
+    reached.contains(&shorter.ident.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use syn::parse_quote;
+
+    /// The messages for `signature`, without their codes.
+    fn messages(signature: Signature) -> Vec<String> {
+        superfluous_lifetimes(&signature)
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn used_lifetimes_pass() {
+        assert!(messages(parse_quote!(
+            fn f<'a, 'b: 'a, 'c, T: Trait<'c>>(x: &'a str, y: &'b str, t: T) -> &'a str
+        ))
+        .is_empty());
+        assert!(messages(parse_quote!(
+            fn f<'a, 'b, T>(x: &'a T, y: &'b T) where 'a: 'b, T: 'a
+        ))
+        .is_empty());
+    }
+
+    #[test]
+    fn unused_lifetimes_are_errors() {
+        assert_eq!(
+            messages(parse_quote!(fn f<'a, 'b: 'a, 'c>(x: &'a str) where 'c: 'a)),
+            vec![
+                "`'b` is declared but never used; remove it",
+                "`'c` is declared but never used; remove it",
+            ]
+        );
+    }
+
+    #[test]
+    fn needless_bounds_are_errors() {
+        assert_eq!(
+            messages(parse_quote!(
+                fn f<'a: 'a, 'b: 'c, 'c>(x: &'a str, y: &'b str, z: &'c str)
+                where 'static: 'a
+            )),
+            vec![
+                "`'a: 'a` always holds; remove it",
+                "`'static: 'a` always holds; remove it",
+            ]
+        );
+        assert_eq!(
+            messages(parse_quote!(
+                fn f<'a: 'b, 'b: 'c, 'c>(x: &'a str, y: &'b str, z: &'c str)
+                where 'a: 'c, 'b: 'c
+            )),
+            vec![
+                "`'a: 'c` follows from the other bounds; remove it",
+                "`'b: 'c` follows from the other bounds; remove it",
+            ]
+        );
+        // Neither of these follows from the other.
+        assert!(messages(parse_quote!(
+            fn f<'a: 'b, 'b: 'a>(x: &'a str, y: &'b str)
+        ))
+        .is_empty());
+    }
+
+    fn implied(signature: Signature) -> Vec<String> {
+        implied_bounds(&signature)
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn bounds_implied_by_references_are_errors() {
+        assert_eq!(
+            implied(parse_quote!(
+                fn f<'a: 'b, 'b, 'c, 'd>(x: &'b &'a str, y: &'d mut Vec<Thing<'c>>)
+                where 'c: 'd
+            )),
+            vec![
+                "`'a: 'b` is already implied by `&'b &'a str`; remove it",
+                "`'c: 'd` is already implied by `&'d mut Vec<Thing<'c>>`; remove it",
+            ]
+        );
+    }
+
+    #[test]
+    fn bounds_the_types_dont_imply_pass() {
+        assert!(implied(parse_quote!(
+            fn f<'a: 'b, 'b>(x: &'a str, y: &'b str) -> &'b str
+        ))
+        .is_empty());
+        // The other way around from what the reference implies.
+        assert!(implied(parse_quote!(fn f<'a, 'b: 'a>(x: &'b &'a str))).is_empty());
+        assert!(implied(parse_quote!(
+            fn f<'a: 'b, 'b>(x: &'b dyn Trait<'a>, y: &'b fn(&'a str))
+        ))
+        .is_empty());
+    }
+}
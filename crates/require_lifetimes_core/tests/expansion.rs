@@ -0,0 +1,100 @@
+//! Snapshots of what each macro expands to, so that a change to the
+//! expansion shows up as a diff to review rather than only as a UI test
+//! starting or stopping to pass. `cargo insta review` updates them.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use require_lifetimes_core::expand;
+
+/// `tokens` as rustfmt would lay them out, for readable snapshots.
+fn pretty(tokens: TokenStream) -> String {
+    prettyplease::unparse(&syn::parse2(tokens).expect("the expansion is a valid file"))
+}
+
+#[test]
+fn explicit_function() {
+    insta::assert_snapshot!(pretty(expand::require_lifetimes(
+        quote!(),
+        quote! {
+            fn longest<'a>(x: &'a str, y: &'a str) -> &'a str {
+                if x.len() > y.len() { x } else { y }
+            }
+        },
+    )));
+}
+
+#[test]
+fn elided_function() {
+    insta::assert_snapshot!(pretty(expand::require_lifetimes(
+        quote!(),
+        quote! {
+            fn first(words: &[String], count: &mut usize) -> &str {
+                *count += 1;
+                &words[0]
+            }
+        },
+    )));
+}
+
+#[test]
+fn impl_block() {
+    insta::assert_snapshot!(pretty(expand::require_lifetimes(
+        quote!(deny_anonymous),
+        quote! {
+            impl Counter {
+                fn get<'a>(&'a self) -> &'a u32 { &self.count }
+                fn peek(&self, _other: Thing<'_>) -> &u32 { &self.count }
+                #[require_lifetimes(skip)]
+                fn skipped(&self) -> &u32 { &self.count }
+            }
+        },
+    )));
+}
+
+#[test]
+fn json_diagnostics() {
+    insta::assert_snapshot!(pretty(expand::require_lifetimes(
+        quote!(json, no_notes),
+        quote! {
+            fn first(words: &[String]) -> &str { &words[0] }
+        },
+    )));
+}
+
+#[test]
+fn needless_lifetimes() {
+    insta::assert_snapshot!(pretty(expand::elide_lifetimes(
+        quote!(),
+        quote! {
+            fn first<'a, 'b>(words: &'a [String], _count: &'b usize) -> &'a str { &words[0] }
+        },
+    )));
+}
+
+#[test]
+fn elided_form() {
+    insta::assert_snapshot!(pretty(expand::show_elided(
+        quote!(),
+        quote! {
+            fn first<'a, 'b>(words: &'a [String], _count: &'b usize) -> &'a str { &words[0] }
+        },
+    )));
+}
+
+#[test]
+fn field_lifetimes() {
+    insta::assert_snapshot!(pretty(expand::require_field_lifetimes(quote! {
+        #[field_lifetimes(separate)]
+        struct Split<'a> { first: &'a str, rest: &'a str }
+    })));
+}
+
+#[test]
+fn closure() {
+    let expansion = expand::require_closure_lifetimes(quote!(|x: &str| -> &'a str { x }));
+    insta::assert_snapshot!(pretty(quote!(
+        fn main() {
+            let _ = #expansion;
+        }
+    )));
+}
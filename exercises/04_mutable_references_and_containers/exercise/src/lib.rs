@@ -5,7 +5,7 @@ use require_lifetimes::require_lifetimes;
 /// location (i.e., array index) "loc" with the "new" one.  Don't do
 /// anything if "loc" is beyond the end of "vector".
 ///
-/// Make sure it passes this test:
+/// Make sure it passes these tests:
 ///
 /// ```rust
 /// use ex04::vector_set;
@@ -25,11 +25,21 @@ use require_lifetimes::require_lifetimes;
 /// // Set some references
 /// vector_set(&mut message, 1, &your);
 /// vector_set(&mut message, 4, &unknown);
-/// vector_set(&mut message, 10, &unknown);
 ///
 /// // Hopefully, they're now equal
 /// assert_eq!(message , vec!["Hello", "Your", "Name", "Is", "Unknown"]);
-/// ````
+/// ```
+///
+/// ```rust
+/// use ex04::vector_set;
+///
+/// let mut message = vec!["Hello", "My", "Name"];
+/// let unknown = "Unknown".to_string();
+///
+/// // Setting past the end does nothing.
+/// vector_set(&mut message, 10, &unknown);
+/// assert_eq!(message, vec!["Hello", "My", "Name"]);
+/// ```
 #[require_lifetimes(!)]
 pub fn vector_set(vector: &mut Vec<&str>, loc: usize, new: &str) {
     // TODO: You will need to write this code yourself.
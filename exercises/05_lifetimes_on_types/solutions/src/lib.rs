@@ -36,25 +36,33 @@ pub fn find_difference<'fst, 'snd>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use lifetimekata_common::assert_borrows_from;
+    use lifetimekata_common::texts::{SNOW, SURF_HATED, SURF_LOVED};
     #[test]
-    fn main() {
-        let first_sentence = String::from("I hate the surf and the sand.");
-        let second_sentence = String::from("I love the surf and the sand.");
+    fn first_only_borrows_from_the_first_sentence() {
+        let first_sentence = String::from(SURF_HATED);
 
         let first_only = {
-            let third_sentence = String::from("I love the snow and the sand.");
+            let third_sentence = String::from(SNOW);
             let diff = find_difference(&first_sentence, &third_sentence);
             diff.first_only
         };
 
         assert_eq!(first_only, vec!["hate", "surf"]);
+        assert_borrows_from!(first_only, first_sentence);
+    }
+
+    #[test]
+    fn second_only_borrows_from_the_second_sentence() {
+        let second_sentence = String::from(SURF_LOVED);
 
         let second_only = {
-            let third_sentence = String::from("I love the snow and the sand.");
+            let third_sentence = String::from(SNOW);
             let diff = find_difference(&third_sentence, &second_sentence);
             diff.second_only
         };
 
         assert_eq!(second_only, vec!["surf"]);
+        assert_borrows_from!(second_only, second_sentence);
     }
 }
@@ -1,3 +1,4 @@
+use require_lifetimes::elide_lifetimes;
 use std::collections::HashSet;
 
 struct UniqueWords<'a> {
@@ -5,6 +6,7 @@ struct UniqueWords<'a> {
     unique_words: Vec<&'a str>,
 }
 
+#[elide_lifetimes]
 impl<'a> UniqueWords<'a> {
     fn new(sentence: &'a str) -> UniqueWords<'a> {
         let unique_words = sentence
@@ -1,3 +1,4 @@
+use require_lifetimes::elide_lifetimes;
 use std::collections::HashSet;
 
 struct UniqueWords {
@@ -5,6 +6,7 @@ struct UniqueWords {
     unique_words: Vec<&'static str>,
 }
 
+#[elide_lifetimes]
 impl UniqueWords {
     fn new(sentence: &'static str) -> UniqueWords {
         let unique_words = sentence
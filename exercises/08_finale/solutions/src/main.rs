@@ -112,40 +112,66 @@ impl<'a> Matcher<'a> {
 }
 
 fn main() {
-    unimplemented!()
+    let match_string = "abc(d|e|f).".to_string();
+    let mut matcher = Matcher::new(&match_string).unwrap();
+    for candidate in ["abcge", "abcde"] {
+        println!("{candidate}: {:?}", matcher.match_string(candidate));
+    }
+    println!(
+        "{} matched at most {} tokens",
+        matcher.text, matcher.most_tokens_matched
+    );
 }
 
 #[cfg(test)]
 mod test {
     use super::{Matcher, MatcherToken};
     #[test]
-    fn simple_test() {
+    fn nothing_is_matched_at_first() {
         let match_string = "abc(d|e|f).".to_string();
-        let mut matcher = Matcher::new(&match_string).unwrap();
+        let matcher = Matcher::new(&match_string).unwrap();
 
         assert_eq!(matcher.most_tokens_matched, 0);
+    }
 
-        {
-            let candidate1 = "abcge".to_string();
-            let result = matcher.match_string(&candidate1);
-            assert_eq!(result, vec![(&MatcherToken::RawText("abc"), "abc"),]);
-            assert_eq!(matcher.most_tokens_matched, 1);
-        }
+    #[test]
+    fn matching_stops_at_the_first_token_that_fails() {
+        let match_string = "abc(d|e|f).".to_string();
+        let mut matcher = Matcher::new(&match_string).unwrap();
 
-        {
-            // Change 'e' to '💪' if you want to test unicode.
-            let candidate1 = "abcde".to_string();
-            let result = matcher.match_string(&candidate1);
-            assert_eq!(
-                result,
-                vec![
-                    (&MatcherToken::RawText("abc"), "abc"),
-                    (&MatcherToken::OneOfText(vec!["d", "e", "f"]), "d"),
-                    (&MatcherToken::WildCard, "e") // or '💪'
-                ]
-            );
-            assert_eq!(matcher.most_tokens_matched, 3);
-        }
+        let candidate1 = "abcge".to_string();
+        let result = matcher.match_string(&candidate1);
+        assert_eq!(result, vec![(&MatcherToken::RawText("abc"), "abc"),]);
+        assert_eq!(matcher.most_tokens_matched, 1);
+    }
+
+    #[test]
+    fn every_kind_of_token_matches() {
+        let match_string = "abc(d|e|f).".to_string();
+        let mut matcher = Matcher::new(&match_string).unwrap();
+
+        // Change 'e' to '💪' if you want to test unicode.
+        let candidate1 = "abcde".to_string();
+        let result = matcher.match_string(&candidate1);
+        assert_eq!(
+            result,
+            vec![
+                (&MatcherToken::RawText("abc"), "abc"),
+                (&MatcherToken::OneOfText(vec!["d", "e", "f"]), "d"),
+                (&MatcherToken::WildCard, "e") // or '💪'
+            ]
+        );
+        assert_eq!(matcher.most_tokens_matched, 3);
+    }
+
+    #[test]
+    fn most_tokens_matched_is_the_best_so_far() {
+        let match_string = "abc(d|e|f).".to_string();
+        let mut matcher = Matcher::new(&match_string).unwrap();
+
+        matcher.match_string("abcde");
+        matcher.match_string("abcge");
+        assert_eq!(matcher.most_tokens_matched, 3);
     }
 
     #[test]
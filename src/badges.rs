@@ -0,0 +1,205 @@
+//! Badges for milestones in the kata, like finishing an exercise without
+//! a hint. They're earned from what's in the progress store, and kept
+//! there, so that `lifetimekata badges` can show them; nothing is sent
+//! anywhere.
+
+use std::io;
+use std::path::Path;
+
+use crate::exercise::Mode;
+use crate::i18n::{self, t};
+use crate::info::Info;
+use crate::progress::{Progress, Record};
+
+/// A milestone. Its name and description are in `locales/`, under
+/// `[badges.<id>]`.
+pub struct Badge {
+    pub id: &'static str,
+    /// The kind of exercise it needs, if there has to be one in `info.toml`
+    /// for it to be earned at all.
+    pub needs: Option<Mode>,
+    earned: fn(&Info, &Progress) -> bool,
+}
+
+/// The exercises' records, leaving out those never checked.
+fn records<'a>(info: &'a Info, progress: &'a Progress) -> impl Iterator<Item = &'a Record> {
+    info.exercises
+        .iter()
+        .filter_map(|exercise| progress.get(exercise))
+}
+
+pub const BADGES: [Badge; 8] = [
+    Badge {
+        id: "first_steps",
+        needs: None,
+        earned: |info, progress| records(info, progress).any(|r| r.completed.is_some()),
+    },
+    Badge {
+        id: "first_time",
+        needs: None,
+        earned: |info, progress| {
+            records(info, progress).any(|r| r.completed.is_some() && r.failed_attempts == 0)
+        },
+    },
+    Badge {
+        id: "persistence",
+        needs: None,
+        earned: |info, progress| {
+            records(info, progress).any(|r| r.completed.is_some() && r.failed_attempts >= 5)
+        },
+    },
+    Badge {
+        id: "compile_fail",
+        needs: Some(Mode::CompileFail),
+        earned: |info, progress| {
+            info.exercises.iter().any(|exercise| {
+                exercise.mode == Mode::CompileFail
+                    && progress
+                        .get(exercise)
+                        .is_some_and(|r| r.completed.is_some())
+            })
+        },
+    },
+    Badge {
+        id: "scholar",
+        needs: None,
+        earned: |_, progress| progress.correct_answers() >= 10,
+    },
+    Badge {
+        id: "finale",
+        needs: None,
+        earned: |info, progress| {
+            info.exercises
+                .last()
+                .and_then(|exercise| progress.get(exercise))
+                .is_some_and(|r| r.completed.is_some())
+        },
+    },
+    Badge {
+        id: "complete",
+        needs: None,
+        earned: |info, progress| {
+            info.exercises.iter().all(|exercise| {
+                progress
+                    .get(exercise)
+                    .is_some_and(|r| r.completed.is_some())
+            })
+        },
+    },
+    Badge {
+        id: "no_hints",
+        needs: None,
+        earned: |info, progress| {
+            info.exercises.iter().all(|exercise| {
+                progress
+                    .get(exercise)
+                    .is_some_and(|r| r.completed.is_some() && r.hints_shown == 0)
+            })
+        },
+    },
+];
+
+impl Badge {
+    /// Whether it can be earned with these exercises.
+    fn offered(&self, info: &Info) -> bool {
+        self.needs
+            .is_none_or(|mode| info.exercises.iter().any(|exercise| exercise.mode == mode))
+    }
+
+    fn name(&self) -> &'static str {
+        &i18n::badge(self.id)
+            .expect("every badge is in locales/en.toml")
+            .name
+    }
+
+    fn description(&self) -> &'static str {
+        &i18n::badge(self.id)
+            .expect("every badge is in locales/en.toml")
+            .description
+    }
+}
+
+/// Records the badges which `progress` has earned since they were last
+/// looked for, returning them.
+pub fn award(info: &Info, progress: &mut Progress) -> Vec<&'static Badge> {
+    let new: Vec<_> = BADGES
+        .iter()
+        .filter(|badge| progress.earned(badge.id).is_none() && (badge.earned)(info, progress))
+        .collect();
+    for badge in &new {
+        progress.earn(badge.id);
+    }
+    new
+}
+
+/// Awards any badges earned by the progress saved under `root`, saying
+/// which.
+pub fn announce(root: &Path, info: &Info) -> io::Result<()> {
+    let mut progress = Progress::load(root)?;
+    let new = award(info, &mut progress);
+    if new.is_empty() {
+        return Ok(());
+    }
+    progress.save(root)?;
+    for badge in new {
+        let (name, description) = (badge.name(), badge.description());
+        println!(
+            "🏅 {}",
+            t!("badges.new", name = name, description = description)
+        );
+    }
+    Ok(())
+}
+
+pub fn badges(info: &Info, progress: &Progress) {
+    let offered: Vec<_> = BADGES.iter().filter(|badge| badge.offered(info)).collect();
+    for badge in &offered {
+        let icon = if progress.earned(badge.id).is_some() {
+            "🏅"
+        } else {
+            "  "
+        };
+        println!("{icon} {:<24} {}", badge.name(), badge.description());
+    }
+    let earned = offered
+        .iter()
+        .filter(|badge| progress.earned(badge.id).is_some())
+        .count();
+    let total = offered.len();
+    println!("\n{}", t!("badges.count", earned = earned, total = total));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ids(badges: Vec<&Badge>) -> Vec<&str> {
+        badges.into_iter().map(|badge| badge.id).collect()
+    }
+
+    #[test]
+    fn badges_are_awarded_once() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let mut progress = Progress::default();
+        assert!(award(&info, &mut progress).is_empty());
+
+        progress.record(&info.exercises[0], true);
+        assert_eq!(
+            ids(award(&info, &mut progress)),
+            ["first_steps", "first_time"]
+        );
+        assert!(award(&info, &mut progress).is_empty());
+
+        for exercise in &info.exercises {
+            progress.record(exercise, true);
+        }
+        assert_eq!(
+            ids(award(&info, &mut progress)),
+            ["finale", "complete", "no_hints"]
+        );
+
+        // Starting over doesn't take them away.
+        progress.forget(&info.exercises[0]);
+        assert!(progress.earned("first_steps").is_some());
+    }
+}
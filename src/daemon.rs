@@ -0,0 +1,371 @@
+//! `lifetimekata daemon`: checks every exercise whenever one of its files
+//! changes, and answers questions about how they went over a socket, so that
+//! an editor can show the kata's diagnostics and explanations inline.
+//!
+//! Cargo's incremental builds do the real work of keeping things warm: each
+//! exercise is built once when the daemon starts, and after that only what
+//! changed is rebuilt. Checks here don't count towards progress; `verify`
+//! and `watch` still do that.
+//!
+//! The protocol is JSON-RPC 2.0 over TCP on localhost, one message to a
+//! line. The methods are:
+//!
+//! - `status`: every exercise, whether it passed, and a `version` which goes
+//!   up each time it's checked, so an editor can poll cheaply;
+//! - `diagnostics`, given an `exercise` or a `file`: what rustc said about
+//!   the exercise, with an explanation of each lifetime error;
+//! - `hints`, given an `exercise`: the hints which have been unlocked;
+//! - `check`, given an `exercise`: checks it again, without waiting for a
+//!   file to change.
+
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+use notify::{RecursiveMode, Watcher};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::explain;
+use crate::i18n::{self, t};
+use crate::info::Info;
+use crate::progress::Progress;
+use crate::watch::is_edit;
+
+/// Something rustc said, where an editor can put it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    /// Where it starts and ends, counting lines and columns from 1.
+    pub line: u64,
+    pub column: u64,
+    pub end_line: u64,
+    pub end_column: u64,
+    /// `error`, `warning` or `note`.
+    pub severity: String,
+    /// Like `E0106`, or `LK0001` for `#[require_lifetimes]`, which also
+    /// leaves its code at the start of the message.
+    pub code: Option<String>,
+    pub message: String,
+    /// What the error means, in the kata's words, and where to read up.
+    pub explanation: Option<String>,
+}
+
+/// How an exercise went the last time it was checked.
+#[derive(Debug, Default)]
+struct Checked {
+    passed: bool,
+    diagnostics: Vec<Diagnostic>,
+    /// How many times it has been checked.
+    version: u64,
+}
+
+pub fn daemon(root: &Path, info: &Info, port: u16) -> io::Result<bool> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let state: Mutex<Vec<Checked>> =
+        Mutex::new(info.exercises.iter().map(|_| Checked::default()).collect());
+    let (recheck, wanted) = mpsc::channel();
+
+    let (sender, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(io::Error::other)?;
+    watcher
+        .watch(&root.join("exercises"), RecursiveMode::Recursive)
+        .map_err(io::Error::other)?;
+    for index in 0..info.exercises.len() {
+        let _ = recheck.send(index);
+    }
+    let port = listener.local_addr()?.port();
+    println!("{}", t!("daemon.listening", port = port));
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| check_wanted(root, info, &state, wanted));
+        let recheck_changed = recheck.clone();
+        scope.spawn(move || {
+            for event in changes.into_iter().flatten() {
+                if !is_edit(&event) {
+                    continue;
+                }
+                for path in &event.paths {
+                    if let Some(index) = exercise_of(root, info, path) {
+                        let _ = recheck_changed.send(index);
+                    }
+                }
+            }
+        });
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let recheck = recheck.clone();
+            let state = &state;
+            scope.spawn(move || serve(root, info, state, &recheck, stream));
+        }
+        Ok(true)
+    })
+}
+
+/// The index of the exercise whose crate `path` is in.
+pub fn exercise_of(root: &Path, info: &Info, path: &Path) -> Option<usize> {
+    info.exercises
+        .iter()
+        .position(|exercise| path.starts_with(root.join(&exercise.path)))
+}
+
+/// Checks each exercise which is asked for, once for however many times it
+/// was asked for while the last check ran.
+fn check_wanted(root: &Path, info: &Info, state: &Mutex<Vec<Checked>>, wanted: Receiver<usize>) {
+    while let Ok(first) = wanted.recv() {
+        let mut indices = BTreeSet::from([first]);
+        indices.extend(wanted.try_iter());
+        for index in indices {
+            let exercise = &info.exercises[index];
+            let checked = exercise.check(root).and_then(|(passed, _)| {
+                let messages = exercise.messages(root)?;
+                Ok((passed, diagnostics(root, info, &messages)))
+            });
+            match checked {
+                Ok((passed, diagnostics)) => {
+                    let mut state = state.lock().expect("nothing panics with it locked");
+                    let checked = &mut state[index];
+                    checked.passed = passed;
+                    checked.diagnostics = diagnostics;
+                    checked.version += 1;
+                }
+                Err(error) => eprintln!("couldn't check {exercise}: {error}"),
+            }
+        }
+    }
+}
+
+/// The diagnostics in cargo's JSON `messages`, with paths made absolute.
+fn diagnostics(root: &Path, info: &Info, messages: &str) -> Vec<Diagnostic> {
+    let mut found = Vec::new();
+    for line in messages.lines() {
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if message["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &message["message"];
+        let Some(span) = message["spans"]
+            .as_array()
+            .and_then(|spans| spans.iter().find(|span| span["is_primary"] == true))
+        else {
+            continue;
+        };
+        let number = |key: &str| span[key].as_u64().unwrap_or(1);
+        let text = message["message"].as_str().unwrap_or_default();
+        let code = match message["code"]["code"].as_str() {
+            Some(code) => Some(code.to_string()),
+            None => text
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once(']'))
+                .map(|(code, _)| code.to_string())
+                .filter(|code| code.starts_with("LK")),
+        };
+        let explanation = code
+            .as_deref()
+            .and_then(explain::lookup)
+            .map(|explanation| {
+                let exercise = info
+                    .exercises
+                    .iter()
+                    .find(|exercise| exercise.dir() == explanation.chapter);
+                let text =
+                    i18n::explanation(explanation.code).expect("every code is in locales/en.toml");
+                let readme = format!("exercises/{}/README.md", explanation.chapter);
+                let mut text = format!(
+                    "{} {}",
+                    text.text.split_whitespace().collect::<Vec<_>>().join(" "),
+                    t!("daemon.more", readme = readme)
+                );
+                if let Some(exercise) = exercise {
+                    text += " ";
+                    text += &t!("daemon.practised", exercise = exercise.name);
+                }
+                text
+            });
+        found.push(Diagnostic {
+            file: root.join(span["file_name"].as_str().unwrap_or_default()),
+            line: number("line_start"),
+            column: number("column_start"),
+            end_line: number("line_end"),
+            end_column: number("column_end"),
+            severity: message["level"].as_str().unwrap_or("error").to_string(),
+            code,
+            message: text.to_string(),
+            explanation,
+        });
+    }
+    found
+}
+
+/// Answers each request on `stream` until it's closed.
+fn serve(
+    root: &Path,
+    info: &Info,
+    state: &Mutex<Vec<Checked>>,
+    recheck: &Sender<usize>,
+    stream: TcpStream,
+) {
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = respond(root, info, state, recheck, &line);
+        if writeln!(writer, "{response}").is_err() {
+            return;
+        }
+    }
+}
+
+/// The JSON-RPC response to `request`.
+fn respond(
+    root: &Path,
+    info: &Info,
+    state: &Mutex<Vec<Checked>>,
+    recheck: &Sender<usize>,
+    request: &str,
+) -> Value {
+    let request: Value = match serde_json::from_str(request) {
+        Ok(request) => request,
+        Err(error) => return failure(Value::Null, -32700, &error.to_string()),
+    };
+    let id = request["id"].clone();
+    let params = &request["params"];
+    let state = state.lock().expect("nothing panics with it locked");
+
+    // The exercise named in `params`, or the one `params.file` is in.
+    let exercise = || -> Option<usize> {
+        if let Some(name) = params["exercise"].as_str() {
+            let found = info.find(name)?;
+            info.exercises
+                .iter()
+                .position(|other| other.name == found.name)
+        } else {
+            exercise_of(root, info, Path::new(params["file"].as_str()?))
+        }
+    };
+    let result = match request["method"].as_str().unwrap_or_default() {
+        "status" => json!(info
+            .exercises
+            .iter()
+            .zip(state.iter())
+            .map(|(exercise, checked)| json!({
+                "exercise": exercise.name,
+                "path": root.join(&exercise.path),
+                "passed": checked.passed,
+                "version": checked.version,
+            }))
+            .collect::<Vec<_>>()),
+        "diagnostics" => match exercise() {
+            Some(index) => json!({
+                "exercise": info.exercises[index].name,
+                "version": state[index].version,
+                "diagnostics": state[index].diagnostics,
+            }),
+            None => return failure(id, -32602, "no such exercise"),
+        },
+        "hints" => match exercise() {
+            Some(index) => {
+                let exercise = &info.exercises[index];
+                let shown = Progress::load(root)
+                    .ok()
+                    .and_then(|progress| progress.get(exercise).map(|r| r.hints_shown))
+                    .unwrap_or(0);
+                let hints = i18n::hints(exercise);
+                json!(hints[..shown.min(hints.len())])
+            }
+            None => return failure(id, -32602, "no such exercise"),
+        },
+        "check" => match exercise() {
+            Some(index) => {
+                let _ = recheck.send(index);
+                Value::Null
+            }
+            None => return failure(id, -32602, "no such exercise"),
+        },
+        method => return failure(id, -32601, &format!("there's no method `{method}`")),
+    };
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn failure(id: Value, code: i32, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn info() -> (PathBuf, Info) {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let info = Info::load(&root).unwrap();
+        (root, info)
+    }
+
+    #[test]
+    fn diagnostics_come_from_compiler_messages() {
+        let (root, info) = info();
+        let messages = r#"{"reason":"compiler-artifact"}
+{"reason":"compiler-message","message":{"message":"`x` does not live long enough","code":{"code":"E0597"},"level":"error","spans":[{"file_name":"exercises/02_lifetimes_explained/exercise/src/lib.rs","line_start":3,"line_end":3,"column_start":9,"column_end":11,"is_primary":true}]}}
+{"reason":"compiler-message","message":{"message":"1 error","code":null,"level":"error","spans":[]}}
+{"reason":"compiler-message","message":{"message":"[LK0001] `&i32` here needs a name","code":null,"level":"error","spans":[{"file_name":"src/lib.rs","line_start":1,"line_end":1,"column_start":1,"column_end":2,"is_primary":true}]}}
+"#;
+        let found = diagnostics(&root, &info, messages);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].code.as_deref(), Some("LK0001"));
+        let diagnostic = &found[0];
+        assert_eq!(
+            diagnostic.file,
+            root.join("exercises/02_lifetimes_explained/exercise/src/lib.rs")
+        );
+        assert_eq!(
+            (diagnostic.line, diagnostic.column, diagnostic.end_column),
+            (3, 9, 11)
+        );
+        assert_eq!(diagnostic.code.as_deref(), Some("E0597"));
+        assert!(diagnostic
+            .explanation
+            .as_ref()
+            .unwrap()
+            .contains("exercises/00_welcome/README.md"));
+    }
+
+    #[test]
+    fn requests_are_answered() {
+        let (root, info) = info();
+        let state = Mutex::new(info.exercises.iter().map(|_| Checked::default()).collect());
+        let (recheck, wanted) = mpsc::channel();
+        let ask = |request: &str| respond(&root, &info, &state, &recheck, request);
+
+        let status = ask(r#"{"jsonrpc":"2.0","id":1,"method":"status"}"#);
+        assert_eq!(status["id"], 1);
+        assert_eq!(
+            status["result"].as_array().unwrap().len(),
+            info.exercises.len()
+        );
+
+        let file = root.join("exercises/04_mutable_references_and_containers/exercise/src/lib.rs");
+        let request =
+            json!({"jsonrpc": "2.0", "id": 2, "method": "check", "params": {"file": file}});
+        assert_eq!(ask(&request.to_string())["result"], Value::Null);
+        let wanted = &info.exercises[wanted.try_recv().unwrap()];
+        assert_eq!(wanted.name, "mutable_references_and_containers");
+
+        let missing =
+            ask(r#"{"jsonrpc":"2.0","id":3,"method":"hints","params":{"exercise":"99"}}"#);
+        assert_eq!(missing["error"]["code"], -32602);
+        let unknown = ask(r#"{"jsonrpc":"2.0","id":4,"method":"explode"}"#);
+        assert_eq!(unknown["error"]["code"], -32601);
+        assert_eq!(ask("not json")["error"]["code"], -32700);
+    }
+}
@@ -0,0 +1,149 @@
+//! `lifetimekata diff`: the learner's code beside the reference solution.
+
+use std::io;
+use std::path::Path;
+
+use similar::{DiffOp, TextDiff};
+
+use crate::exercise::Exercise;
+use crate::i18n::t;
+use crate::style::{paint, GREEN, RED};
+
+/// Prints `exercise`'s source beside its solution's, showing only function
+/// signatures if `signatures` is set.
+pub fn diff(root: &Path, exercise: &Exercise, signatures: bool) -> io::Result<()> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(root.join(path))
+            .map_err(|error| io::Error::new(error.kind(), format!("{}: {error}", path.display())))
+    };
+    let mut ours = read(&exercise.source(root))?;
+    let mut theirs = read(&exercise.solution_source(root))?;
+    if signatures {
+        ours = function_signatures(&ours);
+        theirs = function_signatures(&theirs);
+    }
+    let width = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse::<usize>().ok())
+        .unwrap_or(160);
+    let column = width.saturating_sub(3) / 2;
+    let (yours, solution) = (t!("diff.yours"), t!("diff.solution"));
+    println!("{yours:<column$} │ {solution}");
+    for line in side_by_side(&ours, &theirs, column) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// Every function signature in `source`, one after another; a signature
+/// runs from its `fn` to the `{` or `;` that ends it.
+fn function_signatures(source: &str) -> String {
+    let mut signatures = String::new();
+    let mut inside = false;
+    for line in source.lines() {
+        let code = line.trim_start();
+        if !inside {
+            let rest = code
+                .strip_prefix("pub ")
+                .or_else(|| code.strip_prefix("pub(crate) "))
+                .unwrap_or(code);
+            inside = rest.starts_with("fn ")
+                || ["const fn ", "async fn ", "unsafe fn "]
+                    .iter()
+                    .any(|start| rest.starts_with(start));
+        }
+        if inside {
+            let end = line.find(['{', ';']);
+            signatures.push_str(line[..end.unwrap_or(line.len())].trim_end());
+            signatures.push('\n');
+            inside = end.is_none();
+        }
+    }
+    signatures
+}
+
+/// `ours` and `theirs` as lines of two columns, each `column` characters
+/// wide, with the lines only one side has coloured.
+fn side_by_side(ours: &str, theirs: &str, column: usize) -> Vec<String> {
+    let diff = TextDiff::from_lines(ours, theirs);
+    let (old, new) = (diff.old_slices(), diff.new_slices());
+    let cell =
+        |line: Option<&&str>| fit(line.map_or("", |line| line.trim_end_matches('\n')), column);
+    let mut lines = Vec::new();
+    for op in diff.ops() {
+        let (old_range, new_range) = (op.old_range(), op.new_range());
+        let changed = !matches!(op, DiffOp::Equal { .. });
+        for row in 0..old_range.len().max(new_range.len()) {
+            let left = old
+                .get(old_range.start + row)
+                .filter(|_| row < old_range.len());
+            let right = new
+                .get(new_range.start + row)
+                .filter(|_| row < new_range.len());
+            let (mut left_cell, mut right_cell) = (cell(left), cell(right));
+            if changed {
+                left_cell = paint(RED, &left_cell);
+                right_cell = paint(GREEN, &right_cell);
+            }
+            let marker = if changed { '┃' } else { '│' };
+            lines.push(
+                format!("{left_cell} {marker} {right_cell}")
+                    .trim_end()
+                    .to_string(),
+            );
+        }
+    }
+    lines
+}
+
+/// `text` cut or padded to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let text = text.replace('\t', "    ");
+    if text.chars().count() > width {
+        let cut: String = text.chars().take(width.saturating_sub(1)).collect();
+        format!("{cut}…")
+    } else {
+        format!("{text:<width$}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn signatures_are_kept_without_bodies() {
+        let source = "\
+/// fn in a comment
+pub fn identity(number: &i32) -> &i32 {
+    number
+}
+
+impl Matcher {
+    fn new(
+        text: &str,
+    ) -> Option<Matcher> {
+        None
+    }
+}
+";
+        assert_eq!(
+            function_signatures(source),
+            "pub fn identity(number: &i32) -> &i32\n    fn new(\n        text: &str,\n    ) -> Option<Matcher>\n"
+        );
+    }
+
+    #[test]
+    fn changed_lines_are_set_side_by_side() {
+        let ours = "fn f(x: &str) -> &str\nfn g()\n";
+        let theirs = "fn f<'a>(x: &'a str) -> &'a str\nfn g()\nfn h()\n";
+        assert_eq!(
+            side_by_side(ours, theirs, 16),
+            [
+                "fn f(x: &str) -… ┃ fn f<'a>(x: &'a…",
+                "fn g()           │ fn g()",
+                "                 ┃ fn h()",
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! An exercise, and how to check it with cargo.
+
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::variant::Variable;
+
+/// How an exercise is checked once the learner has finished it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    /// It must build.
+    Compile,
+    /// It must build and run to completion.
+    Run,
+    /// Its tests, doctests included, must pass.
+    Test,
+    /// It must fail to build; the exercise is to write the error.
+    CompileFail,
+}
+
+/// One exercise, as `info.toml` describes it.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Exercise {
+    pub name: String,
+    /// What the learner has to do, in a sentence.
+    #[serde(default)]
+    pub description: String,
+    /// The exercise's crate, relative to the repository.
+    pub path: PathBuf,
+    pub mode: Mode,
+    #[serde(default)]
+    pub hints: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The names of the exercises to do first; the previous exercise, if
+    /// `info.toml` doesn't say.
+    pub prerequisites: Option<Vec<String>>,
+    /// Words which `lifetimekata variant` may swap for others.
+    #[serde(default)]
+    pub variants: Vec<Variable>,
+    /// The reference solution's crate; the `solutions` directory next to
+    /// the exercise's, if `info.toml` doesn't say.
+    solution: Option<PathBuf>,
+    /// The crate's package name, like `ex02`, read from its manifest.
+    #[serde(skip)]
+    pub package: String,
+}
+
+impl Exercise {
+    /// The directory under `exercises/` which holds the exercise and its
+    /// chapter, like `02_lifetimes_explained`.
+    pub fn dir(&self) -> &str {
+        self.path
+            .parent()
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.name)
+    }
+
+    /// The number at the start of the exercise's directory.
+    pub fn number(&self) -> Option<u32> {
+        let dir = self.dir();
+        let digits = dir.find(|c: char| !c.is_ascii_digit()).unwrap_or(dir.len());
+        dir[..digits].parse().ok()
+    }
+
+    /// Whether `name` refers to this exercise: its name, number (`2` or
+    /// `02`), package (`ex02`) or directory (`02_lifetimes_explained`).
+    pub fn is_called(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('/');
+        self.name == name
+            || self.package == name
+            || self.dir() == name
+            || name
+                .parse::<u32>()
+                .ok()
+                .is_some_and(|n| self.number() == Some(n))
+    }
+
+    /// The file the learner edits: the crate's `lib.rs`, or its `main.rs`
+    /// if it has no library. Like `path`, it's relative to `root`.
+    pub fn source(&self, root: &Path) -> PathBuf {
+        main_source(root, &self.path)
+    }
+
+    /// The reference solution's counterpart to [`Exercise::source`].
+    pub fn solution_source(&self, root: &Path) -> PathBuf {
+        let solution = match &self.solution {
+            Some(solution) => solution.clone(),
+            None => self.path.with_file_name("solutions"),
+        };
+        main_source(root, &solution)
+    }
+
+    fn command(&self, root: &Path) -> Command {
+        let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let mut command = Command::new(cargo);
+        command
+            .current_dir(root)
+            .arg(match self.mode {
+                Mode::Compile | Mode::CompileFail => "build",
+                Mode::Run => "run",
+                Mode::Test => "test",
+            })
+            .args(["--quiet", "--package", &self.package]);
+        command
+    }
+
+    /// Whether an exercise which exited with `success` has been done.
+    fn passed(&self, success: bool) -> bool {
+        success != (self.mode == Mode::CompileFail)
+    }
+
+    /// Runs the exercise quietly, returning whether it passed and its
+    /// output.
+    pub fn check(&self, root: &Path) -> std::io::Result<(bool, Output)> {
+        let output = self.command(root).stdin(Stdio::null()).output()?;
+        Ok((self.passed(output.status.success()), output))
+    }
+
+    /// Builds the exercise, tests and all, returning cargo's JSON messages
+    /// about it, as `--message-format=json` writes them.
+    pub fn messages(&self, root: &Path) -> std::io::Result<String> {
+        let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let mut command = Command::new(cargo);
+        command.current_dir(root);
+        if self.mode == Mode::Test {
+            command.args(["test", "--no-run"]);
+        } else {
+            command.arg("build");
+        }
+        let output = command
+            .args([
+                "--quiet",
+                "--message-format=json",
+                "--package",
+                &self.package,
+            ])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// The `lib.rs` of the crate at `path`, or its `main.rs` if it has no
+/// library.
+fn main_source(root: &Path, path: &Path) -> PathBuf {
+    let lib = path.join("src").join("lib.rs");
+    let main = path.join("src").join("main.rs");
+    if root.join(&main).exists() && !root.join(&lib).exists() {
+        main
+    } else {
+        lib
+    }
+}
+
+impl Display for Exercise {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.dir())
+    }
+}
@@ -0,0 +1,244 @@
+//! Plain-language explanations of the borrow checker's errors, pointing at
+//! the chapter which covers each of them.
+//!
+//! rustc's own explanations (`rustc --explain E0597`) are thorough, but
+//! they're written for someone who already knows what a lifetime is. These
+//! are written for someone partway through the kata.
+//!
+//! Given a signature instead, it goes through what each of the elision
+//! rules makes of it, using the same analysis as `#[require_lifetimes]`.
+
+use std::path::Path;
+
+use require_lifetimes_core::{elaborate, Elaboration};
+use serde_json::Value;
+use syn::Signature;
+
+use crate::i18n::{self, t};
+use crate::info::Info;
+
+/// One of rustc's error codes which there's something to say about. What's
+/// said is in `locales/`, under `[explanations.<code>]`.
+pub struct Explanation {
+    pub code: &'static str,
+    /// The directory under `exercises/` of the chapter which covers it.
+    pub chapter: &'static str,
+}
+
+pub const EXPLANATIONS: [Explanation; 7] = [
+    Explanation {
+        code: "E0106",
+        chapter: "03_lifetime_elision",
+    },
+    Explanation {
+        code: "E0621",
+        chapter: "02_lifetimes_explained",
+    },
+    Explanation {
+        code: "E0499",
+        chapter: "04_mutable_references_and_containers",
+    },
+    Explanation {
+        code: "E0502",
+        chapter: "04_mutable_references_and_containers",
+    },
+    Explanation {
+        code: "E0505",
+        chapter: "00_welcome",
+    },
+    Explanation {
+        code: "E0597",
+        chapter: "00_welcome",
+    },
+    Explanation {
+        code: "E0716",
+        chapter: "00_welcome",
+    },
+];
+
+/// The explanation for `code`, like `E0597` or `e0597`.
+pub fn lookup(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code.eq_ignore_ascii_case(code))
+}
+
+/// The explained error codes in the output of
+/// `cargo build --message-format=json`, each once, in the order they first
+/// appear. Lines which aren't compiler messages are skipped.
+pub fn codes(json: &str) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    for line in json.lines() {
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if message["reason"] != "compiler-message" {
+            continue;
+        }
+        let code = &message["message"]["code"]["code"];
+        if let Some(explanation) = code.as_str().and_then(lookup) {
+            if !found.contains(&explanation.code) {
+                found.push(explanation.code);
+            }
+        }
+    }
+    found
+}
+
+/// Prints `explanation`, saying which chapter to read and, if it has one,
+/// which exercise practises it.
+pub fn print(root: &Path, info: &Info, explanation: &Explanation) {
+    let text = i18n::explanation(explanation.code).expect("every code is in locales/en.toml");
+    println!("{}: {}", explanation.code, text.title);
+    println!("{}", wrap(&text.text, 76, "  "));
+    let readme = Path::new("exercises")
+        .join(explanation.chapter)
+        .join("README.md");
+    if root.join(&readme).exists() {
+        println!("  {}", t!("explain.more", readme = readme.display()));
+    }
+    if let Some(exercise) = info
+        .exercises
+        .iter()
+        .find(|exercise| exercise.dir() == explanation.chapter)
+    {
+        println!("  {}", t!("explain.practised", exercise = exercise.name));
+    }
+}
+
+/// Prints what the elision rules make of `signature`, like
+/// `fn f(x: &str) -> &str`, one rule at a time, returning whether elision
+/// works for it. When it doesn't, E0106 is explained too.
+pub fn elision(root: &Path, info: &Info, signature: &str) -> bool {
+    let parsed = match syn::parse_str::<Signature>(signature.trim().trim_end_matches(';')) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            println!("{}", t!("explain.bad_signature", error = error));
+            return false;
+        }
+    };
+    let elaboration = elaborate(&parsed);
+    for step in steps(&elaboration) {
+        println!("{step}");
+    }
+    if elaboration.elaborated.is_some() {
+        return true;
+    }
+    println!();
+    print(
+        root,
+        info,
+        lookup("E0106").expect("E0106 is one of the explanations"),
+    );
+    false
+}
+
+/// What each rule does to a signature, and what comes of it.
+fn steps(elaboration: &Elaboration) -> Vec<String> {
+    let mut steps = Vec::new();
+    if elaboration.named.is_empty() {
+        steps.push(t!("explain.rule_1_none"));
+    } else {
+        steps.push(t!("explain.rule_1"));
+        steps.push(format!("  {}", elaboration.arguments));
+    }
+    if elaboration.elided_outputs == 0 {
+        steps.push(t!("explain.no_output"));
+    } else {
+        steps.push(match (&elaboration.rule_2, elaboration.input_lifetimes) {
+            (Some(lifetime), _) => t!("explain.rule_2", lifetime = lifetime),
+            (None, 0) => t!("explain.rule_2_none"),
+            (None, count) => t!("explain.rule_2_many", count = count),
+        });
+        steps.push(match &elaboration.rule_3 {
+            Some(lifetime) => t!("explain.rule_3", lifetime = lifetime),
+            None => t!("explain.rule_3_none"),
+        });
+    }
+    steps.push(String::new());
+    match &elaboration.elaborated {
+        Some(elaborated) => {
+            steps.push(t!("explain.elaborated"));
+            steps.push(format!("  {elaborated}"));
+        }
+        None => steps.push(wrap(&t!("explain.fails"), 76, "")),
+    }
+    steps
+}
+
+/// `text` broken into lines of at most `width` characters, each starting
+/// with `indent`.
+fn wrap(text: &str, width: usize, indent: &str) -> String {
+    let mut lines = Vec::new();
+    let mut line = String::from(indent);
+    for word in text.split_whitespace() {
+        if line.len() > indent.len() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::replace(&mut line, String::from(indent)));
+        }
+        if line.len() > indent.len() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    lines.push(line);
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lifetime_errors_are_found_once_each() {
+        let output = r#"{"reason":"compiler-artifact","package_id":"common"}
+{"reason":"compiler-message","message":{"code":{"code":"E0597","explanation":""},"level":"error"}}
+{"reason":"compiler-message","message":{"code":{"code":"E0308","explanation":""},"level":"error"}}
+{"reason":"compiler-message","message":{"code":null,"level":"error"}}
+{"reason":"compiler-message","message":{"code":{"code":"E0499","explanation":""},"level":"error"}}
+{"reason":"compiler-message","message":{"code":{"code":"E0597","explanation":""},"level":"error"}}
+not json at all
+{"reason":"build-finished","success":false}
+"#;
+        assert_eq!(codes(output), ["E0597", "E0499"]);
+    }
+
+    #[test]
+    fn codes_are_looked_up_in_any_case() {
+        assert_eq!(lookup("e0716").unwrap().code, "E0716");
+        assert!(lookup("E0308").is_none());
+    }
+
+    fn steps_for(signature: &str) -> Vec<String> {
+        steps(&elaborate(&syn::parse_str(signature).unwrap()))
+    }
+
+    #[test]
+    fn each_elision_rule_is_a_step() {
+        assert_eq!(
+            steps_for("fn f(x: &str, y: u32) -> &str"),
+            [
+                "Rule 1: each elided lifetime in the arguments gets a name of its own.",
+                "  fn f<'a>(x: &'a str, y: u32) -> &str",
+                "Rule 2: the only lifetime in the arguments is 'a, so it's given to the return type.",
+                "Rule 3: `self` isn't borrowed, so it doesn't apply.",
+                "",
+                "With every lifetime written out:",
+                "  fn f<'a>(x: &'a str, y: u32) -> &'a str",
+            ]
+        );
+        let steps = steps_for("fn f(x: &str, y: &str) -> &str");
+        assert_eq!(
+            steps[2],
+            "Rule 2: there are 2 lifetimes in the arguments, not one, so it doesn't apply."
+        );
+        assert!(steps.last().unwrap().contains("E0106"));
+    }
+
+    #[test]
+    fn explanations_are_wrapped() {
+        assert_eq!(
+            wrap("one two three four", 12, "  "),
+            "  one two\n  three four"
+        );
+    }
+}
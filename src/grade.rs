@@ -0,0 +1,291 @@
+//! `lifetimekata grade`: checks every exercise and scores them, giving
+//! partial credit for tests which pass, in a report which classroom
+//! submission systems can read.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::exercise::{Exercise, Mode};
+use crate::i18n::t;
+use crate::info::Info;
+use crate::variant;
+
+/// The whole report, as it's written out as JSON.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// The sum of the exercises' scores.
+    pub score: f64,
+    /// The most `score` could be: one point for each exercise.
+    pub max_score: f64,
+    /// When the exercises were graded, in seconds since the Unix epoch.
+    pub graded_at: u64,
+    /// The seed the exercises were checked to be the variant for, if any.
+    pub seed: Option<u64>,
+    pub exercises: Vec<Grade>,
+}
+
+/// How one exercise did.
+#[derive(Debug, Serialize)]
+pub struct Grade {
+    pub name: String,
+    pub path: String,
+    pub mode: Mode,
+    pub passed: bool,
+    /// Whether it's the variant for the report's seed. If it isn't, it
+    /// scores nothing, however it does.
+    pub right_variant: bool,
+    /// How many of its tests passed and failed, for exercises checked by
+    /// testing.
+    pub tests_passed: u32,
+    pub tests_failed: u32,
+    /// Each of those tests, doctests included.
+    pub tests: Vec<Test>,
+    /// Between 0 and 1: the share of its tests which pass, or all or
+    /// nothing for exercises without tests.
+    pub score: f64,
+}
+
+/// One test, and whether it passed.
+#[derive(Debug, Serialize)]
+pub struct Test {
+    /// Its name, like `tests::first_only` or, for a doctest,
+    /// `src/lib.rs - split (line 67)`.
+    pub name: String,
+    pub passed: bool,
+}
+
+/// Checks and scores every exercise, making sure that each is the variant
+/// for `seed` if there is one.
+pub fn grade(root: &Path, info: &Info, seed: Option<u64>) -> io::Result<Report> {
+    let exercises = info
+        .exercises
+        .iter()
+        .map(|exercise| {
+            let mut grade = match exercise.mode {
+                Mode::Test => {
+                    let (built, tests) = run_tests(root, exercise)?;
+                    let passed = built && tests.iter().all(|test| test.passed);
+                    grade_one(exercise, passed, tests)
+                }
+                _ => grade_one(exercise, exercise.check(root)?.0, Vec::new()),
+            };
+            if let Some(seed) = seed {
+                if !variant::matches(root, exercise, seed)? {
+                    grade.right_variant = false;
+                    grade.score = 0.0;
+                }
+            }
+            Ok(grade)
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok(Report {
+        score: exercises.iter().map(|grade| grade.score).sum(),
+        max_score: exercises.len() as f64,
+        graded_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+        seed,
+        exercises,
+    })
+}
+
+fn grade_one(exercise: &Exercise, passed: bool, tests: Vec<Test>) -> Grade {
+    let tests_passed = tests.iter().filter(|test| test.passed).count() as u32;
+    let tests_failed = tests.len() as u32 - tests_passed;
+    let score = if passed {
+        1.0
+    } else if !tests.is_empty() {
+        f64::from(tests_passed) / tests.len() as f64
+    } else {
+        0.0
+    };
+    Grade {
+        name: exercise.name.clone(),
+        path: exercise.path.display().to_string(),
+        mode: exercise.mode,
+        passed,
+        right_variant: true,
+        tests_passed,
+        tests_failed,
+        tests,
+        score,
+    }
+}
+
+/// Builds `exercise`'s tests and runs every one of them, returning whether
+/// it built and how each test went.
+///
+/// The test binaries come from `cargo test --no-run --message-format=json`,
+/// and each test's result is read from the line libtest prints for it.
+/// Doctests can only be run by cargo, so theirs are read from what it
+/// prints.
+fn run_tests(root: &Path, exercise: &Exercise) -> io::Result<(bool, Vec<Test>)> {
+    let messages = exercise.messages(root)?;
+    let mut tests = Vec::new();
+    for binary in test_binaries(&messages) {
+        let output = Command::new(binary)
+            .current_dir(root.join(&exercise.path))
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+        tests.extend(results(&String::from_utf8_lossy(&output.stdout)));
+    }
+    let built = built(&messages);
+    if built
+        && root
+            .join(&exercise.path)
+            .join("src")
+            .join("lib.rs")
+            .exists()
+    {
+        let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let output = Command::new(cargo)
+            .current_dir(root)
+            .args([
+                "test",
+                "--doc",
+                "--no-fail-fast",
+                "--package",
+                &exercise.package,
+            ])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+        tests.extend(results(&String::from_utf8_lossy(&output.stdout)));
+    }
+    Ok((built, tests))
+}
+
+/// Whether the build which cargo's JSON `messages` are from succeeded.
+fn built(messages: &str) -> bool {
+    messages
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .any(|message| message["reason"] == "build-finished" && message["success"] == true)
+}
+
+/// The test binaries in cargo's JSON `messages`.
+fn test_binaries(messages: &str) -> Vec<PathBuf> {
+    messages
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|message| {
+            message["reason"] == "compiler-artifact" && message["profile"]["test"] == true
+        })
+        .filter_map(|message| Some(PathBuf::from(message["executable"].as_str()?)))
+        .collect()
+}
+
+/// The tests which passed or failed in libtest's output, from lines like
+/// `test tests::first_only ... ok` or, for a doctest,
+/// `test src/lib.rs - split (line 67) ... FAILED`. Ignored tests are left
+/// out.
+fn results(stdout: &str) -> Vec<Test> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (name, result) = line.strip_prefix("test ")?.rsplit_once(" ... ")?;
+            let passed = match result {
+                "ok" => true,
+                "FAILED" => false,
+                _ => return None,
+            };
+            Some(Test {
+                name: name.to_string(),
+                passed,
+            })
+        })
+        .collect()
+}
+
+impl Report {
+    pub fn print(&self) {
+        for grade in &self.exercises {
+            let tests = grade.tests_passed + grade.tests_failed;
+            let detail = if !grade.right_variant {
+                format!(" ({})", t!("grade.wrong_variant"))
+            } else if tests > 0 && !grade.passed {
+                let passed = grade.tests_passed;
+                format!(" ({})", t!("grade.tests", passed = passed, tests = tests))
+            } else {
+                String::new()
+            };
+            println!("{:>5.2}  {}{detail}", grade.score, grade.name);
+        }
+        let max_score = self.max_score;
+        println!(
+            "{:>5.2}  {}",
+            self.score,
+            t!("grade.out_of", max_score = max_score)
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(tests: &[Test]) -> Vec<(&str, bool)> {
+        tests
+            .iter()
+            .map(|test| (test.name.as_str(), test.passed))
+            .collect()
+    }
+
+    #[test]
+    fn test_binaries_are_read_from_the_json() {
+        let messages = r#"{"reason":"compiler-artifact","profile":{"test":false},"executable":null}
+{"reason":"compiler-artifact","profile":{"test":true},"executable":"/target/debug/deps/ex05-1234"}
+{"reason":"build-finished","success":true}
+"#;
+        assert_eq!(
+            test_binaries(messages),
+            [PathBuf::from("/target/debug/deps/ex05-1234")]
+        );
+        assert!(built(messages));
+        assert!(!built(r#"{"reason":"build-finished","success":false}"#));
+    }
+
+    #[test]
+    fn each_test_is_read_from_what_libtest_prints() {
+        let stdout = "\
+running 3 tests
+test tests::later ... ignored
+test tests::first_only ... ok
+test tests::second_only ... FAILED
+
+failures:
+
+---- tests::second_only stdout ----
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.00s
+";
+        assert_eq!(
+            names(&results(stdout)),
+            [("tests::first_only", true), ("tests::second_only", false)]
+        );
+    }
+
+    #[test]
+    fn doctests_are_read_from_what_cargo_prints() {
+        let stdout = "\
+running 2 tests
+test src/lib.rs - split (line 67) ... ok
+test src/lib.rs - identity (line 7) ... FAILED
+
+failures:
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+";
+        assert_eq!(
+            names(&results(stdout)),
+            [
+                ("src/lib.rs - split (line 67)", true),
+                ("src/lib.rs - identity (line 7)", false)
+            ]
+        );
+    }
+}
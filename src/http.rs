@@ -0,0 +1,190 @@
+//! Just enough HTTP/1.1 for `serve` and `server` to answer browsers and
+//! each other's CLIs, one request to a connection, and for the CLI to send
+//! progress to a workshop server.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// The most a request's body may be, so that nobody can fill the memory
+/// of an instructor's laptop.
+const MAX_BODY: usize = 1 << 20;
+
+/// The most the request line and headers together may be.
+const MAX_HEAD: u64 = 16 << 10;
+
+/// How long a client may take to send anything, before it's given up on,
+/// so that one which stalls doesn't hold a thread for ever.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Request {
+    pub method: String,
+    /// The path, without any query.
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+/// Reads one request from `reader`.
+pub fn read(reader: &mut impl BufRead) -> io::Result<Request> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut head = reader.take(MAX_HEAD);
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    let [method, target, _] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+        return Err(invalid("not an HTTP request"));
+    };
+    let (method, target) = (method.to_string(), target.to_string());
+    let mut length = 0;
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? <= 2 {
+            break;
+        }
+        if !line.ends_with('\n') {
+            return Err(invalid("too many headers"));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("a bad Content-Length"))?;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Err(invalid("too much to read"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        body,
+    })
+}
+
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+    pub location: Option<&'static str>,
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Response {
+        Response {
+            status: "200 OK",
+            content_type,
+            body: body.into(),
+            location: None,
+        }
+    }
+
+    pub fn text(status: &'static str, text: &str) -> Response {
+        Response {
+            status,
+            ..Response::ok("text/plain; charset=utf-8", text)
+        }
+    }
+
+    pub fn redirect(location: &'static str) -> Response {
+        Response {
+            location: Some(location),
+            ..Response::text("301 Moved Permanently", "")
+        }
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        write!(
+            writer,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+            self.status,
+            self.content_type,
+            self.body.len(),
+            self.location.map_or(String::new(), |location| format!(
+                "Location: {location}\r\n"
+            )),
+        )?;
+        writer.write_all(&self.body)
+    }
+}
+
+/// Reads a request from `stream` and writes back what `respond` makes of
+/// it, or an error if it wasn't a request.
+pub fn answer(stream: TcpStream, respond: impl FnOnce(Request) -> Response) {
+    if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+        return;
+    }
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    let response = match read(&mut BufReader::new(stream)) {
+        Ok(request) => respond(request),
+        Err(error) => Response::text("400 Bad Request", &error.to_string()),
+    };
+    let _ = response.write(&mut writer);
+}
+
+/// Sends `json` to `path` on the server at `url`, like
+/// `http://192.168.1.10:9000`, returning the status code it answered with.
+/// It gives up after a couple of seconds, so that a server which has gone
+/// away doesn't hold the learner up.
+pub fn post(url: &str, path: &str, json: &str) -> io::Result<u16> {
+    let host = url
+        .strip_prefix("http://")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "only http:// is supported"))?
+        .trim_end_matches('/');
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+    let timeout = Duration::from_secs(2);
+    let address = std::net::ToSocketAddrs::to_socket_addrs(&address)?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("can't find {host}")))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{json}",
+        json.len()
+    )?;
+    let mut status = String::new();
+    BufReader::new(stream).read_line(&mut status)?;
+    status
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not an HTTP response"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn requests_are_read_with_their_body() {
+        let text = "POST /sessions/abc/events?x=1 HTTP/1.1\r\nHost: here\r\n\
+                    content-length: 7\r\n\r\n{\"a\":1}trailing";
+        let request = read(&mut text.as_bytes()).unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/sessions/abc/events");
+        assert_eq!(request.body, b"{\"a\":1}");
+        assert!(read(&mut "nonsense\r\n\r\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn headers_are_only_read_so_far() {
+        let text = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-Padding: 0123456789\r\n".repeat(1000)
+        );
+        let error = read(&mut text.as_bytes()).err().unwrap();
+        assert_eq!(error.to_string(), "too many headers");
+    }
+}
@@ -0,0 +1,278 @@
+//! Everything the CLI says to the learner, looked up by key in
+//! `locales/<lang>.toml`, so that translating LifetimeKata doesn't need any
+//! changes to the code.
+//!
+//! English is built in, and fills in whatever a translation leaves out.
+//! Other languages are read from the repository when the CLI starts; which
+//! one is chosen by `--lang`, or else `LIFETIMEKATA_LANG`, or else the
+//! language in `LANG`, like `fr` in `fr_FR.UTF-8`.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+use crate::exercise::Exercise;
+
+/// The message called by a key, with named arguments, like
+/// `t!("run.passed", exercise = exercise)`.
+macro_rules! t {
+    ($key:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::message(
+            $key,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),*],
+        )
+    };
+}
+pub(crate) use t;
+
+/// The English catalog, which every key must be in.
+const ENGLISH: &str = include_str!("../locales/en.toml");
+
+/// One language's texts.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Catalog {
+    /// The CLI's messages, by command and then key, with `{name}` where an
+    /// argument goes.
+    #[serde(default)]
+    messages: BTreeMap<String, BTreeMap<String, String>>,
+    /// The exercises' descriptions and hints, by exercise name. English
+    /// has none of these, since `info.toml` is in English.
+    #[serde(default)]
+    exercises: BTreeMap<String, ExerciseText>,
+    /// What `lifetimekata explain` says about each error code.
+    #[serde(default)]
+    explanations: BTreeMap<String, Explanation>,
+    /// What each badge is called, and what it's for.
+    #[serde(default)]
+    badges: BTreeMap<String, Badge>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExerciseText {
+    description: Option<String>,
+    hints: Option<Vec<String>>,
+}
+
+/// An error code's explanation, in one language.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Explanation {
+    /// What rustc calls the error.
+    pub title: String,
+    pub text: String,
+}
+
+/// A badge's name and description, in one language.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Badge {
+    pub name: String,
+    pub description: String,
+}
+
+struct Catalogs {
+    english: Catalog,
+    chosen: Option<Catalog>,
+}
+
+static CATALOGS: OnceLock<Catalogs> = OnceLock::new();
+
+fn parse(text: &str, name: &str) -> io::Result<Catalog> {
+    toml::from_str(text).map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("in locales/{name}.toml: {error}"),
+        )
+    })
+}
+
+fn english() -> Catalog {
+    parse(ENGLISH, "en").expect("locales/en.toml is checked by the tests")
+}
+
+fn catalogs() -> &'static Catalogs {
+    CATALOGS.get_or_init(|| Catalogs {
+        english: english(),
+        chosen: None,
+    })
+}
+
+/// Chooses the language, `lang` if it's given, and loads its catalog from
+/// `root`. A language asked for by `--lang` has to be there; one which only
+/// came from the environment falls back to English quietly.
+pub fn init(root: &Path, lang: Option<&str>) -> io::Result<()> {
+    let from_environment = || {
+        let lang = std::env::var("LIFETIMEKATA_LANG")
+            .or_else(|_| std::env::var("LANG"))
+            .ok()?;
+        let lang = lang.split(['_', '.', '-']).next()?.to_string();
+        Some(lang).filter(|lang| !lang.is_empty())
+    };
+    let (lang, asked) = match lang {
+        Some(lang) => (lang.to_string(), true),
+        None => match from_environment() {
+            Some(lang) => (lang, false),
+            None => return Ok(()),
+        },
+    };
+    if matches!(lang.as_str(), "en" | "C" | "POSIX") {
+        return Ok(());
+    }
+    let path = root.join("locales").join(format!("{lang}.toml"));
+    let chosen = match std::fs::read_to_string(path) {
+        Ok(text) => parse(&text, &lang)?,
+        Err(error) if error.kind() == io::ErrorKind::NotFound && !asked => return Ok(()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(
+                error.kind(),
+                t!("i18n.missing", lang = lang),
+            ))
+        }
+        Err(error) => return Err(error),
+    };
+    let _ = CATALOGS.set(Catalogs {
+        english: english(),
+        chosen: Some(chosen),
+    });
+    Ok(())
+}
+
+impl Catalog {
+    /// The message called `key`, which is like `run.passed` for the
+    /// `passed` key under `[messages.run]`.
+    fn message(&self, key: &str) -> Option<&str> {
+        let (section, name) = key.split_once('.')?;
+        self.messages.get(section)?.get(name).map(String::as_str)
+    }
+}
+
+/// The message called `key`, with each `{name}` in it replaced by the
+/// argument of that name. Use [`t!`] rather than calling this directly.
+pub fn message(key: &str, arguments: &[(&str, &dyn Display)]) -> String {
+    let catalogs = catalogs();
+    let template = catalogs
+        .chosen
+        .as_ref()
+        .and_then(|chosen| chosen.message(key))
+        .or_else(|| catalogs.english.message(key))
+        .unwrap_or(key);
+    fill(template, arguments)
+}
+
+fn fill(template: &str, arguments: &[(&str, &dyn Display)]) -> String {
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        filled.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let argument = after.find('}').and_then(|close| {
+            let (_, value) = arguments
+                .iter()
+                .find(|(name, _)| *name == &after[..close])?;
+            Some((close, value))
+        });
+        match argument {
+            Some((close, value)) => {
+                filled.push_str(&value.to_string());
+                rest = &after[close + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = after;
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+/// `exercise`'s description, translated if it has been.
+pub fn description(exercise: &Exercise) -> &str {
+    catalogs()
+        .chosen
+        .as_ref()
+        .and_then(|chosen| chosen.exercises.get(&exercise.name)?.description.as_deref())
+        .unwrap_or(&exercise.description)
+}
+
+/// `exercise`'s hints, translated if they have been.
+pub fn hints(exercise: &Exercise) -> &[String] {
+    catalogs()
+        .chosen
+        .as_ref()
+        .and_then(|chosen| chosen.exercises.get(&exercise.name)?.hints.as_deref())
+        .unwrap_or(&exercise.hints)
+}
+
+/// What to say about the error `code`, like `E0597`.
+pub fn explanation(code: &str) -> Option<&'static Explanation> {
+    let catalogs = catalogs();
+    catalogs
+        .chosen
+        .as_ref()
+        .and_then(|chosen| chosen.explanations.get(code))
+        .or_else(|| catalogs.english.explanations.get(code))
+}
+
+/// What the badge `id` is called, and what it's for.
+pub fn badge(id: &str) -> Option<&'static Badge> {
+    let catalogs = catalogs();
+    catalogs
+        .chosen
+        .as_ref()
+        .and_then(|chosen| chosen.badges.get(id))
+        .or_else(|| catalogs.english.badges.get(id))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arguments_are_filled_in() {
+        assert_eq!(
+            fill(
+                "{exercise} needs {missing}, {unknown} {",
+                &[("exercise", &"ex04"), ("missing", &3)]
+            ),
+            "ex04 needs 3, {unknown} {"
+        );
+    }
+
+    /// Every key the code uses has an English message, so nothing falls
+    /// back to showing its key.
+    #[test]
+    fn every_message_is_in_english() {
+        let english = english();
+        let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        for entry in std::fs::read_dir(source).unwrap() {
+            let path = entry.unwrap().path();
+            let text = std::fs::read_to_string(&path).unwrap();
+            for (at, call) in text.match_indices("t!(\"") {
+                // Not the end of `format!("`.
+                if text[..at].ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+                    continue;
+                }
+                let part = &text[at + call.len()..];
+                let key = &part[..part.find('"').unwrap()];
+                assert!(
+                    english.message(key).is_some(),
+                    "{} uses `{key}`, which isn't in locales/en.toml",
+                    path.display()
+                );
+            }
+        }
+        for explanation in crate::explain::EXPLANATIONS {
+            assert!(english.explanations.contains_key(explanation.code));
+        }
+        for badge in crate::badges::BADGES {
+            assert!(english.badges.contains_key(badge.id), "{}", badge.id);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Loads `info.toml`, which lists the exercises, so that adding one doesn't
+//! need any changes here.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::exercise::Exercise;
+
+/// Every exercise, in the order they should be done.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Info {
+    pub exercises: Vec<Exercise>,
+}
+
+impl Info {
+    /// Reads `info.toml` from the repository at `root`, and the package name
+    /// of each exercise from its manifest.
+    pub fn load(root: &Path) -> io::Result<Info> {
+        let text = std::fs::read_to_string(root.join("info.toml"))?;
+        let mut info = Info::parse(&text)?;
+        for exercise in &mut info.exercises {
+            exercise.package = package_name(&root.join(&exercise.path))?;
+        }
+        Ok(info)
+    }
+
+    /// Parses and checks the contents of `info.toml`.
+    fn parse(text: &str) -> io::Result<Info> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let info: Info =
+            toml::from_str(text).map_err(|error| invalid(format!("in info.toml: {error}")))?;
+        for (index, exercise) in info.exercises.iter().enumerate() {
+            let earlier = &info.exercises[..index];
+            if earlier.iter().any(|other| other.name == exercise.name) {
+                return Err(invalid(format!(
+                    "info.toml lists `{}` twice",
+                    exercise.name
+                )));
+            }
+            for prerequisite in exercise.prerequisites.iter().flatten() {
+                if !earlier.iter().any(|other| &other.name == prerequisite) {
+                    return Err(invalid(format!(
+                        "`{}` needs `{prerequisite}`, which isn't an exercise before it",
+                        exercise.name
+                    )));
+                }
+            }
+        }
+        Ok(info)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Exercise> {
+        self.exercises
+            .iter()
+            .find(|exercise| exercise.is_called(name))
+    }
+
+    /// The names of the exercises which have to be done before the one at
+    /// `index`.
+    pub fn prerequisites(&self, index: usize) -> Vec<&str> {
+        match &self.exercises[index].prerequisites {
+            Some(names) => names.iter().map(String::as_str).collect(),
+            None if index == 0 => Vec::new(),
+            None => vec![self.exercises[index - 1].name.as_str()],
+        }
+    }
+}
+
+/// The package name in the manifest of the crate at `path`.
+fn package_name(path: &Path) -> io::Result<String> {
+    #[derive(Deserialize)]
+    struct Manifest {
+        package: Package,
+    }
+    #[derive(Deserialize)]
+    struct Package {
+        name: String,
+    }
+
+    let manifest = path.join("Cargo.toml");
+    let text = std::fs::read_to_string(&manifest).map_err(|error| {
+        io::Error::new(error.kind(), format!("{}: {error}", manifest.display()))
+    })?;
+    let manifest: Manifest = toml::from_str(&text).map_err(|error| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {error}", manifest.display()),
+        )
+    })?;
+    Ok(manifest.package.name)
+}
+
+/// The repository's root: the nearest directory at or above the current one
+/// with an `info.toml`.
+pub fn root() -> Option<PathBuf> {
+    let current = std::env::current_dir().ok()?;
+    current
+        .ancestors()
+        .find(|dir| dir.join("info.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn the_repository_info_loads() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let info = Info::load(root).unwrap();
+        for name in ["4", "04", "ex04", "04_mutable_references_and_containers"] {
+            let found = info.find(name).map(|exercise| exercise.name.as_str());
+            assert_eq!(found, Some("mutable_references_and_containers"), "{name}");
+        }
+        assert!(info.find("06").is_none());
+        assert!(info.find("lifetimes").is_none());
+        assert!(info.prerequisites(0).is_empty());
+        assert_eq!(info.prerequisites(1), ["lifetimes_explained"]);
+
+        let sources = ["02", "07"].map(|name| info.find(name).unwrap().source(root));
+        assert!(sources[0].ends_with("src/lib.rs"));
+        assert!(sources[1].ends_with("src/main.rs"));
+        for exercise in &info.exercises {
+            assert!(
+                root.join(exercise.solution_source(root)).is_file(),
+                "{exercise}"
+            );
+        }
+    }
+
+    #[test]
+    fn prerequisites_must_come_first() {
+        let text = r#"
+            [[exercises]]
+            name = "first"
+            path = "first"
+            mode = "compile"
+            prerequisites = ["second"]
+
+            [[exercises]]
+            name = "second"
+            path = "second"
+            mode = "compile-fail"
+        "#;
+        let error = Info::parse(text).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`first` needs `second`, which isn't an exercise before it"
+        );
+        assert!(Info::parse(&text.replace(r#"["second"]"#, "[]")).is_ok());
+        assert!(Info::parse(&text.replace("compile-fail", "explode")).is_err());
+    }
+}
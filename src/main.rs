@@ -1,3 +1,565 @@
-fn main() {
-    println!("Hello, world!");
+//! The `lifetimekata` command, which runs and checks the exercises so that
+//! learners don't have to find each crate themselves.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+mod badges;
+mod daemon;
+mod diff;
+mod exercise;
+mod explain;
+mod grade;
+mod http;
+mod i18n;
+mod info;
+mod progress;
+mod quiz;
+mod report;
+mod reset;
+mod serve;
+mod server;
+mod stats;
+mod style;
+mod tui;
+mod variant;
+mod visualize;
+mod watch;
+mod workshop;
+
+use exercise::Exercise;
+use i18n::t;
+use info::Info;
+use progress::Progress;
+
+#[derive(Parser)]
+#[command(version, about = "Run and check the LifetimeKata exercises")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Let exercises be run before the ones they build on are done.
+    #[arg(long, global = true)]
+    ignore_prerequisites: bool,
+    /// The language to speak, like `fr`, from `locales/`; otherwise it's
+    /// taken from `LIFETIMEKATA_LANG` or `LANG`.
+    #[arg(long, global = true)]
+    lang: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the exercises, and which are done.
+    List,
+    /// Build and check one exercise, showing what went wrong.
+    Run {
+        /// The exercise's name, number, package or directory, like `04` or
+        /// `lifetimes_on_types`.
+        exercise: String,
+    },
+    /// Check the exercises in order, stopping at the first that fails.
+    Verify {
+        /// Start from the first exercise, rather than the first that isn't
+        /// done yet.
+        #[arg(long)]
+        all: bool,
+    },
+    /// Find the first exercise which doesn't pass yet, and say what to do.
+    Next {
+        /// Open the exercise in `$EDITOR` too.
+        #[arg(long)]
+        edit: bool,
+    },
+    /// Show the hints unlocked for an exercise.
+    Hint {
+        exercise: String,
+        /// Unlock the next hint too.
+        #[arg(long)]
+        more: bool,
+    },
+    /// Check the current exercise again whenever its source changes.
+    Watch,
+    /// Show the exercises, the output of checking one and its hints on one
+    /// screen, checking them again as they change.
+    Tui,
+    /// Show the badges earned for milestones, and those still to earn.
+    Badges,
+    /// Show how long each exercise took and how often it failed, and which
+    /// topics were hardest.
+    Stats,
+    /// Check the exercises whenever they change, and serve their
+    /// diagnostics to editors as JSON-RPC; see src/daemon.rs.
+    Daemon {
+        #[arg(long, default_value_t = 7878)]
+        port: u16,
+    },
+    /// Serve the book and a page of how the exercises are going on
+    /// localhost, checking them as they change; see src/serve.rs.
+    Serve {
+        #[arg(long, default_value_t = 8000)]
+        port: u16,
+    },
+    /// Run a workshop: collect the progress of students who `join` it, and
+    /// show a leaderboard for each session; see src/server.rs.
+    Server {
+        #[arg(long, default_value_t = 9000)]
+        port: u16,
+        /// Show students by number rather than by name.
+        #[arg(long)]
+        anonymize: bool,
+    },
+    /// Send your progress to a workshop's server whenever it changes.
+    Join {
+        /// Where the server is, like `http://192.168.1.10:9000`.
+        url: String,
+        /// The session the instructor gave out.
+        session: String,
+        /// The name to show; `$USER`, if it isn't given.
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Stop sending your progress to the workshop joined.
+    Leave,
+    /// Check and score every exercise, with partial credit for passing
+    /// tests.
+    Grade {
+        /// Where to write the report as JSON.
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// The seed the exercises should be the variant for; the one
+        /// `lifetimekata variant` saved, if it isn't given.
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Make the exercises into a variant of their own, starting them over.
+    /// An instructor may hand out seeds, so that `grade` can check them.
+    Variant {
+        /// Which variant; a random one, if it isn't given.
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Don't ask first.
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Write your progress as an HTML page, for handing in.
+    ExportReport {
+        #[arg(long, default_value = "lifetimekata-report.html")]
+        output: PathBuf,
+        /// Who the report is for; `$USER`, if it isn't given.
+        #[arg(long)]
+        student: Option<String>,
+    },
+    /// Show your code for an exercise beside the reference solution.
+    Diff {
+        exercise: String,
+        /// Only compare function signatures, to see the lifetimes without
+        /// the rest of the answer.
+        #[arg(long)]
+        signatures: bool,
+    },
+    /// Explain a lifetime error in plain language, or what the elision
+    /// rules make of a signature. Without either, reads the output of
+    /// `cargo build --message-format=json` and explains the errors in it.
+    Explain {
+        /// An error code, like `E0597`, or a signature, like
+        /// `'fn f(x: &str, y: &str) -> &str'`.
+        code: Option<String>,
+    },
+    /// Answer multiple-choice questions on a chapter, or on all of them.
+    Quiz {
+        /// The chapter's name, number or directory, like `03`.
+        chapter: Option<String>,
+        /// Ask the questions already answered correctly too.
+        #[arg(long)]
+        all: bool,
+        /// Instead, build every snippet which a question says does or
+        /// doesn't compile, to make sure that it's right.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Show where each borrow in a Rust file starts and ends, and what gets
+    /// in its way.
+    Visualize {
+        file: PathBuf,
+        /// Write the picture to this file as HTML, rather than printing it.
+        #[arg(long)]
+        html: Option<PathBuf>,
+    },
+    /// Put an exercise back the way it started, keeping a copy of your
+    /// attempt in `.lifetimekata/backups`.
+    Reset {
+        exercise: String,
+        /// Don't ask first.
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let Some(root) = info::root() else {
+        eprintln!("{}", t!("main.no_root"));
+        return ExitCode::FAILURE;
+    };
+    let result = command(cli, &root);
+    if let Ok(info) = Info::load(&root) {
+        if let Err(error) = badges::announce(&root, &info) {
+            eprintln!("error: {error}");
+        }
+        if let Err(error) = workshop::push(&root, &info) {
+            eprintln!("{}", t!("workshop.unsent", error = error));
+        }
+    }
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Does what `cli` asks, returning whether it went well.
+fn command(cli: Cli, root: &Path) -> io::Result<bool> {
+    i18n::init(root, cli.lang.as_deref())?;
+    let info = Info::load(root)?;
+    let mut progress = Progress::load(root)?;
+    match cli.command {
+        Command::List => {
+            list(&info, &progress);
+            Ok(true)
+        }
+        Command::Run { exercise } => {
+            let exercise = find(root, &info, &exercise)?;
+            let index = info
+                .exercises
+                .iter()
+                .position(|other| other.name == exercise.name)
+                .expect("find only returns listed exercises");
+            let missing = progress.missing_prerequisites(&info, index);
+            if !missing.is_empty() && !cli.ignore_prerequisites {
+                let missing = missing.join(&t!("run.and"));
+                println!(
+                    "🔒 {}",
+                    t!("run.locked", exercise = exercise, missing = missing)
+                );
+                return Ok(false);
+            }
+            let (passed, output) = exercise.check(root)?;
+            print!("{}", clean(&String::from_utf8_lossy(&output.stdout)));
+            print!("{}", clean(&String::from_utf8_lossy(&output.stderr)));
+            report(exercise, passed);
+            progress.record(exercise, passed);
+            progress.record_errors(exercise, &String::from_utf8_lossy(&output.stderr));
+            progress.save(root)?;
+            if !passed && exercise.mode != exercise::Mode::CompileFail {
+                explain_errors(root, &info, &exercise.messages(root)?);
+            }
+            Ok(passed)
+        }
+        Command::Verify { all } => verify(root, &info, &mut progress, all),
+        Command::Next { edit } => next(root, &info, &mut progress, edit),
+        Command::Hint { exercise, more } => {
+            let exercise = find(root, &info, &exercise)?;
+            let hints = i18n::hints(exercise);
+            if hints.is_empty() {
+                println!("{}", t!("hint.none", exercise = exercise));
+                return Ok(true);
+            }
+            let shown = progress.unlock_hints(exercise, more);
+            progress.save(root)?;
+            for (number, hint) in hints[..shown].iter().enumerate() {
+                println!("{}. {hint}", number + 1);
+            }
+            if shown < hints.len() {
+                let more = hints.len() - shown;
+                println!(
+                    "\n{}",
+                    t!("hint.more", count = more, exercise = exercise.name)
+                );
+            }
+            Ok(true)
+        }
+        Command::Watch => watch::watch(root, &info),
+        Command::Tui => tui::tui(root, &info),
+        Command::Variant { seed, yes } => variant::variant(root, &info, seed, yes),
+        Command::Daemon { port } => daemon::daemon(root, &info, port),
+        Command::Serve { port } => serve::serve(root, &info, port),
+        Command::Server { port, anonymize } => server::server(&info, port, anonymize),
+        Command::Join { url, session, name } => workshop::join(root, &info, &url, &session, name),
+        Command::Leave => workshop::leave(root),
+        Command::Badges => {
+            badges::announce(root, &info)?;
+            badges::badges(&info, &Progress::load(root)?);
+            Ok(true)
+        }
+        Command::Stats => {
+            stats::stats(&info, &progress);
+            Ok(true)
+        }
+        Command::Grade { output, seed } => {
+            let seed = match seed {
+                Some(seed) => Some(seed),
+                None => variant::seed(root)?,
+            };
+            let report = grade::grade(root, &info, seed)?;
+            report.print();
+            if let Some(output) = output {
+                let json = serde_json::to_string_pretty(&report).map_err(io::Error::other)?;
+                std::fs::write(&output, json + "\n")?;
+                println!("{}", t!("report.written", path = output.display()));
+            }
+            Ok(true)
+        }
+        Command::ExportReport { output, student } => {
+            let student = student
+                .or_else(|| std::env::var("USER").ok())
+                .unwrap_or_else(|| t!("report.student"));
+            std::fs::write(&output, report::html(&info, &progress, &student))?;
+            println!("{}", t!("report.written", path = output.display()));
+            Ok(true)
+        }
+        Command::Diff {
+            exercise,
+            signatures,
+        } => {
+            diff::diff(root, find(root, &info, &exercise)?, signatures)?;
+            Ok(true)
+        }
+        Command::Explain {
+            code: Some(signature),
+        } if signature.contains('(') => Ok(explain::elision(root, &info, &signature)),
+        Command::Explain { code: Some(code) } => match explain::lookup(&code) {
+            Some(explanation) => {
+                explain::print(root, &info, explanation);
+                Ok(true)
+            }
+            None => {
+                let codes: Vec<_> = explain::EXPLANATIONS.iter().map(|e| e.code).collect();
+                let codes = codes.join(", ");
+                println!("{}", t!("explain.unknown", code = code, codes = codes));
+                Ok(false)
+            }
+        },
+        Command::Explain { code: None } => {
+            let json = io::read_to_string(io::stdin())?;
+            let explained = explain_errors(root, &info, &json);
+            if !explained {
+                println!("{}", t!("explain.nothing"));
+            }
+            Ok(explained)
+        }
+        Command::Quiz { check: true, .. } => quiz::check(root),
+        Command::Quiz { chapter, all, .. } => {
+            quiz::quiz(root, &mut progress, chapter.as_deref(), all)
+        }
+        Command::Visualize { file, html } => {
+            visualize::visualize(&file, html.as_deref())?;
+            Ok(true)
+        }
+        Command::Reset { exercise, yes } => {
+            let exercise = find(root, &info, &exercise)?;
+            if reset::reset(root, exercise, yes)? {
+                progress.forget(exercise);
+                progress.save(root)?;
+            }
+            Ok(true)
+        }
+    }
+}
+
+/// The exercise called `name`. If there isn't one, but there's a chapter
+/// by that name, the error says to read it instead.
+fn find<'a>(root: &Path, info: &'a Info, name: &str) -> io::Result<&'a Exercise> {
+    if let Some(exercise) = info.find(name) {
+        return Ok(exercise);
+    }
+    let chapter = std::fs::read_dir(root.join("exercises"))?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .find(|dir| {
+            let (number, title) = dir.split_once('_').unwrap_or((dir, ""));
+            dir == name
+                || title == name
+                || name.parse::<u32>().is_ok_and(|n| number.parse() == Ok(n))
+        });
+    let message = match chapter {
+        Some(dir) => t!("find.chapter", dir = dir),
+        None => t!("find.unknown", name = name),
+    };
+    Err(io::Error::new(io::ErrorKind::NotFound, message))
+}
+
+/// `output` from cargo without the lines which only repeat that something
+/// failed, or say where cargo is up to.
+fn clean(output: &str) -> String {
+    const NOISE: &[&str] = &[
+        "error: could not compile",
+        "warning: build failed",
+        "error: test failed, to rerun pass",
+        "For more information about this error",
+        "For more information about an error",
+        "Some errors have detailed explanations",
+        "     Running ",
+        "   Doc-tests ",
+    ];
+    let mut cleaned = String::new();
+    let mut blank = true;
+    for line in output.lines() {
+        if NOISE.iter().any(|noise| line.starts_with(noise)) {
+            continue;
+        }
+        // Runs of blank lines are left where the noise was.
+        let is_blank = line.trim().is_empty();
+        if !(is_blank && blank) {
+            cleaned.push_str(line);
+            cleaned.push('\n');
+        }
+        blank = is_blank;
+    }
+    cleaned
+}
+
+/// Explains the lifetime errors in cargo's JSON `messages`, returning
+/// whether there were any.
+fn explain_errors(root: &Path, info: &Info, messages: &str) -> bool {
+    let codes = explain::codes(messages);
+    for code in &codes {
+        println!();
+        explain::print(
+            root,
+            info,
+            explain::lookup(code).expect("codes are explained"),
+        );
+    }
+    !codes.is_empty()
+}
+
+fn list(info: &Info, progress: &Progress) {
+    for (index, exercise) in info.exercises.iter().enumerate() {
+        let attempts = progress.get(exercise).map_or(0, |record| record.attempts);
+        println!(
+            "{} {:<40} {:>3} {}  {:<57} {}",
+            progress.state(info, index).icon(),
+            exercise.dir(),
+            attempts,
+            t!("list.attempts"),
+            exercise.path.display(),
+            exercise.tags.join(", ")
+        );
+    }
+}
+
+fn report(exercise: &Exercise, passed: bool) {
+    if passed {
+        println!("✓ {}", t!("run.passed", exercise = exercise));
+    } else {
+        println!("✗ {}", t!("run.failed", exercise = exercise));
+    }
+}
+
+/// Checks the exercises in order, showing the output of the first one
+/// that fails. Unless `all` is set, those already done are skipped.
+fn verify(root: &Path, info: &Info, progress: &mut Progress, all: bool) -> io::Result<bool> {
+    let start = if all {
+        0
+    } else {
+        progress.first_incomplete(info)
+    };
+    let (done, rest) = info.exercises.split_at(start);
+    for exercise in done {
+        println!("✓ {}", t!("verify.done", exercise = exercise));
+    }
+    for exercise in rest {
+        let (passed, output) = exercise.check(root)?;
+        report(exercise, passed);
+        progress.record(exercise, passed);
+        progress.record_errors(exercise, &String::from_utf8_lossy(&output.stderr));
+        progress.save(root)?;
+        if !passed {
+            print!("{}", clean(&String::from_utf8_lossy(&output.stdout)));
+            eprint!("{}", clean(&String::from_utf8_lossy(&output.stderr)));
+            println!("\n{}", t!("verify.stuck", exercise = exercise.name));
+            return Ok(false);
+        }
+    }
+    println!("{}", t!("verify.all", count = info.exercises.len()));
+    Ok(true)
+}
+
+/// Checks the exercises which aren't done, and describes the first of them
+/// which fails, opening it in `$EDITOR` if `edit` is set.
+fn next(root: &Path, info: &Info, progress: &mut Progress, edit: bool) -> io::Result<bool> {
+    for exercise in &info.exercises[progress.first_incomplete(info)..] {
+        let (passed, output) = exercise.check(root)?;
+        progress.record(exercise, passed);
+        progress.record_errors(exercise, &String::from_utf8_lossy(&output.stderr));
+        progress.save(root)?;
+        if passed {
+            continue;
+        }
+        println!("{}", t!("next.up", exercise = exercise));
+        println!("  {}", i18n::description(exercise));
+        println!(
+            "  {}",
+            t!("next.edit", path = exercise.source(root).display())
+        );
+        if edit {
+            let Some(editor) = std::env::var_os("EDITOR") else {
+                println!("{}", t!("next.editor"));
+                return Ok(true);
+            };
+            // `$EDITOR` may carry arguments of its own, like `code --wait`.
+            let editor = editor.to_string_lossy().into_owned();
+            let mut words = editor.split_whitespace();
+            let program = words.next().unwrap_or("vi");
+            std::process::Command::new(program)
+                .args(words)
+                .arg(exercise.source(root))
+                .current_dir(root)
+                .status()?;
+        }
+        return Ok(true);
+    }
+    println!("{}", t!("verify.all", count = info.exercises.len()));
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chapters_without_exercises_are_pointed_to() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let info = Info::load(root).unwrap();
+        assert_eq!(find(root, &info, "05").unwrap().name, "lifetimes_on_types");
+        for name in ["06", "lifetimes_on_impls"] {
+            let error = find(root, &info, name).unwrap_err().to_string();
+            assert!(
+                error.starts_with("06_lifetimes_on_impls is a chapter"),
+                "{error}"
+            );
+        }
+        let error = find(root, &info, "99").unwrap_err().to_string();
+        assert!(error.starts_with("there's no exercise called `99`"));
+    }
+
+    #[test]
+    fn cargo_noise_is_cleaned_away() {
+        let output = "\
+error[E0106]: missing lifetime specifier
+ --> src/lib.rs:1:1
+
+For more information about this error, try `rustc --explain E0106`.
+error: could not compile `ex02` (lib) due to 1 previous error
+";
+        assert_eq!(
+            clean(output),
+            "error[E0106]: missing lifetime specifier\n --> src/lib.rs:1:1\n\n"
+        );
+    }
 }
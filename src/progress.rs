@@ -0,0 +1,291 @@
+//! What the learner has done so far, kept in `.lifetimekata/progress.json`
+//! so that `list` and `verify` can pick up where they left off.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::exercise::Exercise;
+use crate::info::Info;
+
+/// Every exercise's record, by its directory, every quiz question's, by
+/// its id, and when each badge was earned.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Progress {
+    exercises: BTreeMap<String, Record>,
+    #[serde(default)]
+    quiz: BTreeMap<String, Answer>,
+    #[serde(default)]
+    badges: BTreeMap<String, u64>,
+}
+
+/// How one exercise has gone. Times are in seconds since the Unix epoch.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Record {
+    /// Whether it passed the last time it was checked.
+    pub done: bool,
+    /// How many times it has been checked.
+    pub attempts: u32,
+    /// How many of those checks failed before it first passed.
+    #[serde(default)]
+    pub failed_attempts: u32,
+    pub first_attempt: Option<u64>,
+    pub last_attempt: Option<u64>,
+    /// When it first passed.
+    pub completed: Option<u64>,
+    /// How many of its hints have been unlocked.
+    #[serde(default)]
+    pub hints_shown: usize,
+    /// How many times each error code, like `E0106` or `LK0001`, came up
+    /// while it was failing.
+    #[serde(default)]
+    pub errors: BTreeMap<String, u32>,
+}
+
+impl Record {
+    /// How many seconds it took from first being checked, by `next`, `run`,
+    /// `verify` or `watch`, to first passing.
+    pub fn time_to_pass(&self) -> Option<u64> {
+        Some(self.completed?.saturating_sub(self.first_attempt?))
+    }
+}
+
+/// How one quiz question has gone.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Answer {
+    /// How many times it has been answered.
+    pub attempts: u32,
+    /// Whether it was answered correctly the last time.
+    pub correct: bool,
+}
+
+/// How an exercise is shown by `lifetimekata list`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum State {
+    Done,
+    /// An exercise whose prerequisites are done, but which isn't.
+    Current,
+    /// An exercise whose prerequisites aren't all done.
+    Locked,
+}
+
+impl State {
+    pub fn icon(self) -> &'static str {
+        match self {
+            State::Done => "✅",
+            State::Current => "❌",
+            State::Locked => "🔒",
+        }
+    }
+}
+
+impl Progress {
+    fn path(root: &Path) -> PathBuf {
+        root.join(".lifetimekata").join("progress.json")
+    }
+
+    /// The progress saved under `root`, or none at all if nothing has been
+    /// saved yet.
+    pub fn load(root: &Path) -> io::Result<Progress> {
+        match std::fs::read_to_string(Self::path(root)) {
+            Ok(json) => serde_json::from_str(&json).map_err(io::Error::other),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Progress::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn save(&self, root: &Path) -> io::Result<()> {
+        let path = Self::path(root);
+        std::fs::create_dir_all(path.parent().expect("the path is in a directory"))?;
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, json + "\n")
+    }
+
+    /// Notes that `exercise` was just checked, and whether it passed.
+    pub fn record(&mut self, exercise: &Exercise, passed: bool) {
+        let now = now();
+        let record = self.entry(exercise);
+        record.done = passed;
+        record.attempts += 1;
+        if !passed && record.completed.is_none() {
+            record.failed_attempts += 1;
+        }
+        record.first_attempt.get_or_insert(now);
+        record.last_attempt = Some(now);
+        if passed {
+            record.completed.get_or_insert(now);
+        }
+    }
+
+    /// Counts the error codes in `output`, from a failed check of
+    /// `exercise`.
+    pub fn record_errors(&mut self, exercise: &Exercise, output: &str) {
+        let record = self.entry(exercise);
+        for code in error_codes(output) {
+            *record.errors.entry(code).or_default() += 1;
+        }
+    }
+
+    /// Notes that `exercise` was reset, so it isn't done any more.
+    pub fn forget(&mut self, exercise: &Exercise) {
+        self.entry(exercise).done = false;
+    }
+
+    /// Unlocks the first of `exercise`'s hints, or the next if `more` is
+    /// set, returning how many of them are unlocked.
+    pub fn unlock_hints(&mut self, exercise: &Exercise, more: bool) -> usize {
+        let record = self.entry(exercise);
+        if more || record.hints_shown == 0 {
+            record.hints_shown += 1;
+        }
+        record.hints_shown = record.hints_shown.min(exercise.hints.len());
+        record.hints_shown
+    }
+
+    /// Notes that the quiz question `id` was just answered.
+    pub fn record_answer(&mut self, id: &str, correct: bool) {
+        let answer = self.quiz.entry(id.to_string()).or_default();
+        answer.attempts += 1;
+        answer.correct = correct;
+    }
+
+    pub fn answer(&self, id: &str) -> Option<&Answer> {
+        self.quiz.get(id)
+    }
+
+    /// How many quiz questions were answered correctly the last time.
+    pub fn correct_answers(&self) -> usize {
+        self.quiz.values().filter(|answer| answer.correct).count()
+    }
+
+    /// Notes that the badge `id` was earned, unless it already had been.
+    /// Badges are kept even if what earned them is reset.
+    pub fn earn(&mut self, id: &str) {
+        self.badges.entry(id.to_string()).or_insert_with(now);
+    }
+
+    /// When the badge `id` was earned, if it has been.
+    pub fn earned(&self, id: &str) -> Option<u64> {
+        self.badges.get(id).copied()
+    }
+
+    fn entry(&mut self, exercise: &Exercise) -> &mut Record {
+        self.exercises
+            .entry(exercise.dir().to_string())
+            .or_default()
+    }
+
+    pub fn get(&self, exercise: &Exercise) -> Option<&Record> {
+        self.exercises.get(exercise.dir())
+    }
+
+    pub fn is_done(&self, exercise: &Exercise) -> bool {
+        self.get(exercise).is_some_and(|record| record.done)
+    }
+
+    /// The index of the first exercise which isn't done, or the number of
+    /// exercises if they all are.
+    pub fn first_incomplete(&self, info: &Info) -> usize {
+        info.exercises
+            .iter()
+            .position(|exercise| !self.is_done(exercise))
+            .unwrap_or(info.exercises.len())
+    }
+
+    pub fn state(&self, info: &Info, index: usize) -> State {
+        if self.is_done(&info.exercises[index]) {
+            State::Done
+        } else if self.missing_prerequisites(info, index).is_empty() {
+            State::Current
+        } else {
+            State::Locked
+        }
+    }
+
+    /// The prerequisites of the exercise at `index` which aren't done yet.
+    pub fn missing_prerequisites<'a>(&self, info: &'a Info, index: usize) -> Vec<&'a str> {
+        let done = |name: &&str| info.find(name).is_some_and(|other| self.is_done(other));
+        info.prerequisites(index)
+            .into_iter()
+            .filter(|name| !done(name))
+            .collect()
+    }
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+/// The codes of the errors in rustc's `output`: `error[E0106]` for rustc's
+/// own, and `error: [LK0001]` for `#[require_lifetimes]`.
+fn error_codes(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let rest = line.strip_prefix("error")?;
+            let rest = rest.strip_prefix(": ").unwrap_or(rest);
+            let (code, _) = rest.strip_prefix('[')?.split_once(']')?;
+            Some(code.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn passing_moves_the_current_exercise_on() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let first = &info.exercises[0];
+        let mut progress = Progress::default();
+        assert_eq!(progress.first_incomplete(&info), 0);
+        progress.record(first, false);
+        progress.record(first, true);
+        assert_eq!(progress.first_incomplete(&info), 1);
+        assert_eq!(progress.state(&info, 0), State::Done);
+        assert_eq!(progress.state(&info, 1), State::Current);
+        assert_eq!(progress.state(&info, 2), State::Locked);
+        assert_eq!(
+            progress.missing_prerequisites(&info, 2),
+            [info.exercises[1].name.as_str()]
+        );
+
+        let record = progress.get(first).unwrap();
+        assert_eq!(record.attempts, 2);
+        assert_eq!(record.failed_attempts, 1);
+        assert!(record.completed.is_some());
+        assert!(record.time_to_pass().is_some());
+
+        assert_eq!(progress.unlock_hints(first, false), 1);
+        assert_eq!(progress.unlock_hints(first, false), 1);
+        assert_eq!(progress.unlock_hints(first, true), 2);
+        let levels = first.hints.len();
+        for _ in 0..levels {
+            progress.unlock_hints(first, true);
+        }
+        assert_eq!(progress.unlock_hints(first, true), levels);
+
+        progress.record_errors(
+            first,
+            "error[E0106]: missing lifetime specifier\n\
+             error: [LK0001] `&str` here needs a name\n\
+             error[E0106]: missing lifetime specifier\n\
+             error: could not compile `ex02`\n",
+        );
+        let errors = &progress.get(first).unwrap().errors;
+        assert_eq!(errors.get("E0106"), Some(&2));
+        assert_eq!(errors.get("LK0001"), Some(&1));
+        assert_eq!(errors.len(), 2);
+
+        let json = serde_json::to_string(&progress).unwrap();
+        let loaded: Progress = serde_json::from_str(&json).unwrap();
+        assert!(loaded.is_done(first));
+    }
+}
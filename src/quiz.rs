@@ -0,0 +1,307 @@
+//! `lifetimekata quiz`: multiple-choice questions on each chapter, from the
+//! files in `quiz/`.
+//!
+//! A question about whether a snippet compiles says so with `compiles`, and
+//! `lifetimekata quiz --check` builds every such snippet to make sure the
+//! answers haven't gone stale as the compiler's got cleverer.
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+
+use crate::i18n::t;
+use crate::progress::Progress;
+use crate::style::{paint, GREEN, RED};
+
+/// The questions in one of the files in `quiz/`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Bank {
+    questions: Vec<Question>,
+}
+
+/// One question, as a quiz file describes it.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Question {
+    /// What the learner's progress is kept under, so it mustn't change.
+    pub id: String,
+    pub prompt: String,
+    /// A snippet to show under the prompt.
+    #[serde(default)]
+    pub code: Option<String>,
+    pub choices: Vec<String>,
+    /// The letter of the right choice, like `b`.
+    pub answer: char,
+    /// Why that's the answer, shown once the question has been answered.
+    pub explanation: String,
+    /// Whether `code` compiles, for `quiz --check` to make sure of.
+    #[serde(default)]
+    pub compiles: Option<bool>,
+    /// The chapter the question is about: the name of its file.
+    #[serde(skip)]
+    pub chapter: String,
+}
+
+impl Question {
+    /// The index into `choices` of the right one.
+    fn right(&self) -> usize {
+        (self.answer.to_ascii_lowercase() as u8).wrapping_sub(b'a') as usize
+    }
+}
+
+/// Every question in `quiz/`, a chapter at a time, in chapter order.
+pub fn load(root: &Path) -> io::Result<Vec<Question>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(root.join("quiz"))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+    files.sort();
+    let mut questions = Vec::new();
+    for file in files {
+        let chapter = file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let text = std::fs::read_to_string(&file)?;
+        questions.extend(parse(&text, &chapter)?);
+    }
+    for (index, question) in questions.iter().enumerate() {
+        if questions[..index]
+            .iter()
+            .any(|other| other.id == question.id)
+        {
+            return Err(invalid(format!(
+                "the quiz has two questions called `{}`",
+                question.id
+            )));
+        }
+    }
+    Ok(questions)
+}
+
+/// Parses and checks the questions in the quiz file for `chapter`.
+fn parse(text: &str, chapter: &str) -> io::Result<Vec<Question>> {
+    let bank: Bank = toml::from_str(text)
+        .map_err(|error| invalid(format!("in quiz/{chapter}.toml: {error}")))?;
+    let mut questions = bank.questions;
+    for question in &mut questions {
+        question.chapter = chapter.to_string();
+        if question.right() >= question.choices.len() {
+            return Err(invalid(format!(
+                "`{}` in quiz/{chapter}.toml has no choice `{}`",
+                question.id, question.answer
+            )));
+        }
+        if question.compiles.is_some() && question.code.is_none() {
+            return Err(invalid(format!(
+                "`{}` in quiz/{chapter}.toml says whether its code compiles, but has none",
+                question.id
+            )));
+        }
+    }
+    Ok(questions)
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Whether `name` refers to `chapter`, like `03_lifetime_elision`: all of
+/// it, its number or the words after the number.
+fn is_chapter(chapter: &str, name: &str) -> bool {
+    let (number, title) = chapter.split_once('_').unwrap_or((chapter, ""));
+    chapter == name || title == name || name.parse::<u32>().is_ok_and(|n| number.parse() == Ok(n))
+}
+
+/// Asks the questions about `chapter`, or every chapter, which haven't been
+/// answered correctly yet; all of them, if `all` is set. Returns whether
+/// every question answered was answered correctly.
+pub fn quiz(
+    root: &Path,
+    progress: &mut Progress,
+    chapter: Option<&str>,
+    all: bool,
+) -> io::Result<bool> {
+    let questions: Vec<Question> = load(root)?
+        .into_iter()
+        .filter(|question| chapter.is_none_or(|name| is_chapter(&question.chapter, name)))
+        .filter(|question| all || !progress.answer(&question.id).is_some_and(|a| a.correct))
+        .collect();
+    if questions.is_empty() {
+        println!("{}", t!("quiz.nothing"));
+        return Ok(true);
+    }
+
+    let mut input = io::stdin().lock();
+    let (mut asked, mut right) = (0, 0);
+    for (number, question) in questions.iter().enumerate() {
+        println!(
+            "\n{}/{} ({})",
+            number + 1,
+            questions.len(),
+            question.chapter
+        );
+        println!("{}", question.prompt);
+        if let Some(code) = &question.code {
+            println!("\n{}", code.trim_end());
+        }
+        println!();
+        for (index, choice) in question.choices.iter().enumerate() {
+            println!("  {}) {choice}", (b'a' + index as u8) as char);
+        }
+        let Some(chosen) = ask(&mut input, question.choices.len())? else {
+            println!();
+            break;
+        };
+        let correct = chosen == question.right();
+        asked += 1;
+        if correct {
+            right += 1;
+            println!("{}", paint(GREEN, &format!("✓ {}", t!("quiz.right"))));
+        } else {
+            let answer = question.answer.to_ascii_lowercase();
+            let message = format!("✗ {}", t!("quiz.wrong", answer = answer));
+            println!("{}", paint(RED, &message));
+        }
+        println!("{}", question.explanation.trim_end());
+        progress.record_answer(&question.id, correct);
+        progress.save(root)?;
+    }
+    println!("\n{}", t!("quiz.score", right = right, asked = asked));
+    Ok(right == asked)
+}
+
+/// Reads a choice from `input`, asking again until it's one of the first
+/// `count` letters. `None` means the learner has had enough.
+fn ask(input: &mut impl BufRead, count: usize) -> io::Result<Option<usize>> {
+    loop {
+        print!("Your answer (q to stop): ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        match parse_choice(&line, count) {
+            Some(Choice::Stop) => return Ok(None),
+            Some(Choice::Letter(index)) => return Ok(Some(index)),
+            None => {}
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Choice {
+    Letter(usize),
+    Stop,
+}
+
+fn parse_choice(line: &str, count: usize) -> Option<Choice> {
+    let mut chars = line.trim().chars();
+    let letter = chars.next()?.to_ascii_lowercase();
+    if chars.next().is_some_and(|c| c != ')') {
+        return None;
+    }
+    match letter {
+        'q' => Some(Choice::Stop),
+        'a'..='z' if ((letter as u8 - b'a') as usize) < count => {
+            Some(Choice::Letter((letter as u8 - b'a') as usize))
+        }
+        _ => None,
+    }
+}
+
+/// Builds every snippet which a question says does or doesn't compile, in a
+/// crate of its own, returning whether they all do what their question says.
+pub fn check(root: &Path) -> io::Result<bool> {
+    let dir = std::env::temp_dir().join("lifetimekata-quiz");
+    std::fs::create_dir_all(dir.join("src"))?;
+    // `[workspace]` keeps cargo from looking for a workspace further up.
+    std::fs::write(
+        dir.join("Cargo.toml"),
+        "[package]\nname = \"quiz\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+    )?;
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+
+    let mut wrong = 0;
+    for question in load(root)? {
+        let (Some(code), Some(compiles)) = (&question.code, question.compiles) else {
+            continue;
+        };
+        std::fs::write(
+            dir.join("src").join("lib.rs"),
+            format!("#![allow(unused)]\n{code}"),
+        )?;
+        let built = Command::new(&cargo)
+            .current_dir(&dir)
+            .args(["check", "--quiet", "--offline"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?
+            .success();
+        if built == compiles {
+            println!("{} {}", paint(GREEN, "✓"), question.id);
+        } else {
+            wrong += 1;
+            let says = if compiles {
+                "compiles"
+            } else {
+                "doesn't compile"
+            };
+            println!(
+                "{} {}: quiz/{}.toml says it {says}, but it {}",
+                paint(RED, "✗"),
+                question.id,
+                question.chapter,
+                if built { "does" } else { "doesn't" }
+            );
+        }
+    }
+    Ok(wrong == 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn the_quiz_files_load() {
+        let questions = load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        assert!(!questions.is_empty());
+        assert!(questions
+            .iter()
+            .any(|question| is_chapter(&question.chapter, "lifetime_elision")));
+    }
+
+    #[test]
+    fn answers_must_be_choices() {
+        let text = r#"
+[[questions]]
+id = "too-far"
+prompt = "Which?"
+choices = ["this", "that"]
+answer = "c"
+explanation = "Neither."
+"#;
+        let error = parse(text, "01_lifetimes_needed").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "`too-far` in quiz/01_lifetimes_needed.toml has no choice `c`"
+        );
+    }
+
+    #[test]
+    fn choices_are_letters() {
+        assert_eq!(parse_choice("b\n", 3), Some(Choice::Letter(1)));
+        assert_eq!(parse_choice(" C) ", 3), Some(Choice::Letter(2)));
+        assert_eq!(parse_choice("d", 3), None);
+        assert_eq!(parse_choice("q", 3), Some(Choice::Stop));
+        assert_eq!(parse_choice("", 3), None);
+    }
+}
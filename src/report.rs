@@ -0,0 +1,150 @@
+//! `lifetimekata export-report`: a self-contained HTML page of the learner's
+//! progress, for an instructor to collect one of from each student.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::info::Info;
+use crate::progress::{Progress, State};
+
+/// The page for `student`, from what `progress` has recorded.
+pub fn html(info: &Info, progress: &Progress, student: &str) -> String {
+    let mut rows = String::new();
+    let mut errors: BTreeMap<String, u32> = BTreeMap::new();
+    let mut done = 0;
+    for (index, exercise) in info.exercises.iter().enumerate() {
+        let record = progress.get(exercise).cloned().unwrap_or_default();
+        let state = progress.state(info, index);
+        if state == State::Done {
+            done += 1;
+        }
+        let status = if state == State::Done {
+            "complete"
+        } else if record.attempts > 0 {
+            "in progress"
+        } else {
+            "not started"
+        };
+        // The time between the first check and the one that passed, or the
+        // latest one if none has.
+        let spent = record
+            .first_attempt
+            .zip(record.completed.or(record.last_attempt))
+            .map_or(String::from("–"), |(first, last)| duration(last - first));
+        for (code, count) in &record.errors {
+            *errors.entry(code.clone()).or_default() += count;
+        }
+        let _ = writeln!(
+            rows,
+            "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            status.replace(' ', "-"),
+            escape(exercise.dir()),
+            status,
+            record.attempts,
+            spent,
+            record.hints_shown,
+        );
+    }
+
+    let mut common: Vec<_> = errors.into_iter().collect();
+    common.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let mut error_rows = String::new();
+    for (code, count) in common.iter().take(10) {
+        let _ = writeln!(
+            error_rows,
+            "<tr><td><code>{}</code></td><td>{count}</td></tr>",
+            escape(code)
+        );
+    }
+    if error_rows.is_empty() {
+        error_rows.push_str("<tr><td colspan=\"2\">None recorded.</td></tr>\n");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>LifetimeKata progress: {student}</title>
+<style>
+body {{ font-family: sans-serif; max-width: 50em; margin: 2em auto; }}
+table {{ border-collapse: collapse; width: 100%; margin-bottom: 2em; }}
+th, td {{ border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }}
+tr.complete td:nth-child(2) {{ color: #1a7f37; }}
+tr.in-progress td:nth-child(2) {{ color: #9a6700; }}
+tr.not-started td:nth-child(2) {{ color: #777; }}
+</style>
+</head>
+<body>
+<h1>LifetimeKata progress: {student}</h1>
+<p>{done} of {total} exercises complete.</p>
+<h2>Exercises</h2>
+<table>
+<tr><th>Exercise</th><th>Status</th><th>Attempts</th><th>Time spent</th><th>Hints used</th></tr>
+{rows}</table>
+<h2>Most common errors</h2>
+<table>
+<tr><th>Code</th><th>Times seen</th></tr>
+{error_rows}</table>
+</body>
+</html>
+"#,
+        student = escape(student),
+        total = info.exercises.len(),
+    )
+}
+
+/// `seconds` the way a person would say it, like `1h 5m`.
+pub fn duration(seconds: u64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds % 3600 / 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, _) => format!("{minutes}m"),
+        _ => format!("{hours}h {minutes}m"),
+    }
+}
+
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn the_report_counts_what_was_recorded() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let mut progress = Progress::default();
+        progress.record(&info.exercises[0], true);
+        progress.record(&info.exercises[1], false);
+        progress.record_errors(
+            &info.exercises[1],
+            "error[E0106]: missing lifetime specifier",
+        );
+
+        let html = html(&info, &progress, "<Sam>");
+        assert!(html.contains("LifetimeKata progress: &lt;Sam&gt;"));
+        assert!(html.contains(&format!("1 of {} exercises complete", info.exercises.len())));
+        assert!(html.contains("<tr><td><code>E0106</code></td><td>1</td></tr>"));
+        assert!(!html.contains("None recorded."));
+    }
+
+    #[test]
+    fn durations_are_readable() {
+        assert_eq!(duration(42), "42s");
+        assert_eq!(duration(600), "10m");
+        assert_eq!(duration(3900), "1h 5m");
+    }
+}
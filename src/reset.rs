@@ -0,0 +1,122 @@
+//! `lifetimekata reset`: puts an exercise back the way it started, keeping
+//! a copy of the learner's attempt.
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use include_dir::{include_dir, Dir};
+
+use crate::exercise::Exercise;
+use crate::i18n::t;
+use crate::variant;
+
+/// Every exercise crate as it was committed, laid out as in the repository.
+static PRISTINE: Dir = include_dir!("$OUT_DIR/pristine");
+
+/// Asks whether to reset `exercise`, unless `yes` is set, then backs it up
+/// and resets it. Returns whether it was reset.
+pub fn reset(root: &Path, exercise: &Exercise, yes: bool) -> io::Result<bool> {
+    if !yes {
+        print!("{} [y/N] ", t!("reset.ask", exercise = exercise));
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("{}", t!("reset.declined", exercise = exercise));
+            return Ok(false);
+        }
+    }
+    let backup = restore(root, exercise)?;
+    if let Some(seed) = variant::seed(root)? {
+        variant::apply(root, exercise, seed)?;
+    }
+    let backup = backup.display();
+    println!("{}", t!("reset.done", exercise = exercise, backup = backup));
+    Ok(true)
+}
+
+/// Copies `exercise`'s source into `.lifetimekata/backups`, then replaces it
+/// with the pristine copy, returning where the backup went.
+pub fn restore(root: &Path, exercise: &Exercise) -> io::Result<PathBuf> {
+    let pristine = PRISTINE.get_dir(&exercise.path).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("there's no original copy of {exercise} to reset it to"),
+        )
+    })?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let backup = Path::new(".lifetimekata")
+        .join("backups")
+        .join(format!("{}-{now}", exercise.dir()));
+    let crate_dir = root.join(&exercise.path);
+    copy(&crate_dir, &root.join(&backup))?;
+
+    // Files the learner added to `src` go too; they're in the backup.
+    match std::fs::remove_dir_all(crate_dir.join("src")) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => {}
+    }
+    pristine.extract(root)?;
+    Ok(backup)
+}
+
+/// Copies the crate at `from` to `to`, leaving out anything it has built.
+fn copy(from: &Path, to: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if entry.file_name() != "target" {
+                copy(&path, &to.join(entry.file_name()))?;
+            }
+        } else {
+            std::fs::copy(&path, to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::info::Info;
+
+    #[test]
+    fn every_exercise_has_an_original() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        for exercise in &info.exercises {
+            let pristine = PRISTINE.get_dir(&exercise.path).unwrap();
+            assert!(pristine
+                .get_file(exercise.path.join("Cargo.toml"))
+                .is_some());
+            assert!(pristine
+                .get_file(exercise.path.join("src/lib.rs"))
+                .or_else(|| pristine.get_file(exercise.path.join("src/main.rs")))
+                .is_some());
+        }
+    }
+
+    #[test]
+    fn restoring_keeps_the_attempt() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let exercise = &info.exercises[0];
+        let root = std::env::temp_dir().join(format!("lifetimekata-reset-{}", std::process::id()));
+        let source = root.join(exercise.source(&root));
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        std::fs::write(&source, "mangled").unwrap();
+        std::fs::write(source.with_file_name("extra.rs"), "extra").unwrap();
+
+        let backup = restore(&root, exercise).unwrap();
+        let restored = std::fs::read_to_string(&source).unwrap();
+        assert!(restored.contains("require_lifetimes"));
+        assert!(!source.with_file_name("extra.rs").exists());
+        let kept = root.join(backup).join("src/lib.rs");
+        assert_eq!(std::fs::read_to_string(kept).unwrap(), "mangled");
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
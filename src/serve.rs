@@ -0,0 +1,285 @@
+//! `lifetimekata serve`: the book, and a page showing how the exercises are
+//! going, on a local web server, for workshops where the venue's internet
+//! can't be relied on.
+//!
+//! The book is served from `book/book`, where `mdbook build book` puts it,
+//! under `/book/`. The page at `/` shows which exercises are done, and
+//! updates itself from `/status.json`: like `watch`, the server checks an
+//! exercise whenever one of its files changes, and records how it went.
+
+use std::io;
+use std::net::TcpListener;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+
+use notify::{RecursiveMode, Watcher};
+use serde_json::json;
+
+use crate::daemon::exercise_of;
+use crate::http::{self, Request, Response};
+use crate::i18n::t;
+use crate::info::Info;
+use crate::progress::{Progress, State};
+use crate::watch::{is_edit, SETTLE};
+use crate::workshop;
+
+/// Where `mdbook build book` writes the book.
+fn book(root: &Path) -> PathBuf {
+    root.join("book").join("book")
+}
+
+pub fn serve(root: &Path, info: &Info, port: u16) -> io::Result<bool> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let (tx, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(io::Error::other)?;
+    watcher
+        .watch(&root.join("exercises"), RecursiveMode::Recursive)
+        .map_err(io::Error::other)?;
+    let port = listener.local_addr()?.port();
+    println!("{}", t!("serve.listening", port = port));
+    if !book(root).join("index.html").exists() {
+        println!("{}", t!("serve.no_book"));
+    }
+
+    // Held while progress is read or written, so that a page never sees
+    // the file half-saved.
+    let progress = Mutex::new(());
+    std::thread::scope(|scope| {
+        let progress = &progress;
+        scope.spawn(move || {
+            for event in changes.iter().flatten() {
+                if !is_edit(&event) {
+                    continue;
+                }
+                let Some(index) = event
+                    .paths
+                    .iter()
+                    .find_map(|path| exercise_of(root, info, path))
+                else {
+                    continue;
+                };
+                // Editors save in bursts; only the last change matters.
+                while changes.recv_timeout(SETTLE).is_ok() {}
+                if let Err(error) = check(root, info, index, progress) {
+                    eprintln!("error: {error}");
+                }
+            }
+        });
+        // Returning an error would leave the scope waiting on the watcher
+        // thread, so a connection which couldn't be accepted is only
+        // reported.
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    eprintln!("error: {error}");
+                    continue;
+                }
+            };
+            scope.spawn(move || {
+                http::answer(stream, |request| respond(root, info, progress, &request))
+            });
+        }
+        Ok(true)
+    })
+}
+
+/// Checks the exercise at `index`, recording how it went.
+fn check(root: &Path, info: &Info, index: usize, lock: &Mutex<()>) -> io::Result<()> {
+    let exercise = &info.exercises[index];
+    let (passed, output) = exercise.check(root)?;
+    let _lock = lock.lock().expect("nothing panics with it locked");
+    let mut progress = Progress::load(root)?;
+    progress.record(exercise, passed);
+    progress.record_errors(exercise, &String::from_utf8_lossy(&output.stderr));
+    progress.save(root)?;
+    if let Err(error) = workshop::push(root, info) {
+        eprintln!("{}", t!("workshop.unsent", error = error));
+    }
+    Ok(())
+}
+
+/// The answer to `request`.
+fn respond(root: &Path, info: &Info, lock: &Mutex<()>, request: &Request) -> Response {
+    if request.method != "GET" {
+        return Response::text("405 Method Not Allowed", "Only GET is served.");
+    }
+    let path = request.path.as_str();
+    match path {
+        "/" => Response::ok("text/html; charset=utf-8", PAGE),
+        "/status.json" => {
+            let _lock = lock.lock().expect("nothing panics with it locked");
+            match Progress::load(root) {
+                Ok(progress) => Response::ok("application/json", status(info, &progress)),
+                Err(error) => Response::text("500 Internal Server Error", &error.to_string()),
+            }
+        }
+        "/book" => Response::redirect("/book/"),
+        _ => {
+            let file = path
+                .strip_prefix("/book/")
+                .and_then(|rest| book_file(&book(root), rest));
+            match file.as_deref().map(|file| (file, std::fs::read(file))) {
+                Some((file, Ok(body))) => Response::ok(content_type(file), body),
+                _ if !book(root).exists() => Response::text("404 Not Found", &t!("serve.no_book")),
+                _ => Response::text("404 Not Found", "There's nothing here."),
+            }
+        }
+    }
+}
+
+/// Every exercise and how it's going, as JSON.
+fn status(info: &Info, progress: &Progress) -> String {
+    let exercises: Vec<_> = info
+        .exercises
+        .iter()
+        .enumerate()
+        .map(|(index, exercise)| {
+            let record = progress.get(exercise).cloned().unwrap_or_default();
+            let state = match progress.state(info, index) {
+                State::Done => "done",
+                State::Current => "current",
+                State::Locked => "locked",
+            };
+            json!({
+                "exercise": exercise.dir(),
+                "state": state,
+                "attempts": record.attempts,
+                "hints": record.hints_shown,
+            })
+        })
+        .collect();
+    json!({ "exercises": exercises }).to_string()
+}
+
+/// The file under `book` which `rest` of a URL asks for, if it doesn't
+/// climb out of it. Directories are served by their `index.html`.
+fn book_file(book: &Path, rest: &str) -> Option<PathBuf> {
+    let relative = PathBuf::from(decode(rest)?);
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    let file = book.join(relative);
+    if file.is_dir() {
+        Some(file.join("index.html"))
+    } else {
+        Some(file)
+    }
+}
+
+/// `text` with its `%xx` escapes undone.
+fn decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(after.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &after[2..];
+        } else {
+            bytes.push(byte);
+            rest = after;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("txt" | "md" | "rs" | "toml") => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The status page, which fetches `/status.json` every couple of seconds.
+const PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>LifetimeKata</title>
+<style>
+body { font-family: sans-serif; max-width: 50em; margin: 2em auto; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+.done { color: #1a7f37; }
+.current { color: #9a6700; font-weight: bold; }
+.locked { color: #777; }
+</style>
+</head>
+<body>
+<h1>LifetimeKata</h1>
+<p><a href="/book/">Read the book</a>. <span id="summary"></span></p>
+<table>
+<thead><tr><th>Exercise</th><th>Status</th><th>Attempts</th><th>Hints</th></tr></thead>
+<tbody id="exercises"></tbody>
+</table>
+<script>
+const names = { done: "done", current: "to do", locked: "locked" };
+async function refresh() {
+  try {
+    const { exercises } = await (await fetch("/status.json")).json();
+    const rows = exercises.map((exercise) => {
+      const row = document.createElement("tr");
+      row.className = exercise.state;
+      for (const cell of [exercise.exercise, names[exercise.state], exercise.attempts, exercise.hints]) {
+        row.appendChild(document.createElement("td")).textContent = cell;
+      }
+      return row;
+    });
+    document.getElementById("exercises").replaceChildren(...rows);
+    const done = exercises.filter((exercise) => exercise.state === "done").length;
+    document.getElementById("summary").textContent =
+      `${done} of ${exercises.length} exercises done.`;
+  } catch {
+    document.getElementById("summary").textContent = "The server has stopped.";
+  }
+}
+refresh();
+setInterval(refresh, 2000);
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn book_files_stay_in_the_book() {
+        let book = Path::new("/srv/book");
+        assert_eq!(
+            book_file(book, "ch01%20intro.html"),
+            Some(PathBuf::from("/srv/book/ch01 intro.html"))
+        );
+        assert_eq!(book_file(book, "../../etc/passwd"), None);
+        assert_eq!(book_file(book, "%2e%2e/secret"), None);
+        assert_eq!(book_file(book, "/etc/passwd"), None);
+        assert_eq!(book_file(book, "bad%zz"), None);
+    }
+
+    #[test]
+    fn status_lists_every_exercise() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let mut progress = Progress::default();
+        progress.record(&info.exercises[0], true);
+        let status: serde_json::Value = serde_json::from_str(&status(&info, &progress)).unwrap();
+        let exercises = status["exercises"].as_array().unwrap();
+        assert_eq!(exercises.len(), info.exercises.len());
+        assert_eq!(exercises[0]["state"], "done");
+        assert_eq!(exercises[0]["attempts"], 1);
+    }
+}
@@ -0,0 +1,310 @@
+//! `lifetimekata server`: what an instructor runs for a workshop. Students'
+//! CLIs, once they've run `lifetimekata join`, send it their progress, and
+//! it shows everyone in a session on a leaderboard which updates itself.
+//!
+//! Sessions are whatever the instructor tells students to join, and last
+//! as long as the server runs; nothing is written to disk. With
+//! `--anonymize`, students are shown as "Student 1" and so on, in the order
+//! they joined, rather than by name.
+//!
+//! - `POST /sessions/<session>/progress` takes a [`Snapshot`];
+//! - `GET /sessions/<session>` is the leaderboard page;
+//! - `GET /sessions/<session>/leaderboard.json` is what it shows.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::net::TcpListener;
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+use crate::http::{self, Request, Response};
+use crate::i18n::t;
+use crate::info::Info;
+use crate::workshop::{is_session, Attempted, Snapshot};
+
+/// The students in one session, in the order they joined.
+#[derive(Default)]
+struct Session {
+    students: Vec<Student>,
+}
+
+struct Student {
+    name: String,
+    exercises: Vec<Attempted>,
+}
+
+impl Student {
+    fn done(&self) -> usize {
+        self.exercises
+            .iter()
+            .filter(|attempted| attempted.done)
+            .count()
+    }
+
+    fn attempts(&self) -> u32 {
+        self.exercises
+            .iter()
+            .map(|attempted| attempted.attempts)
+            .sum()
+    }
+
+    /// When the student last finished an exercise.
+    fn last_completed(&self) -> u64 {
+        self.exercises
+            .iter()
+            .filter(|attempted| attempted.done)
+            .filter_map(|attempted| attempted.completed)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+pub fn server(info: &Info, port: u16, anonymize: bool) -> io::Result<bool> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let port = listener.local_addr()?.port();
+    println!("{}", t!("server.listening", port = port));
+    let sessions = Mutex::new(BTreeMap::new());
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            // A connection which couldn't be accepted, perhaps because
+            // too many are open, mustn't stop the workshop.
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(error) => {
+                    eprintln!("error: {error}");
+                    continue;
+                }
+            };
+            let sessions = &sessions;
+            scope.spawn(move || {
+                http::answer(stream, |request| {
+                    let mut sessions = sessions.lock().expect("nothing panics with it locked");
+                    respond(info, &mut sessions, anonymize, &request)
+                })
+            });
+        }
+        Ok(true)
+    })
+}
+
+fn respond(
+    info: &Info,
+    sessions: &mut BTreeMap<String, Session>,
+    anonymize: bool,
+    request: &Request,
+) -> Response {
+    let not_found = || Response::text("404 Not Found", "There's nothing here.");
+    let Some(rest) = request.path.strip_prefix("/sessions/") else {
+        return not_found();
+    };
+    let (session, what) = rest.split_once('/').unwrap_or((rest, ""));
+    if !is_session(session) {
+        return not_found();
+    }
+    match (request.method.as_str(), what) {
+        ("POST", "progress") => {
+            let Ok(snapshot) = serde_json::from_slice::<Snapshot>(&request.body) else {
+                return Response::text("400 Bad Request", "That isn't a snapshot of progress.");
+            };
+            if snapshot.student.is_empty() || snapshot.student.len() > 64 {
+                return Response::text("400 Bad Request", "Names are 1 to 64 bytes long.");
+            }
+            record(
+                info,
+                sessions.entry(session.to_string()).or_default(),
+                snapshot,
+            );
+            Response::text("200 OK", "")
+        }
+        ("GET", "") => Response::ok("text/html; charset=utf-8", PAGE),
+        ("GET", "leaderboard.json") => {
+            let leaderboard = match sessions.get(session) {
+                Some(session) => leaderboard(info, session, anonymize),
+                None => leaderboard(info, &Session::default(), anonymize),
+            };
+            Response::ok("application/json", leaderboard.to_string())
+        }
+        ("GET", _) => not_found(),
+        _ => Response::text("405 Method Not Allowed", "That can't be done here."),
+    }
+}
+
+/// Replaces what `session` knew of the student in `snapshot`, keeping only
+/// the exercises this server knows of.
+fn record(info: &Info, session: &mut Session, snapshot: Snapshot) {
+    let exercises = snapshot
+        .exercises
+        .into_iter()
+        .filter(|attempted| {
+            info.exercises
+                .iter()
+                .any(|exercise| exercise.dir() == attempted.exercise)
+        })
+        .collect();
+    match session
+        .students
+        .iter_mut()
+        .find(|student| student.name == snapshot.student)
+    {
+        Some(student) => student.exercises = exercises,
+        None => session.students.push(Student {
+            name: snapshot.student,
+            exercises,
+        }),
+    }
+}
+
+/// The students, most exercises done first, then whoever got there first,
+/// and how many have finished each exercise.
+fn leaderboard(info: &Info, session: &Session, anonymize: bool) -> Value {
+    let mut ranked: Vec<_> = session.students.iter().enumerate().collect();
+    ranked.sort_by_key(|(_, student)| {
+        (
+            std::cmp::Reverse(student.done()),
+            student.last_completed(),
+            student.attempts(),
+        )
+    });
+    let students: Vec<_> = ranked
+        .into_iter()
+        .enumerate()
+        .map(|(rank, (joined, student))| {
+            let name = if anonymize {
+                t!("server.anonymous", number = joined + 1)
+            } else {
+                student.name.clone()
+            };
+            json!({
+                "rank": rank + 1,
+                "student": name,
+                "done": student.done(),
+                "attempts": student.attempts(),
+            })
+        })
+        .collect();
+    let exercises: Vec<_> = info
+        .exercises
+        .iter()
+        .map(|exercise| {
+            let done = session
+                .students
+                .iter()
+                .filter(|student| {
+                    student
+                        .exercises
+                        .iter()
+                        .any(|attempted| attempted.exercise == exercise.dir() && attempted.done)
+                })
+                .count();
+            json!({ "exercise": exercise.dir(), "done": done })
+        })
+        .collect();
+    json!({ "students": students, "exercises": exercises })
+}
+
+/// The leaderboard page, which fetches `leaderboard.json` every few
+/// seconds.
+const PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>LifetimeKata workshop</title>
+<style>
+body { font-family: sans-serif; max-width: 50em; margin: 2em auto; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+td:first-child { width: 3em; }
+</style>
+</head>
+<body>
+<h1>LifetimeKata workshop: <span id="session"></span></h1>
+<table>
+<thead><tr><th>#</th><th>Student</th><th>Done</th><th>Attempts</th></tr></thead>
+<tbody id="students"></tbody>
+</table>
+<table>
+<thead><tr><th>Exercise</th><th>Finished by</th></tr></thead>
+<tbody id="exercises"></tbody>
+</table>
+<script>
+const session = location.pathname.replace(/\/$/, "").split("/").pop();
+document.getElementById("session").textContent = session;
+function rows(id, items, cells) {
+  document.getElementById(id).replaceChildren(...items.map((item) => {
+    const row = document.createElement("tr");
+    for (const cell of cells(item)) {
+      row.appendChild(document.createElement("td")).textContent = cell;
+    }
+    return row;
+  }));
+}
+async function refresh() {
+  try {
+    const board = await (await fetch(`/sessions/${session}/leaderboard.json`)).json();
+    rows("students", board.students, (s) => [s.rank, s.student, s.done, s.attempts]);
+    rows("exercises", board.exercises, (e) => [e.exercise, e.done]);
+  } catch {
+    document.getElementById("session").textContent = `${session} (the server has stopped)`;
+  }
+}
+refresh();
+setInterval(refresh, 3000);
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::Path;
+
+    fn snapshot(info: &Info, student: &str, done: &[(usize, u64)]) -> Snapshot {
+        Snapshot {
+            student: student.to_string(),
+            exercises: done
+                .iter()
+                .map(|&(index, completed)| Attempted {
+                    exercise: info.exercises[index].dir().to_string(),
+                    done: true,
+                    attempts: 1,
+                    completed: Some(completed),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn most_done_soonest_leads() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let mut session = Session::default();
+        record(&info, &mut session, snapshot(&info, "ana", &[(0, 10)]));
+        record(
+            &info,
+            &mut session,
+            snapshot(&info, "bo", &[(0, 5), (1, 20)]),
+        );
+        record(&info, &mut session, snapshot(&info, "cy", &[(0, 5)]));
+        // A later snapshot replaces an earlier one.
+        record(
+            &info,
+            &mut session,
+            snapshot(&info, "ana", &[(0, 10), (1, 30)]),
+        );
+
+        let board = leaderboard(&info, &session, false);
+        let names: Vec<_> = board["students"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|student| student["student"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["bo", "ana", "cy"]);
+        assert_eq!(board["exercises"][0]["done"], 3);
+        assert_eq!(board["exercises"][1]["done"], 2);
+
+        let board = leaderboard(&info, &session, true);
+        assert_eq!(board["students"][0]["student"], "Student 2");
+    }
+}
@@ -0,0 +1,141 @@
+//! `lifetimekata stats`: how long each exercise took and how often it
+//! failed, and which topics those add up to being the hardest.
+
+use std::collections::BTreeMap;
+
+use crate::i18n::t;
+use crate::info::Info;
+use crate::progress::Progress;
+use crate::report::duration;
+
+/// How the exercises with one tag have gone.
+#[derive(Debug, PartialEq)]
+struct Topic<'a> {
+    tag: &'a str,
+    /// How many of its exercises have been tried.
+    tried: usize,
+    failed_attempts: u32,
+    /// The seconds taken over those which have passed, and how many there
+    /// are.
+    seconds: u64,
+    passed: usize,
+}
+
+impl Topic<'_> {
+    fn failures_each(&self) -> f64 {
+        f64::from(self.failed_attempts) / self.tried as f64
+    }
+}
+
+pub fn stats(info: &Info, progress: &Progress) {
+    println!(
+        "{:<40} {:>6} {:>8} {:>14} {:>6}",
+        t!("stats.exercise"),
+        t!("stats.status"),
+        t!("stats.failures"),
+        t!("stats.time_to_pass"),
+        t!("stats.hints"),
+    );
+    for (index, exercise) in info.exercises.iter().enumerate() {
+        let record = progress.get(exercise).cloned().unwrap_or_default();
+        println!(
+            "{:<40} {:>6} {:>8} {:>14} {:>6}",
+            exercise.dir(),
+            progress.state(info, index).icon(),
+            record.failed_attempts,
+            record.time_to_pass().map_or(String::from("–"), duration),
+            record.hints_shown,
+        );
+    }
+
+    let topics = topics(info, progress);
+    if topics.is_empty() {
+        println!("\n{}", t!("stats.untried"));
+        return;
+    }
+    println!("\n{}", t!("stats.topics"));
+    for topic in topics {
+        let time = match topic.passed {
+            0 => t!("stats.none_passed"),
+            passed => {
+                let time = duration(topic.seconds / passed as u64);
+                t!("stats.to_pass", time = time)
+            }
+        };
+        let failures = format!("{:.1}", topic.failures_each());
+        println!(
+            "  {:<22} {}",
+            topic.tag,
+            t!(
+                "stats.topic",
+                failures = failures,
+                time = time,
+                tried = topic.tried
+            )
+        );
+    }
+}
+
+/// Every tag of an exercise which has been tried, with the most failures
+/// an exercise first.
+fn topics<'a>(info: &'a Info, progress: &Progress) -> Vec<Topic<'a>> {
+    let mut topics: BTreeMap<&str, Topic> = BTreeMap::new();
+    for exercise in &info.exercises {
+        let Some(record) = progress.get(exercise).filter(|record| record.attempts > 0) else {
+            continue;
+        };
+        for tag in &exercise.tags {
+            let topic = topics.entry(tag).or_insert(Topic {
+                tag,
+                tried: 0,
+                failed_attempts: 0,
+                seconds: 0,
+                passed: 0,
+            });
+            topic.tried += 1;
+            topic.failed_attempts += record.failed_attempts;
+            if let Some(seconds) = record.time_to_pass() {
+                topic.seconds += seconds;
+                topic.passed += 1;
+            }
+        }
+    }
+    let mut topics: Vec<Topic> = topics.into_values().collect();
+    topics.sort_by(|a, b| b.failures_each().total_cmp(&a.failures_each()));
+    topics
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn topics_are_ranked_by_failures() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let tagged = |tag: &str| {
+            info.exercises
+                .iter()
+                .find(|exercise| exercise.tags.iter().any(|t| t == tag))
+                .unwrap()
+        };
+        let mut progress = Progress::default();
+        progress.record(tagged("elision"), true);
+        for _ in 0..3 {
+            progress.record(tagged("containers"), false);
+        }
+
+        let found: Vec<_> = topics(&info, &progress)
+            .into_iter()
+            .map(|topic| (topic.tag, topic.failed_attempts, topic.passed))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("containers", 3, 0),
+                ("mutable references", 3, 0),
+                ("elision", 0, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,16 @@
+//! Colouring output, when it's going to a terminal which can show it.
+
+use std::io::IsTerminal;
+
+pub const GREEN: &str = "\x1b[32m";
+pub const RED: &str = "\x1b[31m";
+
+/// `text` in `colour`, if standard output is a terminal and `NO_COLOR`
+/// isn't set.
+pub fn paint(colour: &str, text: &str) -> String {
+    if std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        format!("{colour}{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
@@ -0,0 +1,301 @@
+//! `lifetimekata tui`: the exercises, how each is going, what the last
+//! check of one said and its hints, all on one screen. Like `watch`, an
+//! exercise is checked again whenever one of its files changes, and the
+//! checks count towards progress.
+//!
+//! Checks run on their own thread, so that the screen can still be moved
+//! around while cargo works.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::daemon::exercise_of;
+use crate::i18n::{self, t};
+use crate::info::Info;
+use crate::progress::{Progress, State};
+use crate::watch::is_edit;
+use crate::workshop;
+
+/// What's on the screen, besides what's in the progress store.
+struct App {
+    selected: usize,
+    /// What the last check of each exercise printed, once it has been
+    /// checked here.
+    output: Vec<Option<String>>,
+    /// The exercises waiting for a check, or being checked.
+    checking: BTreeSet<usize>,
+    /// How far down the output is scrolled.
+    scroll: u16,
+}
+
+/// How a check went: the exercise's index, and whether it passed and what
+/// cargo printed.
+type Checked = (usize, io::Result<(bool, String)>);
+
+pub fn tui(root: &Path, info: &Info) -> io::Result<bool> {
+    let mut progress = Progress::load(root)?;
+    let (events, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(events).map_err(io::Error::other)?;
+    watcher
+        .watch(&root.join("exercises"), RecursiveMode::Recursive)
+        .map_err(io::Error::other)?;
+    let (wanted, checks) = mpsc::channel();
+    let (send_checked, checked) = mpsc::channel();
+
+    let mut app = App {
+        selected: progress
+            .first_incomplete(info)
+            .min(info.exercises.len().saturating_sub(1)),
+        output: vec![None; info.exercises.len()],
+        checking: BTreeSet::new(),
+        scroll: 0,
+    };
+    std::thread::scope(|scope| {
+        let changed = wanted.clone();
+        scope.spawn(move || {
+            for event in changes.iter().flatten() {
+                if !is_edit(&event) {
+                    continue;
+                }
+                for path in &event.paths {
+                    if let Some(index) = exercise_of(root, info, path) {
+                        let _ = changed.send(index);
+                    }
+                }
+            }
+        });
+        scope.spawn(|| check_wanted(root, info, checks, send_checked));
+
+        let mut terminal = ratatui::init();
+        let result = run(
+            &mut terminal,
+            root,
+            info,
+            &mut app,
+            &mut progress,
+            &wanted,
+            &checked,
+        );
+        ratatui::restore();
+        // Stopping the watcher and dropping the last senders lets the
+        // threads finish, once any check that's running is done.
+        drop(watcher);
+        drop(wanted);
+        result
+    })
+}
+
+/// Checks each exercise asked for by `wanted`, sending how it went back.
+fn check_wanted(root: &Path, info: &Info, wanted: Receiver<usize>, checked: Sender<Checked>) {
+    while let Ok(first) = wanted.recv() {
+        let mut indices = BTreeSet::from([first]);
+        indices.extend(wanted.try_iter());
+        for index in indices {
+            let result = info.exercises[index].check(root).map(|(passed, output)| {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let mut text = crate::clean(&stdout);
+                text.push_str(&crate::clean(&stderr));
+                (passed, text)
+            });
+            if checked.send((index, result)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Draws the screen and handles keys until the learner quits.
+fn run(
+    terminal: &mut DefaultTerminal,
+    root: &Path,
+    info: &Info,
+    app: &mut App,
+    progress: &mut Progress,
+    wanted: &Sender<usize>,
+    checked: &Receiver<Checked>,
+) -> io::Result<bool> {
+    let check = |app: &mut App, index: usize| {
+        app.checking.insert(index);
+        let _ = wanted.send(index);
+    };
+    let selected = app.selected;
+    check(app, selected);
+    loop {
+        for (index, result) in checked.try_iter() {
+            app.checking.remove(&index);
+            let (passed, output) = result?;
+            let exercise = &info.exercises[index];
+            progress.record(exercise, passed);
+            progress.record_errors(exercise, &output);
+            progress.save(root)?;
+            // A workshop server that can't be reached mustn't spoil the
+            // screen; the next command will say so.
+            let _ = workshop::push(root, info);
+            app.output[index] = Some(output);
+        }
+        terminal.draw(|frame| draw(frame, info, progress, app))?;
+
+        if !event::poll(Duration::from_millis(100))? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let last = info.exercises.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
+            KeyCode::Up | KeyCode::Char('k') => {
+                app.selected = app.selected.saturating_sub(1);
+                app.scroll = 0;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                app.selected = (app.selected + 1).min(last);
+                app.scroll = 0;
+            }
+            KeyCode::PageUp => app.scroll = app.scroll.saturating_sub(10),
+            KeyCode::PageDown => app.scroll = app.scroll.saturating_add(10),
+            KeyCode::Enter | KeyCode::Char('r') => {
+                let selected = app.selected;
+                check(app, selected);
+            }
+            KeyCode::Char('h') => {
+                progress.unlock_hints(&info.exercises[app.selected], true);
+                progress.save(root)?;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, info: &Info, progress: &Progress, app: &App) {
+    let [main, help] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let [list, right] =
+        Layout::horizontal([Constraint::Length(44), Constraint::Min(0)]).areas(main);
+    let [output, hints] =
+        Layout::vertical([Constraint::Percentage(70), Constraint::Percentage(30)]).areas(right);
+
+    let items: Vec<_> = info
+        .exercises
+        .iter()
+        .enumerate()
+        .map(|(index, exercise)| {
+            let icon = if app.checking.contains(&index) {
+                "⏳"
+            } else {
+                progress.state(info, index).icon()
+            };
+            let style = match progress.state(info, index) {
+                State::Done => Style::new().fg(Color::Green),
+                State::Current => Style::new(),
+                State::Locked => Style::new().fg(Color::DarkGray),
+            };
+            ListItem::new(format!("{icon} {}", exercise.dir())).style(style)
+        })
+        .collect();
+    let mut state = ListState::default().with_selected(Some(app.selected));
+    frame.render_stateful_widget(
+        List::new(items)
+            .block(Block::bordered().title(t!("tui.exercises")))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+        list,
+        &mut state,
+    );
+
+    let exercise = &info.exercises[app.selected];
+    let text = if app.checking.contains(&app.selected) {
+        t!("tui.checking")
+    } else {
+        match &app.output[app.selected] {
+            Some(output) if output.trim().is_empty() => t!("run.passed", exercise = exercise),
+            Some(output) => output.clone(),
+            None => format!("{}\n\n{}", i18n::description(exercise), t!("tui.unchecked")),
+        }
+    };
+    let title = t!("tui.output", path = exercise.path.display());
+    frame.render_widget(
+        Paragraph::new(text)
+            .block(Block::bordered().title(title))
+            .wrap(Wrap { trim: false })
+            .scroll((app.scroll, 0)),
+        output,
+    );
+
+    let all = i18n::hints(exercise);
+    let shown = progress
+        .get(exercise)
+        .map_or(0, |record| record.hints_shown)
+        .min(all.len());
+    let lines: Vec<Line> = if all.is_empty() {
+        vec![Line::from(t!("tui.no_hints"))]
+    } else {
+        all[..shown]
+            .iter()
+            .enumerate()
+            .map(|(number, hint)| Line::from(format!("{}. {hint}", number + 1)))
+            .collect()
+    };
+    let title = t!("tui.hints", shown = shown, total = all.len());
+    frame.render_widget(
+        Paragraph::new(lines)
+            .block(Block::bordered().title(title))
+            .wrap(Wrap { trim: true }),
+        hints,
+    );
+
+    frame.render_widget(
+        Paragraph::new(t!("tui.keys")).style(Style::new().fg(Color::DarkGray)),
+        help,
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn the_screen_shows_the_exercises_and_hints() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let mut progress = Progress::default();
+        progress.unlock_hints(&info.exercises[0], true);
+        let app = App {
+            selected: 0,
+            output: vec![None; info.exercises.len()],
+            checking: BTreeSet::from([1]),
+            scroll: 0,
+        };
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal
+            .draw(|frame| draw(frame, &info, &progress, &app))
+            .unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for exercise in &info.exercises {
+            assert!(screen.contains(exercise.dir()), "{}", exercise.dir());
+        }
+        assert!(screen.contains(&format!("Hints (1 of {})", info.exercises[0].hints.len())));
+        assert!(screen.contains("⏳"));
+    }
+}
@@ -0,0 +1,258 @@
+//! `lifetimekata variant`: gives each learner a slightly different copy of
+//! the exercises, so that a classmate's answers can't just be pasted in.
+//!
+//! `info.toml` lists words in each exercise which can be swapped for others,
+//! like a function's name or a word in its sample text. A seed picks one of
+//! the choices for each, and the word is replaced everywhere in the
+//! exercise's crate, tests included, so the variant is as consistent as the
+//! original. `grade` then checks that each exercise is the variant for the
+//! learner's seed.
+
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::exercise::Exercise;
+use crate::i18n::t;
+use crate::info::Info;
+use crate::reset;
+
+/// A word in an exercise which a variant may swap for another.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Variable {
+    /// The word as the exercise has it; a whole identifier, or a whole word
+    /// in a string or comment.
+    pub text: String,
+    /// What it may become. `text` should be one of them, so that some
+    /// learners get the exercise as it's written.
+    pub choices: Vec<String>,
+}
+
+/// The learner's seed, kept in `.lifetimekata/variant.json`.
+#[derive(Debug, Serialize, Deserialize)]
+struct Saved {
+    seed: u64,
+}
+
+fn path(root: &Path) -> PathBuf {
+    root.join(".lifetimekata").join("variant.json")
+}
+
+/// The seed the exercises were made from, if they've been given a variant.
+pub fn seed(root: &Path) -> io::Result<Option<u64>> {
+    match std::fs::read_to_string(path(root)) {
+        Ok(json) => {
+            let saved: Saved = serde_json::from_str(&json).map_err(io::Error::other)?;
+            Ok(Some(saved.seed))
+        }
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// The choice for `variable` in `exercise`'s variant for `seed`. The same
+/// seed always makes the same choices.
+pub fn choose<'v>(seed: u64, exercise: &Exercise, variable: &'v Variable) -> &'v str {
+    // FNV-1a, then SplitMix64's finaliser to spread the bits; unlike std's
+    // hashers, these won't change from one Rust release to the next.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let bytes = seed.to_le_bytes();
+    let parts = [
+        &bytes[..],
+        exercise.name.as_bytes(),
+        variable.text.as_bytes(),
+    ];
+    for byte in parts.into_iter().flat_map(|part| part.iter().chain([&0])) {
+        hash = (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^= hash >> 31;
+    &variable.choices[(hash % variable.choices.len() as u64) as usize]
+}
+
+/// `source` with each word which is a variable's `text` replaced, all at
+/// once so that one replacement can't be replaced again.
+fn substitute(source: &str, replacements: &[(&str, &str)]) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+    while !rest.is_empty() {
+        let length = match rest.find(|c: char| !is_word(c)) {
+            Some(0) => rest.chars().next().map_or(1, char::len_utf8),
+            Some(length) => length,
+            None => rest.len(),
+        };
+        let (word, after) = rest.split_at(length);
+        match replacements.iter().find(|(from, _)| *from == word) {
+            Some((_, to)) => out.push_str(to),
+            None => out.push_str(word),
+        }
+        rest = after;
+    }
+    out
+}
+
+/// Every `.rs` file in the crate at `dir`, leaving out what it has built.
+fn sources(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if !path.ends_with("target") {
+                found.extend(sources(&path)?);
+            }
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            found.push(path);
+        }
+    }
+    Ok(found)
+}
+
+/// Turns `exercise`, as it was just reset, into the variant for `seed`.
+pub fn apply(root: &Path, exercise: &Exercise, seed: u64) -> io::Result<()> {
+    let replacements: Vec<(&str, &str)> = exercise
+        .variants
+        .iter()
+        .map(|variable| (variable.text.as_str(), choose(seed, exercise, variable)))
+        .collect();
+    for file in sources(&root.join(&exercise.path))? {
+        let source = std::fs::read_to_string(&file)?;
+        std::fs::write(&file, substitute(&source, &replacements))?;
+    }
+    Ok(())
+}
+
+/// Whether `exercise`, as the learner has it, is the variant for `seed`:
+/// each variable's choice is there, and none of its other choices are.
+pub fn matches(root: &Path, exercise: &Exercise, seed: u64) -> io::Result<bool> {
+    let mut words = std::collections::BTreeSet::new();
+    for file in sources(&root.join(&exercise.path))? {
+        let source = std::fs::read_to_string(file)?;
+        words.extend(
+            source
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .map(String::from),
+        );
+    }
+    Ok(exercise.variants.iter().all(|variable| {
+        let chosen = choose(seed, exercise, variable);
+        words.contains(chosen)
+            && variable
+                .choices
+                .iter()
+                .all(|choice| choice == chosen || !words.contains(choice))
+    }))
+}
+
+/// Saves `seed`, picking one if it isn't given, and makes every exercise
+/// which has variables into its variant, after asking unless `yes` is set.
+pub fn variant(root: &Path, info: &Info, seed: Option<u64>, yes: bool) -> io::Result<bool> {
+    let varied: Vec<&Exercise> = info
+        .exercises
+        .iter()
+        .filter(|exercise| !exercise.variants.is_empty())
+        .collect();
+    if !yes {
+        print!("{} [y/N] ", t!("variant.ask", count = varied.len()));
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().lock().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("{}", t!("variant.declined"));
+            return Ok(false);
+        }
+    }
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos() as u64)
+    });
+    let file = path(root);
+    std::fs::create_dir_all(file.parent().expect("the path is in a directory"))?;
+    let json = serde_json::to_string_pretty(&Saved { seed }).map_err(io::Error::other)?;
+    std::fs::write(file, json + "\n")?;
+
+    for exercise in varied {
+        reset::restore(root, exercise)?;
+        apply(root, exercise, seed)?;
+    }
+    println!("{}", t!("variant.done", seed = seed));
+    Ok(true)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn whole_words_are_replaced_at_once() {
+        assert_eq!(
+            substitute(
+                "use ex02::split; // split, not last_split or splitted",
+                &[("split", "cut"), ("cut", "split")]
+            ),
+            "use ex02::cut; // cut, not last_split or splitted"
+        );
+        assert_eq!(
+            substitute("ünïcode split", &[("split", "cut")]),
+            "ünïcode cut"
+        );
+    }
+
+    #[test]
+    fn seeds_always_make_the_same_variant() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let exercise = info
+            .exercises
+            .iter()
+            .find(|exercise| !exercise.variants.is_empty())
+            .unwrap();
+        let variable = &exercise.variants[0];
+        let picks: Vec<&str> = (0..64)
+            .map(|seed| choose(seed, exercise, variable))
+            .collect();
+        assert_eq!(
+            picks,
+            (0..64)
+                .map(|seed| choose(seed, exercise, variable))
+                .collect::<Vec<_>>()
+        );
+        for choice in &variable.choices {
+            assert!(picks.contains(&choice.as_str()), "{choice} is never picked");
+        }
+    }
+
+    #[test]
+    fn variants_are_told_apart() {
+        let info = Info::load(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let exercise = info
+            .exercises
+            .iter()
+            .find(|exercise| !exercise.variants.is_empty())
+            .unwrap();
+        let root =
+            std::env::temp_dir().join(format!("lifetimekata-variant-{}", std::process::id()));
+        let source = root.join(exercise.source(&root));
+        std::fs::create_dir_all(source.parent().unwrap()).unwrap();
+        let original =
+            std::fs::read_to_string(exercise.source(Path::new(env!("CARGO_MANIFEST_DIR"))))
+                .unwrap();
+
+        // Two seeds which choose differently, for the first variable.
+        let variable = &exercise.variants[0];
+        let (first, second) = (0..)
+            .map(|seed| (seed, seed + 1))
+            .find(|&(a, b)| choose(a, exercise, variable) != choose(b, exercise, variable))
+            .unwrap();
+        std::fs::write(&source, &original).unwrap();
+        apply(&root, exercise, first).unwrap();
+        assert!(matches(&root, exercise, first).unwrap());
+        assert!(!matches(&root, exercise, second).unwrap());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,481 @@
+//! `lifetimekata visualize`: shows where each borrow in a small snippet
+//! starts, where it's last used, and the lines in between, which is all
+//! that a borrow lasts for since non-lexical lifetimes.
+//!
+//! This is a teaching aid rather than a borrow checker. It follows
+//! `let name = &place;` and `let name = &mut place;`, and counts a borrow
+//! as alive until the last line which mentions `name`. Shadowing, loops,
+//! and references returned from calls aren't understood.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use proc_macro2::{Span, TokenStream, TokenTree};
+use syn::visit::{self, Visit};
+use syn::{Block, Expr, ExprAssign, ExprPath, ExprReference, ImplItemFn, ItemFn, Local, Pat};
+
+use crate::i18n::t;
+
+/// One `let` which borrows a place.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Borrow {
+    /// The variable which holds the reference.
+    pub name: String,
+    /// The variable which is borrowed, or whose field or element is.
+    pub of: String,
+    pub mutable: bool,
+    /// The line where the reference is made, counting from 1.
+    pub start: usize,
+    /// The line where it's last used; `start`, if it's never used.
+    pub end: usize,
+    /// The lines which use `of` in a way the borrow doesn't allow while
+    /// it's alive: at all, if it's mutable, or to change it, if it isn't.
+    pub conflicts: Vec<usize>,
+}
+
+/// Every borrow in `source`'s functions, in the order they're made.
+pub fn borrows(source: &str) -> syn::Result<Vec<Borrow>> {
+    let file = syn::parse_file(source)?;
+    let mut functions = Functions::default();
+    functions.visit_file(&file);
+
+    let mut borrows = Vec::new();
+    for body in functions.bodies {
+        let mut uses = Uses::default();
+        uses.visit_block(body);
+        for (name, of, mutable, start) in uses.lets {
+            let end = uses
+                .uses
+                .iter()
+                .filter(|used| used.name == name && used.line > start)
+                .map(|used| used.line)
+                .max()
+                .unwrap_or(start);
+            let mut conflicts: Vec<usize> = uses
+                .uses
+                .iter()
+                .filter(|used| used.name == of && used.line > start && used.line <= end)
+                .filter(|used| mutable || used.mutable)
+                .map(|used| used.line)
+                .collect();
+            conflicts.dedup();
+            borrows.push(Borrow {
+                name,
+                of,
+                mutable,
+                start,
+                end,
+                conflicts,
+            });
+        }
+    }
+    Ok(borrows)
+}
+
+/// The body of every function, including methods.
+#[derive(Default)]
+struct Functions<'ast> {
+    bodies: Vec<&'ast Block>,
+}
+
+impl<'ast> Visit<'ast> for Functions<'ast> {
+    fn visit_item_fn(&mut self, function: &'ast ItemFn) {
+        self.bodies.push(&function.block);
+        visit::visit_item_fn(self, function);
+    }
+
+    fn visit_impl_item_fn(&mut self, function: &'ast ImplItemFn) {
+        self.bodies.push(&function.block);
+        visit::visit_impl_item_fn(self, function);
+    }
+}
+
+/// A variable being mentioned on a line.
+struct Use {
+    name: String,
+    line: usize,
+    /// Whether it's borrowed mutably or assigned to.
+    mutable: bool,
+}
+
+/// The borrowing `let`s in one function body, and every use of a variable.
+#[derive(Default)]
+struct Uses {
+    /// The name bound, what it borrows, whether mutably, and the line.
+    lets: Vec<(String, String, bool, usize)>,
+    uses: Vec<Use>,
+}
+
+impl Uses {
+    fn add(&mut self, name: String, span: Span, mutable: bool) {
+        self.uses.push(Use {
+            name,
+            line: span.start().line,
+            mutable,
+        });
+    }
+
+    /// Macros aren't parsed, so any identifier in one counts as a use, as
+    /// does `{name}` in a format string.
+    fn add_tokens(&mut self, tokens: TokenStream) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) => self.add(ident.to_string(), ident.span(), false),
+                TokenTree::Group(group) => self.add_tokens(group.stream()),
+                TokenTree::Literal(literal) => {
+                    let text = literal.to_string();
+                    for name in inline_arguments(&text) {
+                        self.add(name.to_string(), literal.span(), false);
+                    }
+                }
+                TokenTree::Punct(_) => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Uses {
+    fn visit_local(&mut self, local: &'ast Local) {
+        let pattern = match &local.pat {
+            Pat::Type(typed) => &*typed.pat,
+            pattern => pattern,
+        };
+        if let (Pat::Ident(binding), Some(init)) = (pattern, &local.init) {
+            if let Expr::Reference(reference) = unparen(&init.expr) {
+                if let Some(of) = place(&reference.expr) {
+                    self.lets.push((
+                        binding.ident.to_string(),
+                        of,
+                        reference.mutability.is_some(),
+                        binding.ident.span().start().line,
+                    ));
+                }
+            }
+        }
+        visit::visit_local(self, local);
+    }
+
+    fn visit_expr_reference(&mut self, reference: &'ast ExprReference) {
+        if reference.mutability.is_some() {
+            if let Some(of) = place(&reference.expr) {
+                self.add(of, reference.and_token.span, true);
+            }
+        }
+        visit::visit_expr_reference(self, reference);
+    }
+
+    fn visit_expr_assign(&mut self, assign: &'ast ExprAssign) {
+        if let Some(of) = place(&assign.left) {
+            self.add(of, assign.eq_token.span, true);
+        }
+        visit::visit_expr_assign(self, assign);
+    }
+
+    fn visit_expr_path(&mut self, path: &'ast ExprPath) {
+        if let Some(ident) = path.path.get_ident() {
+            self.add(ident.to_string(), ident.span(), false);
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        self.add_tokens(mac.tokens.clone());
+    }
+
+    // A function inside a body is a body of its own.
+    fn visit_item_fn(&mut self, _: &'ast ItemFn) {}
+}
+
+fn unparen(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(paren) => unparen(&paren.expr),
+        expr => expr,
+    }
+}
+
+/// The variable at the root of a place like `x`, `x.field` or `x[0]`.
+fn place(expr: &Expr) -> Option<String> {
+    match unparen(expr) {
+        Expr::Path(path) => path.path.get_ident().map(ToString::to_string),
+        Expr::Field(field) => place(&field.base),
+        Expr::Index(index) => place(&index.expr),
+        _ => None,
+    }
+}
+
+/// The variables named inline in a format string, like `x` in `"{x:?}"`.
+fn inline_arguments(literal: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = literal;
+    while let Some(open) = rest.find('{') {
+        rest = &rest[open + 1..];
+        if rest.starts_with('{') {
+            rest = &rest[1..];
+            continue;
+        }
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let name = &rest[..length];
+        if name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && rest[length..].starts_with(['}', ':'])
+        {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// The letter which labels the borrow at `index`.
+fn label(index: usize) -> char {
+    (b'A' + (index % 26) as u8) as char
+}
+
+/// What the borrow's column shows on `line`.
+fn mark(borrow: &Borrow, line: usize) -> char {
+    match line {
+        _ if line == borrow.start && line == borrow.end => '●',
+        _ if line == borrow.start => '┬',
+        _ if line == borrow.end => '┴',
+        _ if borrow.start < line && line < borrow.end => '│',
+        _ => ' ',
+    }
+}
+
+/// The borrow, like `&mut v`.
+fn describe(borrow: &Borrow) -> String {
+    let mutable = if borrow.mutable { "mut " } else { "" };
+    format!("{} = &{mutable}{}", borrow.name, borrow.of)
+}
+
+/// What conflicts with `borrow` on `line`.
+fn conflict(borrow: &Borrow, line: usize) -> String {
+    if borrow.mutable {
+        format!(
+            "line {line} uses `{}` while `{}` still borrows it mutably",
+            borrow.of, borrow.name
+        )
+    } else {
+        format!(
+            "line {line} changes `{}` while `{}` still borrows it",
+            borrow.of, borrow.name
+        )
+    }
+}
+
+/// `source` with a column beside it for each borrow, and a key underneath.
+pub fn ascii(source: &str, borrows: &[Borrow]) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let width = lines.len().to_string().len();
+    let mut out = String::new();
+    let labels: String = (0..borrows.len())
+        .map(|i| format!("{} ", label(i)))
+        .collect();
+    let _ = writeln!(out, "{labels}");
+    for (index, code) in lines.iter().enumerate() {
+        let line = index + 1;
+        let gutter: String = borrows
+            .iter()
+            .map(|borrow| format!("{} ", mark(borrow, line)))
+            .collect();
+        let conflicted = borrows
+            .iter()
+            .any(|borrow| borrow.conflicts.contains(&line));
+        let flag = if conflicted { "✗" } else { " " };
+        let _ = writeln!(out, "{gutter}{flag}{line:>width$} │ {code}");
+    }
+    out.push('\n');
+    for (index, borrow) in borrows.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "{}  {:<20} made on line {}, last used on line {}",
+            label(index),
+            describe(borrow),
+            borrow.start,
+            borrow.end
+        );
+        for &line in &borrow.conflicts {
+            let _ = writeln!(out, "   ✗ {}", conflict(borrow, line));
+        }
+    }
+    out
+}
+
+/// The same as [`ascii`], as a page of HTML with each borrow in a colour.
+pub fn html(source: &str, borrows: &[Borrow], title: &str) -> String {
+    const COLOURS: [&str; 6] = [
+        "#4e79a7", "#f28e2b", "#59a14f", "#b07aa1", "#76b7b2", "#edc948",
+    ];
+    let escape = crate::report::escape;
+    let mut rows = String::new();
+    for (index, code) in source.lines().enumerate() {
+        let line = index + 1;
+        rows.push_str("<tr>");
+        for (column, borrow) in borrows.iter().enumerate() {
+            let colour = COLOURS[column % COLOURS.len()];
+            match mark(borrow, line) {
+                ' ' => rows.push_str("<td></td>"),
+                mark => {
+                    let _ = write!(
+                        rows,
+                        "<td class=\"borrow\" style=\"background: {colour}\" \
+                         title=\"{}\">{mark}</td>",
+                        escape(&describe(borrow))
+                    );
+                }
+            }
+        }
+        let conflicted = borrows
+            .iter()
+            .any(|borrow| borrow.conflicts.contains(&line));
+        let class = if conflicted {
+            " class=\"conflict\""
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            rows,
+            "<td class=\"line\">{line}</td><td{class}><code>{}</code></td></tr>",
+            escape(code)
+        );
+    }
+
+    let mut key = String::new();
+    for (index, borrow) in borrows.iter().enumerate() {
+        let colour = COLOURS[index % COLOURS.len()];
+        let _ = write!(
+            key,
+            "<li><span class=\"swatch\" style=\"background: {colour}\"></span>\
+             <code>{}</code>: made on line {}, last used on line {}",
+            escape(&describe(borrow)),
+            borrow.start,
+            borrow.end
+        );
+        for &line in &borrow.conflicts {
+            let _ = write!(key, "<br>✗ {}", escape(&conflict(borrow, line)));
+        }
+        key.push_str("</li>\n");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Borrows in {title}</title>
+<style>
+body {{ font-family: sans-serif; max-width: 60em; margin: 2em auto; }}
+table {{ border-collapse: collapse; }}
+td {{ padding: 0 0.4em; white-space: pre; font-family: monospace; }}
+td.borrow {{ color: white; text-align: center; }}
+td.line {{ color: #777; text-align: right; }}
+td.conflict {{ background: #ffd7d5; }}
+.swatch {{ display: inline-block; width: 1em; height: 1em; margin-right: 0.5em; }}
+</style>
+</head>
+<body>
+<h1>Borrows in {title}</h1>
+<table>
+{rows}</table>
+<ul>
+{key}</ul>
+</body>
+</html>
+"#,
+        title = escape(title),
+    )
+}
+
+/// Prints the borrows in the file at `path`, or writes them to `html` as
+/// a page.
+pub fn visualize(path: &Path, html_output: Option<&Path>) -> std::io::Result<()> {
+    let source = std::fs::read_to_string(path)?;
+    let found = borrows(&source).map_err(|error| {
+        let line = error.span().start().line;
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{}:{line}: {error}", path.display()),
+        )
+    })?;
+    if found.is_empty() {
+        println!("{}", t!("visualize.nothing", path = path.display()));
+        return Ok(());
+    }
+    match html_output {
+        Some(output) => {
+            let title = path.display().to_string();
+            std::fs::write(output, html(&source, &found, &title))?;
+            println!("{}", t!("visualize.written", path = output.display()));
+        }
+        None => print!("{}", ascii(&source, &found)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SNIPPET: &str = "\
+fn main() {
+    let mut v = vec![1, 2];
+    let first = &v[0];
+    println!(\"{first}\");
+    let last = &mut v;
+    v.push(3);
+    last.push(4);
+}
+";
+
+    #[test]
+    fn borrows_last_until_their_last_use() {
+        assert_eq!(
+            borrows(SNIPPET).unwrap(),
+            [
+                Borrow {
+                    name: "first".into(),
+                    of: "v".into(),
+                    mutable: false,
+                    start: 3,
+                    end: 4,
+                    conflicts: vec![],
+                },
+                Borrow {
+                    name: "last".into(),
+                    of: "v".into(),
+                    mutable: true,
+                    start: 5,
+                    end: 7,
+                    conflicts: vec![6],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn borrows_are_drawn_beside_the_code() {
+        let drawn = ascii(SNIPPET, &borrows(SNIPPET).unwrap());
+        assert_eq!(
+            drawn,
+            "\
+A B 
+     1 │ fn main() {
+     2 │     let mut v = vec![1, 2];
+┬    3 │     let first = &v[0];
+┴    4 │     println!(\"{first}\");
+  ┬  5 │     let last = &mut v;
+  │ ✗6 │     v.push(3);
+  ┴  7 │     last.push(4);
+     8 │ }
+
+A  first = &v           made on line 3, last used on line 4
+B  last = &mut v        made on line 5, last used on line 7
+   ✗ line 6 uses `v` while `last` still borrows it mutably
+"
+        );
+    }
+
+    #[test]
+    fn format_strings_name_variables() {
+        assert_eq!(inline_arguments("\"{a} {{b}} {c:?} {0} {}\""), ["a", "c"]);
+    }
+}
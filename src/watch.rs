@@ -0,0 +1,145 @@
+//! `lifetimekata watch`: checks the current exercise again whenever its
+//! source changes, and shows how it went in a few lines.
+
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+
+use crate::badges;
+use crate::i18n::t;
+use crate::info::Info;
+use crate::progress::Progress;
+use crate::style::{paint, GREEN, RED};
+use crate::workshop;
+
+/// How long to wait for more changes after the first, since editors often
+/// save a file in several steps.
+pub const SETTLE: Duration = Duration::from_millis(200);
+
+pub fn watch(root: &Path, info: &Info) -> std::io::Result<bool> {
+    let (sender, changes) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(std::io::Error::other)?;
+    for exercise in &info.exercises {
+        watcher
+            .watch(
+                &root.join(&exercise.path).join("src"),
+                RecursiveMode::Recursive,
+            )
+            .map_err(std::io::Error::other)?;
+    }
+
+    let mut progress = Progress::load(root)?;
+    let mut current = progress.first_incomplete(info);
+    loop {
+        current = check_from(root, info, current, &mut progress)?;
+        badges::announce(root, info)?;
+        if let Err(error) = workshop::push(root, info) {
+            eprintln!("{}", t!("workshop.unsent", error = error));
+        }
+        progress = Progress::load(root)?;
+        if current == info.exercises.len() {
+            println!("{}", paint(GREEN, &t!("watch.all")));
+            return Ok(true);
+        }
+        let path = info.exercises[current].path.display();
+        println!("{}", t!("watch.watching", path = path));
+
+        // Wait for a change to a source file, then for things to settle.
+        loop {
+            let event = changes.recv().map_err(std::io::Error::other)?;
+            if event.is_ok_and(|event| is_edit(&event)) {
+                break;
+            }
+        }
+        while changes.recv_timeout(SETTLE).is_ok() {}
+    }
+}
+
+/// Checks the exercises from `start` on, stopping at the first that fails
+/// and returning its index.
+fn check_from(
+    root: &Path,
+    info: &Info,
+    start: usize,
+    progress: &mut Progress,
+) -> std::io::Result<usize> {
+    let count = info.exercises.len();
+    for (index, exercise) in info.exercises.iter().enumerate().skip(start) {
+        let (passed, output) = exercise.check(root)?;
+        progress.record(exercise, passed);
+        progress.record_errors(exercise, &String::from_utf8_lossy(&output.stderr));
+        progress.save(root)?;
+        let progress = format!("[{}/{count}]", index + 1);
+        if passed {
+            println!("{progress} {} {exercise}", paint(GREEN, "✓"));
+            continue;
+        }
+        println!("{progress} {} {exercise}", paint(RED, "✗"));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if let Some(error) = first_error(&stderr).or_else(|| first_error(&stdout)) {
+            println!("{error}");
+        }
+        return Ok(index);
+    }
+    Ok(count)
+}
+
+/// The first error in cargo's output, up to the blank line which ends it;
+/// failing tests are reported as a panic rather than an error.
+fn first_error(output: &str) -> Option<String> {
+    let lines: Vec<&str> = output.lines().collect();
+    let start = lines.iter().position(|line| {
+        line.starts_with("error") && !line.starts_with("error: could not compile")
+            || line.contains("panicked at")
+    })?;
+    let end = lines[start..]
+        .iter()
+        .position(|line| line.trim().is_empty())
+        .map_or(lines.len(), |length| start + length);
+    Some(lines[start..end].join("\n"))
+}
+
+/// Whether `event` changed a source file; building the exercise reads them,
+/// which shouldn't start another build.
+pub fn is_edit(event: &notify::Event) -> bool {
+    let kind = event.kind;
+    (kind.is_create() || kind.is_modify() || kind.is_remove())
+        && event
+            .paths
+            .iter()
+            .any(|path| path.extension().is_some_and(|extension| extension == "rs"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn the_first_error_is_kept_whole() {
+        let output = "\
+   Compiling ex02 v0.1.0
+error: [LK0001] `&i32` here needs a name such as `&'a i32`
+  --> src/lib.rs:14:25
+   |
+14 | pub fn identity(number: &i32) -> &i32 {
+   |                         ^^^^
+
+error: [LK0002] `&i32` here needs a name such as `&'a i32`
+
+error: could not compile `ex02` (lib) due to 2 previous errors
+";
+        assert_eq!(
+            first_error(output).unwrap(),
+            "\
+error: [LK0001] `&i32` here needs a name such as `&'a i32`
+  --> src/lib.rs:14:25
+   |
+14 | pub fn identity(number: &i32) -> &i32 {
+   |                         ^^^^"
+        );
+        assert_eq!(first_error("running 1 test\ntest ok ... ok\n"), None);
+    }
+}
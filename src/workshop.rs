@@ -0,0 +1,170 @@
+//! Taking part in a workshop: `lifetimekata join` saves where the
+//! instructor's `lifetimekata server` is, and from then on the CLI sends it
+//! a snapshot of your progress whenever that changes. `lifetimekata leave`
+//! stops it.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::http;
+use crate::i18n::t;
+use crate::info::Info;
+use crate::progress::Progress;
+
+/// Which workshop to send progress to, kept in
+/// `.lifetimekata/workshop.json`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Workshop {
+    /// The server, like `http://192.168.1.10:9000`.
+    pub url: String,
+    /// The session the instructor gave out.
+    pub session: String,
+    pub student: String,
+}
+
+/// What's sent: each exercise which has been checked, and how it went.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub student: String,
+    pub exercises: Vec<Attempted>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attempted {
+    /// The exercise's directory, like `02_lifetimes_explained`.
+    pub exercise: String,
+    pub done: bool,
+    pub attempts: u32,
+    /// When it first passed, in seconds since the Unix epoch.
+    pub completed: Option<u64>,
+}
+
+fn path(root: &Path) -> PathBuf {
+    root.join(".lifetimekata").join("workshop.json")
+}
+
+/// Whether `session` can be part of a URL as it is.
+pub fn is_session(session: &str) -> bool {
+    (1..=64).contains(&session.len())
+        && session
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The workshop joined under `root`, if one has been.
+pub fn load(root: &Path) -> io::Result<Option<Workshop>> {
+    match std::fs::read_to_string(path(root)) {
+        Ok(json) => serde_json::from_str(&json)
+            .map(Some)
+            .map_err(io::Error::other),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Joins the workshop at `url`, sending what's been done so far.
+pub fn join(
+    root: &Path,
+    info: &Info,
+    url: &str,
+    session: &str,
+    student: Option<String>,
+) -> io::Result<bool> {
+    if !is_session(session) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            t!("workshop.bad_session", session = session),
+        ));
+    }
+    let student = student
+        .or_else(|| std::env::var("USER").ok())
+        .unwrap_or_else(|| t!("report.student"));
+    let workshop = Workshop {
+        url: url.trim_end_matches('/').to_string(),
+        session: session.to_string(),
+        student,
+    };
+    send(&workshop, info, &Progress::load(root)?)?;
+    let file = path(root);
+    std::fs::create_dir_all(file.parent().expect("the path is in a directory"))?;
+    let json = serde_json::to_string_pretty(&workshop).map_err(io::Error::other)?;
+    std::fs::write(file, json + "\n")?;
+    println!(
+        "{}",
+        t!(
+            "workshop.joined",
+            session = workshop.session,
+            url = workshop.url
+        )
+    );
+    Ok(true)
+}
+
+pub fn leave(root: &Path) -> io::Result<bool> {
+    match std::fs::remove_file(path(root)) {
+        Ok(()) => println!("{}", t!("workshop.left")),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            println!("{}", t!("workshop.not_joined"))
+        }
+        Err(error) => return Err(error),
+    }
+    Ok(true)
+}
+
+/// Sends the progress saved under `root` to the workshop, if one was
+/// joined.
+pub fn push(root: &Path, info: &Info) -> io::Result<()> {
+    match load(root)? {
+        Some(workshop) => send(&workshop, info, &Progress::load(root)?),
+        None => Ok(()),
+    }
+}
+
+fn send(workshop: &Workshop, info: &Info, progress: &Progress) -> io::Result<()> {
+    let json = serde_json::to_string(&snapshot(&workshop.student, info, progress))
+        .map_err(io::Error::other)?;
+    let path = format!("/sessions/{}/progress", workshop.session);
+    match http::post(&workshop.url, &path, &json)? {
+        200..=299 => Ok(()),
+        status => Err(io::Error::other(t!(
+            "workshop.refused",
+            url = workshop.url,
+            status = status
+        ))),
+    }
+}
+
+fn snapshot(student: &str, info: &Info, progress: &Progress) -> Snapshot {
+    let exercises = info
+        .exercises
+        .iter()
+        .filter_map(|exercise| {
+            let record = progress.get(exercise)?;
+            Some(Attempted {
+                exercise: exercise.dir().to_string(),
+                done: record.done,
+                attempts: record.attempts,
+                completed: record.completed,
+            })
+        })
+        .collect();
+    Snapshot {
+        student: student.to_string(),
+        exercises,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sessions_fit_in_a_url() {
+        assert!(is_session("tuesday-class_2"));
+        assert!(!is_session(""));
+        assert!(!is_session("../admin"));
+        assert!(!is_session("a b"));
+    }
+}
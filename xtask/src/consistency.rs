@@ -0,0 +1,101 @@
+//! `cargo xtask check-consistency`: every exercise crate has to fail
+//! `cargo test` as it's shipped, whether it doesn't build or its tests fail,
+//! and every solution crate has to pass. Otherwise a skeleton has been
+//! shipped already solved, or a solution has rotted.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// What `cargo test` should make of a crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Expect {
+    Fail,
+    Pass,
+}
+
+pub fn check(root: &Path) -> Result<(), String> {
+    let mut crates = Vec::new();
+    let mut chapters: Vec<_> = fs::read_dir(root.join("exercises"))
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    chapters.sort();
+    for chapter in chapters {
+        for (dir, expect) in [("exercise", Expect::Fail), ("solutions", Expect::Pass)] {
+            let manifest = chapter.join(dir).join("Cargo.toml");
+            if manifest.is_file() {
+                let text = fs::read_to_string(&manifest).map_err(|e| e.to_string())?;
+                let package = package_name(&text)
+                    .ok_or_else(|| format!("{} has no package name", manifest.display()))?;
+                crates.push((package.to_string(), expect));
+            }
+        }
+    }
+
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut wrong = Vec::new();
+    for (package, expect) in &crates {
+        let passed = Command::new(&cargo)
+            .current_dir(root)
+            .args(["test", "--quiet", "--package", package])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map_err(|e| e.to_string())?
+            .success();
+        let ok = passed == (*expect == Expect::Pass);
+        println!(
+            "{} {package}: {}",
+            if ok { "✓" } else { "✗" },
+            if passed { "passes" } else { "fails" }
+        );
+        if !ok {
+            wrong.push(match expect {
+                Expect::Fail => format!("{package} passes as it's shipped; is it already solved?"),
+                Expect::Pass => {
+                    format!("{package} doesn't pass; `cargo test -p {package}` says why")
+                }
+            });
+        }
+    }
+    if wrong.is_empty() {
+        println!("All {} crates are as they should be.", crates.len());
+        Ok(())
+    } else {
+        Err(wrong.join("\n"))
+    }
+}
+
+/// The `name` in a manifest's `[package]` table.
+fn package_name(manifest: &str) -> Option<&str> {
+    manifest
+        .lines()
+        .skip_while(|line| line.trim() != "[package]")
+        .skip(1)
+        .take_while(|line| !line.trim_start().starts_with('['))
+        .find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "name").then(|| value.trim().trim_matches('"'))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn the_package_name_is_read() {
+        let manifest = "\
+[package]
+version = \"0.1.0\"
+name = \"ex02\"
+
+[dependencies]
+name = \"not this\"
+";
+        assert_eq!(package_name(manifest), Some("ex02"));
+        assert_eq!(package_name("[dependencies]\nname = \"x\"\n"), None);
+    }
+}
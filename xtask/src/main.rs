@@ -0,0 +1,42 @@
+//! `cargo xtask`: chores for working on LifetimeKata itself, as opposed to
+//! doing the exercises.
+//!
+//! - `cargo xtask new-exercise <nn>_<name>` creates an exercise's chapter,
+//!   exercise and solution crates from `templates/`, and lists it in
+//!   `info.toml`. The workspace picks the crates up by itself.
+//! - `cargo xtask check-consistency` checks that no exercise passes its
+//!   tests as it's shipped, and that every solution does.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+mod consistency;
+mod new_exercise;
+
+const USAGE: &str = "\
+usage: cargo xtask new-exercise <nn>_<name>
+       cargo xtask check-consistency";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["new-exercise", dir] => new_exercise::new_exercise(&root(), dir),
+        ["check-consistency"] => consistency::check(&root()),
+        _ => Err(String::from(USAGE)),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The repository, which is the directory above this crate's.
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask is inside the repository")
+        .to_path_buf()
+}
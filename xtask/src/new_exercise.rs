@@ -0,0 +1,174 @@
+//! `cargo xtask new-exercise`: creates an exercise from `templates/`.
+
+use std::fs;
+use std::path::Path;
+
+/// An exercise's directory name, split up.
+#[derive(Debug, PartialEq, Eq)]
+struct NewExercise<'a> {
+    /// Its two digits, like `06`.
+    number: &'a str,
+    /// The rest, like `lifetimes_on_impls`.
+    name: &'a str,
+}
+
+impl<'a> NewExercise<'a> {
+    fn parse(dir: &'a str) -> Result<NewExercise<'a>, String> {
+        let invalid = || format!("`{dir}` should look like `06_lifetimes_on_impls`");
+        let (number, name) = dir.split_once('_').ok_or_else(invalid)?;
+        let is_name = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_';
+        if number.len() != 2
+            || !number.chars().all(|c| c.is_ascii_digit())
+            || name.is_empty()
+            || !name.chars().all(is_name)
+        {
+            return Err(invalid());
+        }
+        Ok(NewExercise { number, name })
+    }
+
+    fn dir(&self) -> String {
+        format!("{}_{}", self.number, self.name)
+    }
+
+    /// The chapter title, like `Lifetimes on impls`.
+    fn title(&self) -> String {
+        let mut title = self.name.replace('_', " ");
+        if let Some(first) = title.get_mut(..1) {
+            first.make_ascii_uppercase();
+        }
+        title
+    }
+
+    /// Fills in a template's `{{placeholders}}`.
+    fn fill(&self, template: &str, package: &str) -> String {
+        template
+            .replace("{{package}}", package)
+            .replace("{{name}}", self.name)
+            .replace("{{title}}", &self.title())
+    }
+}
+
+pub fn new_exercise(root: &Path, dir: &str) -> Result<(), String> {
+    let exercise = NewExercise::parse(dir)?;
+    let chapter = root.join("exercises").join(exercise.dir());
+    for crate_dir in ["exercise", "solutions"] {
+        if chapter.join(crate_dir).exists() {
+            return Err(format!(
+                "{} already exists",
+                chapter.join(crate_dir).display()
+            ));
+        }
+    }
+    let (exercise_package, solution_package) = (
+        format!("ex{}", exercise.number),
+        format!("soln{}", exercise.number),
+    );
+
+    let files = [
+        ("README.md", include_str!("../templates/README.md"), ""),
+        (
+            "exercise/Cargo.toml",
+            include_str!("../templates/exercise.Cargo.toml"),
+            &exercise_package,
+        ),
+        (
+            "exercise/src/lib.rs",
+            include_str!("../templates/exercise.lib.rs"),
+            &exercise_package,
+        ),
+        (
+            "solutions/Cargo.toml",
+            include_str!("../templates/exercise.Cargo.toml"),
+            &solution_package,
+        ),
+        (
+            "solutions/src/lib.rs",
+            include_str!("../templates/solution.lib.rs"),
+            &solution_package,
+        ),
+    ];
+    for (path, template, package) in files {
+        let path = chapter.join(path);
+        // A chapter may already have its README, written before its exercise.
+        if path.exists() {
+            continue;
+        }
+        fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        fs::write(&path, exercise.fill(template, package)).map_err(|e| e.to_string())?;
+        println!(
+            "created {}",
+            path.strip_prefix(root).unwrap_or(&path).display()
+        );
+    }
+
+    let info = root.join("info.toml");
+    let text = fs::read_to_string(&info).map_err(|e| e.to_string())?;
+    fs::write(&info, register(&text, &exercise)).map_err(|e| e.to_string())?;
+    println!("listed {} in info.toml", exercise.name);
+    Ok(())
+}
+
+/// `info.toml`'s text with an entry for `exercise` added, before the first
+/// exercise with a higher number so that they stay in order.
+fn register(info: &str, exercise: &NewExercise) -> String {
+    let entry = format!(
+        "[[exercises]]\n\
+         name = \"{name}\"\n\
+         description = \"TODO: say what to do, in a sentence.\"\n\
+         path = \"exercises/{dir}/exercise\"\n\
+         mode = \"test\"\n\
+         tags = []\n\
+         hints = [\n    \"TODO: a first nudge in the right direction.\",\n]\n\n",
+        name = exercise.name,
+        dir = exercise.dir(),
+    );
+    let later = info.match_indices("[[exercises]]").find(|&(at, _)| {
+        let number = info[at..]
+            .split_once("exercises/")
+            .and_then(|(_, rest)| rest.get(..2));
+        number.is_some_and(|number| number > exercise.number)
+    });
+    match later {
+        Some((at, _)) => format!("{}{entry}{}", &info[..at], &info[at..]),
+        None => format!("{}\n\n{}", info.trim_end(), entry.trim_end()) + "\n",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names_need_a_number() {
+        let parsed = NewExercise::parse("06_lifetimes_on_impls").unwrap();
+        assert_eq!(parsed.number, "06");
+        assert_eq!(parsed.name, "lifetimes_on_impls");
+        assert_eq!(parsed.title(), "Lifetimes on impls");
+        assert!(NewExercise::parse("lifetimes_on_impls").is_err());
+        assert!(NewExercise::parse("6_impls").is_err());
+        assert!(NewExercise::parse("06_Impls").is_err());
+    }
+
+    #[test]
+    fn exercises_are_listed_in_order() {
+        let info = "\
+# The exercises.
+
+[[exercises]]
+name = \"five\"
+path = \"exercises/05_five/exercise\"
+
+[[exercises]]
+name = \"seven\"
+path = \"exercises/07_seven/exercise\"
+";
+        let six = register(info, &NewExercise::parse("06_six").unwrap());
+        let positions = ["05_five", "06_six", "07_seven"].map(|dir| six.find(dir).unwrap());
+        assert!(positions[0] < positions[1] && positions[1] < positions[2]);
+
+        let nine = register(info, &NewExercise::parse("09_nine").unwrap());
+        assert!(nine.find("07_seven").unwrap() < nine.find("09_nine").unwrap());
+        assert!(nine.ends_with("]\n"));
+    }
+}
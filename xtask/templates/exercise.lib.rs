@@ -0,0 +1,13 @@
+use require_lifetimes::require_lifetimes;
+
+/// TODO: replace this with the exercise. It returns the first word of `text`.
+///
+/// ```rust
+/// use {{package}}::first_word;
+///
+/// assert_eq!(first_word("lifetimes are fun"), "lifetimes");
+/// ```
+#[require_lifetimes(!)]
+pub fn first_word(text: &str) -> &str {
+    text.split(' ').next().unwrap_or(text)
+}
@@ -0,0 +1,13 @@
+use require_lifetimes::require_lifetimes;
+
+/// TODO: replace this with the solution.
+///
+/// ```rust
+/// use {{package}}::first_word;
+///
+/// assert_eq!(first_word("lifetimes are fun"), "lifetimes");
+/// ```
+#[require_lifetimes(!)]
+pub fn first_word<'text>(text: &'text str) -> &'text str {
+    text.split(' ').next().unwrap_or(text)
+}